@@ -13,10 +13,10 @@ static PLOT_CONFIG_LOG: LazyLock<PlotConfiguration> =
 
 fn bench_new(c: &mut Criterion) {
     let mut group = c.benchmark_group("new");
-    group.bench_function("hashmap", |b| b.iter(|| HashMap::<String, String>::new()));
-    group.bench_function("indexmap", |b| b.iter(|| IndexMap::<String, String>::new()));
+    group.bench_function("hashmap", |b| b.iter(HashMap::<String, String>::new));
+    group.bench_function("indexmap", |b| b.iter(IndexMap::<String, String>::new));
     group.bench_function("hashslabmap", |b| {
-        b.iter(|| HashSlabMap::<String, String>::new())
+        b.iter(HashSlabMap::<String, String>::new)
     });
 }
 
@@ -257,7 +257,7 @@ fn bench_lookup_index_single(c: &mut Criterion) {
 
 fn bench_lookup_index_random(c: &mut Criterion) {
     let mut group = c.benchmark_group("lookup_index_random");
-    let indices: Vec<usize> = (&*SHUFFLED_KEYS).iter().map(|n| *n as usize).collect();
+    let indices: Vec<usize> = SHUFFLED_KEYS.iter().map(|n| *n as usize).collect();
 
     group.bench_function("indexmap", |b| {
         let map = &*SHUFFLED_INDEXMAP;
@@ -295,8 +295,8 @@ fn bench_lookup_key_size(c: &mut Criterion) {
         list.shuffle(&mut small_rng());
         let lookup = list
             .iter()
-            .cloned()
             .take(cap as usize / 10)
+            .cloned()
             .collect::<Vec<_>>();
         list.shuffle(&mut small_rng());
 
@@ -51,7 +51,7 @@ fn bench_remove_key_few(c: &mut Criterion) {
     let mut group = c.benchmark_group("remove_key_few");
 
     let few_len = 50;
-    let mut keys = (&*SHUFFLED_KEYS).clone();
+    let mut keys = (*SHUFFLED_KEYS).clone();
     let mut rng = small_rng();
     keys.shuffle(&mut rng);
     keys.truncate(few_len);
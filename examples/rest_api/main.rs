@@ -1,4 +1,4 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use axum::{
     extract::{Path, State},
@@ -7,7 +7,7 @@ use axum::{
     routing::get,
     Json, Router,
 };
-use hashslab::HashSlabMap;
+use hashslab::ConcurrentHashSlabMap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,33 +29,27 @@ struct WithId<T> {
     data: T,
 }
 
-type Endpoints = Arc<Mutex<HashSlabMap<String, EndpointState>>>;
+type Endpoints = Arc<ConcurrentHashSlabMap<String, EndpointState>>;
 
 async fn list_endpoints(State(endpoints): State<Endpoints>) -> Json<Vec<WithId<Endpoint>>> {
-    let endpoints = {
-        let lock = endpoints.lock().unwrap();
-        lock.iter_full()
-            .map(|(id, url, state)| WithId {
-                id,
-                data: Endpoint {
-                    url: url.clone(),
-                    state: *state,
-                },
-            })
-            .collect::<Vec<_>>()
-    };
-    Json(endpoints)
+    let mut list = Vec::new();
+    endpoints.for_each(|id, url, state| {
+        list.push(WithId {
+            id,
+            data: Endpoint {
+                url: url.clone(),
+                state: *state,
+            },
+        })
+    });
+    Json(list)
 }
 
 async fn add_endpoint(
     State(endpoints): State<Endpoints>,
     Json(endpoint): Json<Endpoint>,
 ) -> Json<WithId<Endpoint>> {
-    let id = {
-        let mut lock = endpoints.lock().unwrap();
-        let (idx, _) = lock.insert_full(endpoint.url.clone(), endpoint.state);
-        idx
-    };
+    let (id, _) = endpoints.insert_full(endpoint.url.clone(), endpoint.state);
     Json(WithId { id, data: endpoint })
 }
 
@@ -63,13 +57,10 @@ async fn get_endpoint(
     Path(id): Path<usize>,
     State(endpoints): State<Endpoints>,
 ) -> impl IntoResponse {
-    let endpoint = {
-        let lock = endpoints.lock().unwrap();
-        lock.get_index(id).map(|(url, &state)| Endpoint {
-            url: url.clone(),
-            state,
-        })
-    };
+    let endpoint = endpoints.get_index(id).map(|entry| Endpoint {
+        url: entry.key().clone(),
+        state: *entry.value(),
+    });
     if let Some(endpoint) = endpoint {
         (StatusCode::OK, Ok(Json(endpoint)))
     } else {
@@ -81,13 +72,9 @@ async fn remove_endpoint(
     Path(id): Path<usize>,
     State(endpoints): State<Endpoints>,
 ) -> impl IntoResponse {
-    let endpoint = {
-        let mut lock = endpoints.lock().unwrap();
-        lock.remove_index(id).map(|(url, state)| Endpoint {
-            url: url.clone(),
-            state,
-        })
-    };
+    let endpoint = endpoints
+        .remove_index(id)
+        .map(|(url, state)| Endpoint { url, state });
     if let Some(endpoint) = endpoint {
         (StatusCode::OK, Ok(Json(endpoint)))
     } else {
@@ -97,7 +84,7 @@ async fn remove_endpoint(
 
 #[tokio::main]
 async fn main() {
-    let endpoints = Arc::new(Mutex::new(HashSlabMap::new()));
+    let endpoints = Arc::new(ConcurrentHashSlabMap::new());
 
     let app = Router::new()
         .route("/endpoints", get(list_endpoints).post(add_endpoint))
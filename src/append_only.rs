@@ -0,0 +1,519 @@
+//! An append-only hash map, readable without locks during inserts
+use std::{
+    boxed::Box,
+    fmt,
+    hash::{BuildHasher, Hash, RandomState},
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ptr,
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    vec::Vec,
+};
+
+use hashbrown::Equivalent;
+
+#[cfg(test)]
+mod tests;
+
+// Entries live in buckets of growing size: bucket `b` holds
+// `1 << (b + FIRST_BUCKET_BITS)` entries, so the buckets together cover the
+// whole `usize` index range and an entry never moves once written.
+const FIRST_BUCKET_BITS: u32 = 5;
+const BUCKETS: usize = (usize::BITS - FIRST_BUCKET_BITS) as usize;
+
+// Bucket and offset inside the bucket of an entry index.
+fn location(index: usize) -> (usize, usize) {
+    let x = index + (1 << FIRST_BUCKET_BITS);
+    let bucket = (usize::BITS - 1 - x.leading_zeros() - FIRST_BUCKET_BITS) as usize;
+    (bucket, x - bucket_len(bucket))
+}
+
+fn bucket_len(bucket: usize) -> usize {
+    1 << (bucket as u32 + FIRST_BUCKET_BITS)
+}
+
+struct Entry<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+// Open addressing index from hashes to entry indices. A slot holds
+// `index + 1`, zero marks an empty slot. Slots are only written by the
+// writer, and the table is replaced by a larger one instead of growing in
+// place, so readers can probe it without locking.
+struct Table {
+    slots: Box<[AtomicUsize]>,
+}
+
+impl Table {
+    fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity).map(|_| AtomicUsize::new(0)).collect(),
+        }
+    }
+
+    fn mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    fn insert(&self, hash: u64, index: usize) {
+        let mut pos = hash as usize & self.mask();
+        while self.slots[pos].load(Ordering::Relaxed) != 0 {
+            pos = (pos + 1) & self.mask();
+        }
+        self.slots[pos].store(index + 1, Ordering::Release);
+    }
+}
+
+/// An append-only hash map with stable indices, which can be read from many
+/// threads while another thread inserts.
+///
+/// Entries can be inserted through a shared reference and are never moved or
+/// removed, so references returned by [`get`][Self::get] and
+/// [`get_index`][Self::get_index] stay valid while new entries are added.
+/// Entries are stored in chunks of growing size that are never reallocated,
+/// and an atomic open addressing table maps keys to indices.
+///
+/// Lookups never lock and never wait for writers. Inserts are serialized by
+/// an internal mutex; an insert of a key which is already present returns
+/// the existing index without locking.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::AppendOnlyHashSlabMap;
+/// use std::thread;
+///
+/// let registry = AppendOnlyHashSlabMap::new();
+/// let (first, _) = registry.insert_full("first", 1);
+/// let value = registry.get_index(first).unwrap().1;
+///
+/// thread::scope(|s| {
+///     s.spawn(|| {
+///         for i in 0..1000 {
+///             registry.insert_full(format!("{i}").leak(), i);
+///         }
+///     });
+///     s.spawn(|| {
+///         // Readers don't block the writer, and see a consistent entry
+///         // for every index below `len()`
+///         let len = registry.len();
+///         assert!(registry.get_index(len - 1).is_some());
+///     });
+/// });
+///
+/// // The reference is still valid after many inserts
+/// assert_eq!(*value, 1);
+/// assert_eq!(registry.len(), 1001);
+/// assert_eq!(registry.get("500"), Some(&500));
+/// ```
+pub struct AppendOnlyHashSlabMap<K, V, S = RandomState> {
+    buckets: [AtomicPtr<Entry<K, V>>; BUCKETS],
+    // Number of initialized entries, entries below it are immutable
+    len: AtomicUsize,
+    table: AtomicPtr<Table>,
+    // Serializes writers and keeps replaced tables alive until drop, as
+    // readers may still be probing them
+    writer: Mutex<Vec<*mut Table>>,
+    builder: S,
+    // Owns entries, but isn't `Send` or `Sync` by itself: the impls below
+    // add the bounds required to move keys and values between threads
+    marker: PhantomData<(Entry<K, V>, *const ())>,
+}
+
+// SAFETY: the map owns its entries, so it can be sent if they can.
+unsafe impl<K: Send, V: Send, S: Send> Send for AppendOnlyHashSlabMap<K, V, S> {}
+
+// SAFETY: shared references allow reading entries from several threads, and
+// inserting entries which are later dropped by the owner thread.
+unsafe impl<K, V, S> Sync for AppendOnlyHashSlabMap<K, V, S>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    S: Sync,
+{
+}
+
+impl<K, V> AppendOnlyHashSlabMap<K, V> {
+    /// Creates an empty `AppendOnlyHashSlabMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, V, S> AppendOnlyHashSlabMap<K, V, S> {
+    /// Creates an empty `AppendOnlyHashSlabMap` which will use the given hash builder.
+    pub fn with_hasher(builder: S) -> Self {
+        Self {
+            buckets: core::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+            len: AtomicUsize::new(0),
+            table: AtomicPtr::new(ptr::null_mut()),
+            writer: Mutex::new(Vec::new()),
+            builder,
+            marker: PhantomData,
+        }
+    }
+
+    /// Return a reference to the map's [`BuildHasher`].
+    pub fn hasher(&self) -> &S {
+        &self.builder
+    }
+
+    /// Return the number of entries in the map.
+    ///
+    /// All indices below the returned value are valid.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Returns true if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get a key-value pair by index.
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        (index < self.len()).then(|| {
+            // SAFETY: the entry is initialized, since the index is below `len`
+            let entry = unsafe { self.entry(index) };
+            (&entry.key, &entry.value)
+        })
+    }
+
+    /// Get a key and a mutable reference to the value by index.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)> {
+        (index < *self.len.get_mut()).then(|| {
+            let (bucket, offset) = location(index);
+            let bucket = *self.buckets[bucket].get_mut();
+            // SAFETY: the entry is initialized, since the index is below
+            // `len`, and the map is borrowed mutably
+            let entry = unsafe { &mut *bucket.add(offset) };
+            (&entry.key, &mut entry.value)
+        })
+    }
+
+    /// Returns `true` if the map contains an entry at the given index.
+    pub fn contains_index(&self, index: usize) -> bool {
+        index < self.len()
+    }
+
+    /// An iterator visiting all index-key-value triples in index order.
+    ///
+    /// Entries inserted after the iterator was created are not visited.
+    pub fn iter_full(&self) -> IterFull<'_, K, V, S> {
+        IterFull::new(self, self.len())
+    }
+
+    // SAFETY: `index` must be below a value of `len` loaded with `Acquire`
+    // ordering, or found in the table.
+    unsafe fn entry(&self, index: usize) -> &Entry<K, V> {
+        let (bucket, offset) = location(index);
+        let bucket = self.buckets[bucket].load(Ordering::Acquire);
+        &*bucket.add(offset)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<*mut Table>> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<K, V, S> AppendOnlyHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair and returns its index, together with `true`
+    /// if the key was newly inserted.
+    ///
+    /// If the key is already present, the map is not modified and `value` is
+    /// dropped.
+    pub fn insert_full(&self, key: K, value: V) -> (usize, bool) {
+        let hash = self.builder.hash_one(&key);
+        if let Some(index) = self.find(hash, &key) {
+            return (index, false);
+        }
+        let mut retired = self.lock();
+        // Another writer may have inserted the key in the meantime
+        match self.find(hash, &key) {
+            Some(index) => (index, false),
+            None => (self.push(&mut retired, hash, key, value), true),
+        }
+    }
+
+    /// Returns the index and value of `key`, inserting the value returned by
+    /// `f` if the key is absent.
+    pub fn get_or_insert_with<F>(&self, key: K, f: F) -> (usize, &V)
+    where
+        F: FnOnce() -> V,
+    {
+        let hash = self.builder.hash_one(&key);
+        let index = match self.find(hash, &key) {
+            Some(index) => index,
+            None => {
+                let mut retired = self.lock();
+                match self.find(hash, &key) {
+                    Some(index) => index,
+                    None => self.push(&mut retired, hash, key, f()),
+                }
+            }
+        };
+        // SAFETY: the index was found in the table or just pushed
+        (index, &unsafe { self.entry(index) }.value)
+    }
+
+    /// Return a reference to the value of `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Return the index, key and value of `key`.
+    pub fn get_full<Q>(&self, key: &Q) -> Option<(usize, &K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find(self.builder.hash_one(key), key)?;
+        // SAFETY: the index was found in the table
+        let entry = unsafe { self.entry(index) };
+        Some((index, &entry.key, &entry.value))
+    }
+
+    /// Return the index of `key`.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find(self.builder.hash_one(key), key)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_index_of(key).is_some()
+    }
+
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        let table = self.table.load(Ordering::Acquire);
+        if table.is_null() {
+            return None;
+        }
+        // SAFETY: tables are only freed on drop
+        let table = unsafe { &*table };
+        let mut pos = hash as usize & table.mask();
+        loop {
+            match table.slots[pos].load(Ordering::Acquire) {
+                0 => return None,
+                slot => {
+                    // SAFETY: slots are published after their entries
+                    let entry = unsafe { self.entry(slot - 1) };
+                    if entry.hash == hash && key.equivalent(&entry.key) {
+                        return Some(slot - 1);
+                    }
+                }
+            }
+            pos = (pos + 1) & table.mask();
+        }
+    }
+
+    // Append an entry and publish it in the table. Must be called with the
+    // writer lock held.
+    fn push(&self, retired: &mut Vec<*mut Table>, hash: u64, key: K, value: V) -> usize {
+        let index = self.len.load(Ordering::Relaxed);
+        let (bucket, offset) = location(index);
+        let mut chunk = self.buckets[bucket].load(Ordering::Relaxed);
+        if chunk.is_null() {
+            let boxed: Box<[MaybeUninit<Entry<K, V>>]> = (0..bucket_len(bucket))
+                .map(|_| MaybeUninit::uninit())
+                .collect();
+            chunk = Box::into_raw(boxed).cast();
+            self.buckets[bucket].store(chunk, Ordering::Release);
+        }
+        // SAFETY: the slot is past `len`, so no reader accesses it, and the
+        // writer lock is held
+        unsafe { chunk.add(offset).write(Entry { hash, key, value }) };
+
+        // Keep the load factor of the table at most 1/2, so probing always
+        // ends on an empty slot
+        let table = self.table.load(Ordering::Relaxed);
+        // SAFETY: the table is only replaced by the writer
+        let table = match unsafe { table.as_ref() } {
+            Some(current) if (index + 1) * 2 <= current.slots.len() => current,
+            _ => {
+                let grown = Table::new(((index + 1) * 2).next_power_of_two().max(8));
+                for i in 0..index {
+                    // SAFETY: entries below `index` are initialized
+                    grown.insert(unsafe { self.entry(i) }.hash, i);
+                }
+                let grown = Box::into_raw(Box::new(grown));
+                let old = self.table.swap(grown, Ordering::AcqRel);
+                if !old.is_null() {
+                    retired.push(old);
+                }
+                // SAFETY: just created from a box
+                unsafe { &*grown }
+            }
+        };
+        table.insert(hash, index);
+        // Publish the entry last, so every index below `len` is in the table
+        self.len.store(index + 1, Ordering::Release);
+        index
+    }
+}
+
+impl<K, V, S> Drop for AppendOnlyHashSlabMap<K, V, S> {
+    fn drop(&mut self) {
+        let len = *self.len.get_mut();
+        for (bucket, chunk) in self.buckets.iter_mut().enumerate() {
+            let chunk = *chunk.get_mut();
+            if chunk.is_null() {
+                break;
+            }
+            let start = bucket_len(bucket) - bucket_len(0);
+            let initialized = len.saturating_sub(start).min(bucket_len(bucket));
+            // SAFETY: the first `initialized` entries of the chunk were
+            // written, and the chunk was allocated as a boxed slice
+            unsafe {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(chunk, initialized));
+                drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                    chunk.cast::<MaybeUninit<Entry<K, V>>>(),
+                    bucket_len(bucket),
+                )));
+            }
+        }
+        let retired = self
+            .writer
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        let table = *self.table.get_mut();
+        for table in retired.drain(..).chain((!table.is_null()).then_some(table)) {
+            // SAFETY: tables are created by `Box::into_raw`
+            drop(unsafe { Box::from_raw(table) });
+        }
+    }
+}
+
+impl<K, V, S> Clone for AppendOnlyHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    fn clone(&self) -> Self {
+        let map = Self::with_hasher(self.builder.clone());
+        for (_, key, value) in self.iter_full() {
+            map.insert_full(key.clone(), value.clone());
+        }
+        map
+    }
+}
+
+impl<K, V, S> fmt::Debug for AppendOnlyHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter_full().map(|(_, k, v)| (k, v)))
+            .finish()
+    }
+}
+
+impl<K, V, S> Default for AppendOnlyHashSlabMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for AppendOnlyHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Inserts all new key-value pairs from the iterable, keeping the values
+    /// of keys which are already present.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert_full(key, value);
+        });
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for AppendOnlyHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iterable: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iterable);
+        map
+    }
+}
+
+/// An iterator over the entries of an [`AppendOnlyHashSlabMap`].
+///
+/// This `struct` is created by the [`AppendOnlyHashSlabMap::iter_full`] method.
+/// See its documentation for more.
+pub struct IterFull<'a, K, V, S> {
+    map: &'a AppendOnlyHashSlabMap<K, V, S>,
+    index: usize,
+    len: usize,
+}
+
+impl<'a, K, V, S> IterFull<'a, K, V, S> {
+    fn new(map: &'a AppendOnlyHashSlabMap<K, V, S>, len: usize) -> Self {
+        Self { map, index: 0, len }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K, V, S> Clone for IterFull<'_, K, V, S> {
+    fn clone(&self) -> Self {
+        IterFull { ..*self }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for IterFull<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K, V, S> Iterator for IterFull<'a, K, V, S> {
+    type Item = (usize, &'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.index < self.len).then(|| {
+            let index = self.index;
+            self.index += 1;
+            // SAFETY: `len` was loaded with `Acquire` ordering
+            let entry = unsafe { self.map.entry(index) };
+            (index, &entry.key, &entry.value)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len - self.index;
+        (len, Some(len))
+    }
+}
+
+impl<K, V, S> ExactSizeIterator for IterFull<'_, K, V, S> {}
+
+impl<K, V, S> FusedIterator for IterFull<'_, K, V, S> {}
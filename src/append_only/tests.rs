@@ -0,0 +1,94 @@
+use super::*;
+use std::{
+    rc::Rc,
+    string::{String, ToString},
+    thread,
+};
+
+#[test]
+fn locations() {
+    assert_eq!(location(0), (0, 0));
+    assert_eq!(location(31), (0, 31));
+    assert_eq!(location(32), (1, 0));
+    assert_eq!(location(95), (1, 63));
+    assert_eq!(location(96), (2, 0));
+    assert_eq!(
+        location(usize::MAX - 32),
+        (BUCKETS - 1, bucket_len(BUCKETS - 1) - 1)
+    );
+}
+
+#[test]
+fn insert_and_get() {
+    let map = AppendOnlyHashSlabMap::new();
+    assert_eq!(map.get("a"), None);
+    assert_eq!(map.insert_full(String::from("a"), 1), (0, true));
+    assert_eq!(map.insert_full(String::from("b"), 2), (1, true));
+    assert_eq!(map.insert_full(String::from("a"), 3), (0, false));
+    assert_eq!(map.get("a"), Some(&1));
+    assert_eq!(map.get_full("b"), Some((1, &String::from("b"), &2)));
+    assert_eq!(map.get_or_insert_with(String::from("c"), || 4), (2, &4));
+    assert_eq!(map.get_or_insert_with(String::from("c"), || 5), (2, &4));
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get_index(3), None);
+    assert!(!map.contains_index(3));
+    assert!(map.contains_key("c"));
+}
+
+#[test]
+fn references_stay_valid() {
+    let map = AppendOnlyHashSlabMap::new();
+    map.insert_full(0, String::from("zero"));
+    let zero = map.get(&0).unwrap();
+    for i in 1..10_000usize {
+        assert_eq!(map.insert_full(i, i.to_string()), (i, true));
+    }
+    assert_eq!(zero, "zero");
+    assert!(map
+        .iter_full()
+        .all(|(i, &k, v)| i == k && (i == 0 || *v == i.to_string())));
+    assert_eq!(map.iter_full().len(), 10_000);
+}
+
+#[test]
+fn drops_entries() {
+    let counter = Rc::new(());
+    let mut map = AppendOnlyHashSlabMap::new();
+    for i in 0..100 {
+        map.insert_full(i, Rc::clone(&counter));
+    }
+    map.get_index_mut(5).unwrap().1.clone_from(&counter);
+    assert_eq!(Rc::strong_count(&counter), 101);
+    drop(map);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[test]
+fn concurrent_readers_and_writers() {
+    let map = AppendOnlyHashSlabMap::new();
+    thread::scope(|s| {
+        for t in 0..4 {
+            let map = &map;
+            s.spawn(move || {
+                for i in 0..2000 {
+                    let (index, _) = map.insert_full(i, i * 2);
+                    assert_eq!(map.get_index(index), Some((&i, &(i * 2))));
+                    if t == 0 {
+                        assert_eq!(map.get(&(i / 2)), Some(&(i / 2 * 2)));
+                    }
+                }
+            });
+        }
+        s.spawn(|| {
+            for _ in 0..100 {
+                let len = map.len();
+                for (index, key, value) in map.iter_full() {
+                    assert!(index < len);
+                    assert_eq!(*value, key * 2);
+                    assert_eq!(map.get_index_of(key), Some(index));
+                }
+            }
+        });
+    });
+    assert_eq!(map.len(), 2000);
+}
@@ -0,0 +1,61 @@
+//! `Arbitrary` for [`HashSlabMap`] and [`HashSlabSet`], for fuzzing
+//!
+//! Every generated entry is followed by a byte deciding whether up to two
+//! existing entries are removed, so the generated maps have holes in their
+//! indices, like maps that went through interleaved inserts and removals.
+use core::hash::{BuildHasher, Hash};
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{HashSlabMap, HashSlabSet};
+
+fn generate<K, V, S>(
+    entries: impl Iterator<Item = Result<(K, V, u8)>>,
+) -> Result<HashSlabMap<K, V, S>>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    let mut map = HashSlabMap::with_hasher(S::default());
+    for entry in entries {
+        let (key, value, churn) = entry?;
+        map.churn(key, value, churn);
+    }
+    Ok(map)
+}
+
+impl<'a, K, V, S> Arbitrary<'a> for HashSlabMap<K, V, S>
+where
+    K: Arbitrary<'a> + Hash + Eq,
+    V: Arbitrary<'a>,
+    S: BuildHasher + Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        generate(u.arbitrary_iter()?)
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        generate(u.arbitrary_take_rest_iter()?)
+    }
+}
+
+impl<'a, T, S> Arbitrary<'a> for HashSlabSet<T, S>
+where
+    T: Arbitrary<'a> + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let entries = u.arbitrary_iter::<(T, u8)>()?;
+        let map = generate(entries.map(|entry| entry.map(|(value, churn)| (value, (), churn))))?;
+        Ok(Self { map })
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        let entries = u.arbitrary_take_rest_iter::<(T, u8)>()?;
+        let map = generate(entries.map(|entry| entry.map(|(value, churn)| (value, (), churn))))?;
+        Ok(Self { map })
+    }
+}
+
+#[cfg(test)]
+mod tests;
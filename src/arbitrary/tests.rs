@@ -0,0 +1,50 @@
+use alloc::vec::Vec;
+
+use ::arbitrary::{Arbitrary, Unstructured};
+use fnv::FnvBuildHasher;
+
+use crate::{HashSlabMap, HashSlabSet};
+
+type Map = HashSlabMap<u16, u16, FnvBuildHasher>;
+
+// Encodes 64 distinct entries, every fourth one followed by two removals
+fn bytes() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for i in 0..64u16 {
+        bytes.push(1);
+        bytes.extend_from_slice(&i.to_le_bytes());
+        bytes.extend_from_slice(&i.to_le_bytes());
+        bytes.push(if i % 4 == 3 { 0xc0 | i as u8 } else { 0 });
+    }
+    bytes
+}
+
+#[test]
+fn leaves_index_holes() {
+    let map = Map::arbitrary_take_rest(Unstructured::new(&bytes())).unwrap();
+    let end = map
+        .iter_full()
+        .map(|(index, _, _)| index + 1)
+        .max()
+        .unwrap();
+    assert_eq!(map.len(), 32);
+    assert!(end > map.len());
+}
+
+#[test]
+fn same_bytes_same_layout() {
+    let bytes = bytes();
+    let a = Map::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+    let b = Map::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+    let mut a: Vec<_> = a.iter_full().collect();
+    let mut b: Vec<_> = b.iter_full().collect();
+    a.sort_unstable();
+    b.sort_unstable();
+    assert_eq!(a, b);
+
+    let set =
+        HashSlabSet::<u8, FnvBuildHasher>::arbitrary_take_rest(Unstructured::new(&bytes)).unwrap();
+    assert!(set
+        .iter_full()
+        .all(|(index, value)| set.get_index_of(value) == Some(index)));
+}
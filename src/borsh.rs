@@ -0,0 +1,127 @@
+//! `BorshSerialize` and `BorshDeserialize` for [`HashSlabMap`] and
+//! [`HashSlabSet`]
+//!
+//! Like borsh collections, a map is written as its length as a `u32`,
+//! followed by its entries in index order, each the index as a `u64`, the key
+//! and the value. A set is written the same way, without values. The vacant
+//! indices below the highest one are reused highest first after
+//! deserialization, as if they had been removed in ascending order.
+//!
+//! Deserialization rejects a highest index of twice the number of entries
+//! plus 65536 or more, so a forged index can't allocate an arbitrary amount
+//! of memory. Serializing a map with more vacant indices than that fails,
+//! so everything written can be read back.
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use ::borsh::{
+    io::{Error, ErrorKind, Read, Result, Write},
+    BorshDeserialize, BorshSerialize,
+};
+
+use crate::{map::index_bound, HashSlabMap, HashSlabSet};
+
+fn serialize_entries<K, V, S, W>(
+    map: &HashSlabMap<K, V, S>,
+    writer: &mut W,
+    mut entry: impl FnMut(&mut W, &K, &V) -> Result<()>,
+) -> Result<()>
+where
+    W: Write,
+{
+    let len = u32::try_from(map.len()).map_err(|_| ErrorKind::InvalidData)?;
+    let mut entries: Vec<_> = map.iter_full().collect();
+    entries.sort_unstable_by_key(|&(index, _, _)| index);
+    if entries
+        .last()
+        .is_some_and(|&(index, _, _)| index >= index_bound(entries.len()))
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "too many vacant indices below the highest one to deserialize",
+        ));
+    }
+    len.serialize(writer)?;
+    for (index, key, value) in entries {
+        (index as u64).serialize(writer)?;
+        entry(writer, key, value)?;
+    }
+    Ok(())
+}
+
+fn deserialize_entries<K, V, S, R>(
+    reader: &mut R,
+    mut entry: impl FnMut(&mut R) -> Result<(K, V)>,
+) -> Result<HashSlabMap<K, V, S>>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+    R: Read,
+{
+    let len = u32::deserialize_reader(reader)?;
+    // Do not trust the length for the allocation
+    let mut entries = Vec::with_capacity((len as usize).min(4096));
+    for _ in 0..len {
+        let index = u64::deserialize_reader(reader)?;
+        let index = usize::try_from(index).map_err(|_| ErrorKind::InvalidData)?;
+        let (key, value) = entry(reader)?;
+        entries.push((index, key, value));
+    }
+    HashSlabMap::from_indexed(entries, S::default()).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            "duplicate index or key, or index out of range",
+        )
+    })
+}
+
+impl<K, V, S> BorshSerialize for HashSlabMap<K, V, S>
+where
+    K: BorshSerialize,
+    V: BorshSerialize,
+{
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        serialize_entries(self, writer, |writer, key, value| {
+            key.serialize(writer)?;
+            value.serialize(writer)
+        })
+    }
+}
+
+impl<K, V, S> BorshDeserialize for HashSlabMap<K, V, S>
+where
+    K: BorshDeserialize + Hash + Eq,
+    V: BorshDeserialize,
+    S: BuildHasher + Default,
+{
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        deserialize_entries(reader, |reader| {
+            Ok((
+                K::deserialize_reader(reader)?,
+                V::deserialize_reader(reader)?,
+            ))
+        })
+    }
+}
+
+impl<T, S> BorshSerialize for HashSlabSet<T, S>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        serialize_entries(&self.map, writer, |writer, value, _| {
+            value.serialize(writer)
+        })
+    }
+}
+
+impl<T, S> BorshDeserialize for HashSlabSet<T, S>
+where
+    T: BorshDeserialize + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let map = deserialize_entries(reader, |reader| Ok((T::deserialize_reader(reader)?, ())))?;
+        Ok(Self { map })
+    }
+}
@@ -0,0 +1,50 @@
+use super::*;
+use std::{string::ToString, vec, vec::Vec};
+
+fn round_trip<T: Encode + Decode + PartialEq + core::fmt::Debug>(value: T) {
+    let mut bytes = Vec::new();
+    value.encode(&mut bytes);
+    let mut input = bytes.as_slice();
+    assert_eq!(T::decode(&mut input), Ok(value));
+    assert!(input.is_empty());
+}
+
+#[test]
+fn round_trips() {
+    round_trip(0xdead_beef_u32);
+    round_trip(-7i128);
+    round_trip(usize::MAX);
+    round_trip('λ');
+    round_trip("hashslab".to_string());
+    round_trip(vec![Some(1u8), None]);
+    round_trip((true, -1isize, ()));
+}
+
+#[test]
+fn malformed_input() {
+    assert_eq!(
+        u64::decode(&mut [1, 2].as_slice()),
+        Err(DecodeError::UnexpectedEnd)
+    );
+    assert_eq!(
+        bool::decode(&mut [2].as_slice()),
+        Err(DecodeError::Invalid("bool"))
+    );
+    let mut bytes = Vec::new();
+    u64::MAX.encode(&mut bytes);
+    assert_eq!(
+        Vec::<u8>::decode(&mut bytes.as_slice()),
+        Err(DecodeError::UnexpectedEnd)
+    );
+    assert_eq!(
+        Vec::<()>::decode(&mut bytes.as_slice()),
+        Err(DecodeError::Invalid("length"))
+    );
+    round_trip((vec![(); 3], 0u32));
+}
+
+#[test]
+fn checksum() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc32_update(crc32(b"1234"), b"56789"), 0xCBF4_3926);
+}
@@ -0,0 +1,564 @@
+//! A sharded [`HashSlabMap`] for concurrent access
+use std::{
+    boxed::Box,
+    fmt,
+    hash::{BuildHasher, Hash, RandomState},
+    ops::{Deref, DerefMut},
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    thread,
+    vec::Vec,
+};
+
+use hashbrown::Equivalent;
+
+use crate::HashSlabMap;
+
+#[cfg(test)]
+mod tests;
+
+/// A concurrent hash map with stable indices, split into independently
+/// locked shards.
+///
+/// Every shard is a [`HashSlabMap`] behind a [`RwLock`]. A key always lives
+/// in the shard selected by its hash, so operations on keys in different
+/// shards never wait on each other. The index of an entry encodes its shard:
+/// it is `local * shard_count + shard`, where `local` is the index inside the
+/// shard. Index based methods go directly to the right shard without hashing.
+///
+/// All methods take `&self`, so the map can be shared between threads in an
+/// [`Arc`](std::sync::Arc). Accessors return guards holding the lock of one
+/// shard: keep them short-lived, as holding a guard while accessing another
+/// key of the same shard on the same thread may deadlock.
+///
+/// Lock poisoning is ignored: a panic while a shard is locked doesn't make
+/// the shard unusable.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::ConcurrentHashSlabMap;
+/// use std::{sync::Arc, thread};
+///
+/// let map = Arc::new(ConcurrentHashSlabMap::new());
+/// let handles: Vec<_> = (0..4)
+///     .map(|t| {
+///         let map = Arc::clone(&map);
+///         thread::spawn(move || {
+///             for i in 0..100 {
+///                 map.insert(t * 100 + i, t);
+///             }
+///         })
+///     })
+///     .collect();
+/// handles.into_iter().for_each(|h| h.join().unwrap());
+///
+/// assert_eq!(map.len(), 400);
+/// let index = map.get_index_of(&150).unwrap();
+/// assert_eq!(*map.get_index(index).unwrap(), 1);
+/// assert_eq!(map.remove_index(index), Some((150, 1)));
+/// ```
+pub struct ConcurrentHashSlabMap<K, V, S = RandomState> {
+    shards: Box<[RwLock<HashSlabMap<K, V, S>>]>,
+    builder: S,
+}
+
+impl<K, V> ConcurrentHashSlabMap<K, V> {
+    /// Creates an empty `ConcurrentHashSlabMap` with the default number of shards.
+    ///
+    /// The default number of shards is four times the available parallelism,
+    /// rounded up to a power of two.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty `ConcurrentHashSlabMap` with room for `n` entries in
+    /// total, spread across the default number of shards.
+    pub fn with_capacity(n: usize) -> Self {
+        Self::with_capacity_and_hasher(n, RandomState::new())
+    }
+
+    /// Creates an empty `ConcurrentHashSlabMap` with the given number of shards.
+    ///
+    /// ***Panics*** if `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<K, V, S> ConcurrentHashSlabMap<K, V, S>
+where
+    S: Clone,
+{
+    /// Creates an empty `ConcurrentHashSlabMap` with room for `n` entries in
+    /// total, using `builder` to hash the keys.
+    pub fn with_capacity_and_hasher(n: usize, builder: S) -> Self {
+        Self::with_capacity_shards_and_hasher(n, default_shard_count(), builder)
+    }
+
+    /// Creates an empty `ConcurrentHashSlabMap` which will use the given hash builder.
+    pub fn with_hasher(builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, builder)
+    }
+
+    /// Creates an empty `ConcurrentHashSlabMap` with the given number of
+    /// shards, using `builder` to hash the keys.
+    ///
+    /// ***Panics*** if `shards` is zero.
+    pub fn with_shards_and_hasher(shards: usize, builder: S) -> Self {
+        Self::with_capacity_shards_and_hasher(0, shards, builder)
+    }
+
+    /// Creates an empty `ConcurrentHashSlabMap` with room for `n` entries in
+    /// total and the given number of shards, using `builder` to hash the keys.
+    ///
+    /// ***Panics*** if `shards` is zero.
+    pub fn with_capacity_shards_and_hasher(n: usize, shards: usize, builder: S) -> Self {
+        assert!(
+            shards > 0,
+            "ConcurrentHashSlabMap: shard count must be positive"
+        );
+        let per_shard = n.div_ceil(shards);
+        let shards = (0..shards)
+            .map(|_| {
+                RwLock::new(HashSlabMap::with_capacity_and_hasher(
+                    per_shard,
+                    builder.clone(),
+                ))
+            })
+            .collect();
+        Self { shards, builder }
+    }
+}
+
+impl<K, V, S> ConcurrentHashSlabMap<K, V, S> {
+    /// Return the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Return a reference to the map's [`BuildHasher`].
+    pub fn hasher(&self) -> &S {
+        &self.builder
+    }
+
+    /// Return the number of entries in the map.
+    ///
+    /// Shards are counted one at a time, so the result may be outdated if
+    /// other threads modify the map concurrently.
+    pub fn len(&self) -> usize {
+        (0..self.shards.len()).map(|i| self.read(i).len()).sum()
+    }
+
+    /// Returns true if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        (0..self.shards.len()).all(|i| self.read(i).is_empty())
+    }
+
+    /// Returns a guard to the entry at the given index, if it exists.
+    pub fn get_index(&self, index: usize) -> Option<Ref<'_, K, V, S>> {
+        let (shard, local) = self.split_index(index);
+        let guard = self.read(shard);
+        guard
+            .slab
+            .contains(local)
+            .then(|| Ref::new(guard, index, local))
+    }
+
+    /// Returns a mutable guard to the entry at the given index, if it exists.
+    pub fn get_index_mut(&self, index: usize) -> Option<RefMut<'_, K, V, S>> {
+        let (shard, local) = self.split_index(index);
+        let guard = self.write(shard);
+        guard
+            .slab
+            .contains(local)
+            .then(|| RefMut::new(guard, index, local))
+    }
+
+    /// Returns `true` if the map contains an entry at the given index.
+    pub fn contains_index(&self, index: usize) -> bool {
+        let (shard, local) = self.split_index(index);
+        self.read(shard).slab.contains(local)
+    }
+
+    /// Calls `f` with every index-key-value triple, locking one shard at a time.
+    ///
+    /// Entries inserted or removed concurrently in shards that were not
+    /// visited yet may or may not be seen.
+    pub fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &K, &V),
+    {
+        for shard in 0..self.shards.len() {
+            for (local, key, value) in self.read(shard).iter_full() {
+                f(self.join_index(shard, local), key, value);
+            }
+        }
+    }
+
+    /// Calls `f` with every index-key-value triple, with mutable references
+    /// to the values, locking one shard at a time.
+    pub fn for_each_mut<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &K, &mut V),
+    {
+        for shard in 0..self.shards.len() {
+            for (local, key, value) in self.write(shard).iter_full_mut() {
+                f(self.join_index(shard, local), key, value);
+            }
+        }
+    }
+
+    /// Retains only the entries specified by the predicate, locking one shard
+    /// at a time.
+    pub fn retain<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        for shard in 0..self.shards.len() {
+            self.write(shard).retain(&mut f);
+        }
+    }
+
+    /// Remove all entries, while preserving the capacity of the shards.
+    pub fn clear(&self) {
+        for shard in 0..self.shards.len() {
+            self.write(shard).clear();
+        }
+    }
+
+    /// Consumes the map, returning the shards.
+    ///
+    /// The entry with local index `local` in the shard at position `shard` has
+    /// the index `local * shard_count + shard` in the concurrent map.
+    pub fn into_shards(self) -> Vec<HashSlabMap<K, V, S>> {
+        self.shards
+            .into_vec()
+            .into_iter()
+            .map(|lock| lock.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .collect()
+    }
+
+    fn read(&self, shard: usize) -> RwLockReadGuard<'_, HashSlabMap<K, V, S>> {
+        self.shards[shard]
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self, shard: usize) -> RwLockWriteGuard<'_, HashSlabMap<K, V, S>> {
+        self.shards[shard]
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn split_index(&self, index: usize) -> (usize, usize) {
+        (index % self.shards.len(), index / self.shards.len())
+    }
+
+    fn join_index(&self, shard: usize, local: usize) -> usize {
+        local * self.shards.len() + shard
+    }
+}
+
+impl<K, V, S> ConcurrentHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, `None` is returned.
+    /// If the map did have this key present, the value is updated, and the
+    /// old value is returned.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Inserts a key-value pair into the map, and returns the index of the
+    /// entry together with the old value, if any.
+    pub fn insert_full(&self, key: K, value: V) -> (usize, Option<V>) {
+        let shard = self.shard_of(&key);
+        let (local, old) = self.write(shard).insert_full(key, value);
+        (self.join_index(shard, local), old)
+    }
+
+    /// Returns a guard to the entry of `key`, if it exists.
+    pub fn get<Q>(&self, key: &Q) -> Option<Ref<'_, K, V, S>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let shard = self.shard_of(key);
+        let guard = self.read(shard);
+        let local = guard.get_index_of(key)?;
+        Some(Ref::new(guard, self.join_index(shard, local), local))
+    }
+
+    /// Returns a mutable guard to the entry of `key`, if it exists.
+    pub fn get_mut<Q>(&self, key: &Q) -> Option<RefMut<'_, K, V, S>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let shard = self.shard_of(key);
+        let guard = self.write(shard);
+        let local = guard.get_index_of(key)?;
+        Some(RefMut::new(guard, self.join_index(shard, local), local))
+    }
+
+    /// Return the index of `key`, if it exists.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let shard = self.shard_of(key);
+        let local = self.read(shard).get_index_of(key)?;
+        Some(self.join_index(shard, local))
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.read(self.shard_of(key)).contains_key(key)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Removes a key from the map, returning the index, key and value if the
+    /// key was previously in the map.
+    pub fn remove_full<Q>(&self, key: &Q) -> Option<(usize, K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let shard = self.shard_of(key);
+        let (local, key, value) = self.write(shard).remove_full(key)?;
+        Some((self.join_index(shard, local), key, value))
+    }
+
+    /// Removes the entry at the given index, returning its key and value.
+    pub fn remove_index(&self, index: usize) -> Option<(K, V)> {
+        let (shard, local) = self.split_index(index);
+        self.write(shard).remove_index(local)
+    }
+
+    fn shard_of<Q>(&self, key: &Q) -> usize
+    where
+        Q: Hash + ?Sized,
+    {
+        // hashbrown uses the lowest bits to pick a bucket and the highest
+        // ones as a tag, so take the shard from the middle to keep keys of a
+        // shard well spread in its table.
+        let hash = self.builder.hash_one(key);
+        ((hash >> 32) as usize) % self.shards.len()
+    }
+}
+
+impl<K, V, S> Clone for ConcurrentHashSlabMap<K, V, S>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        let shards = (0..self.shards.len())
+            .map(|i| RwLock::new(self.read(i).clone()))
+            .collect();
+        Self {
+            shards,
+            builder: self.builder.clone(),
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for ConcurrentHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for shard in 0..self.shards.len() {
+            map.entries(self.read(shard).iter());
+        }
+        map.finish()
+    }
+}
+
+impl<K, V, S> Default for ConcurrentHashSlabMap<K, V, S>
+where
+    S: Clone + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for ConcurrentHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for ConcurrentHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iterable: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iterable);
+        map
+    }
+}
+
+// Four shards per thread keep contention low, a power of two keeps the
+// modulo cheap.
+fn default_shard_count() -> usize {
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    (threads * 4).next_power_of_two()
+}
+
+/// A guard holding a read lock on the shard of a [`ConcurrentHashSlabMap`]
+/// entry.
+///
+/// Dereferences to the value of the entry.
+pub struct Ref<'a, K, V, S> {
+    guard: RwLockReadGuard<'a, HashSlabMap<K, V, S>>,
+    index: usize,
+    local: usize,
+}
+
+impl<'a, K, V, S> Ref<'a, K, V, S> {
+    fn new(guard: RwLockReadGuard<'a, HashSlabMap<K, V, S>>, index: usize, local: usize) -> Self {
+        Self {
+            guard,
+            index,
+            local,
+        }
+    }
+
+    /// Return the index of the entry.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return a reference to the key of the entry.
+    pub fn key(&self) -> &K {
+        self.pair().0
+    }
+
+    /// Return a reference to the value of the entry.
+    pub fn value(&self) -> &V {
+        &self.guard.slab[self.local].value
+    }
+
+    /// Return references to the key and the value of the entry.
+    pub fn pair(&self) -> (&K, &V) {
+        self.guard
+            .get_index(self.local)
+            .expect("ConcurrentHashSlabMap: guarded entry is missing")
+    }
+}
+
+impl<K, V, S> Deref for Ref<'_, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.value()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for Ref<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (key, value) = self.pair();
+        f.debug_struct("Ref")
+            .field("index", &self.index)
+            .field("key", key)
+            .field("value", value)
+            .finish()
+    }
+}
+
+/// A guard holding a write lock on the shard of a [`ConcurrentHashSlabMap`]
+/// entry.
+///
+/// Dereferences to the value of the entry.
+pub struct RefMut<'a, K, V, S> {
+    guard: RwLockWriteGuard<'a, HashSlabMap<K, V, S>>,
+    index: usize,
+    local: usize,
+}
+
+impl<'a, K, V, S> RefMut<'a, K, V, S> {
+    fn new(guard: RwLockWriteGuard<'a, HashSlabMap<K, V, S>>, index: usize, local: usize) -> Self {
+        Self {
+            guard,
+            index,
+            local,
+        }
+    }
+
+    /// Return the index of the entry.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return a reference to the key of the entry.
+    pub fn key(&self) -> &K {
+        self.pair().0
+    }
+
+    /// Return a reference to the value of the entry.
+    pub fn value(&self) -> &V {
+        &self.guard.slab[self.local].value
+    }
+
+    /// Return a mutable reference to the value of the entry.
+    pub fn value_mut(&mut self) -> &mut V {
+        &mut self.guard.slab[self.local].value
+    }
+
+    /// Return references to the key and the value of the entry.
+    pub fn pair(&self) -> (&K, &V) {
+        self.guard
+            .get_index(self.local)
+            .expect("ConcurrentHashSlabMap: guarded entry is missing")
+    }
+}
+
+impl<K, V, S> Deref for RefMut<'_, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.value()
+    }
+}
+
+impl<K, V, S> DerefMut for RefMut<'_, K, V, S> {
+    fn deref_mut(&mut self) -> &mut V {
+        self.value_mut()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for RefMut<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (key, value) = self.pair();
+        f.debug_struct("RefMut")
+            .field("index", &self.index)
+            .field("key", key)
+            .field("value", value)
+            .finish()
+    }
+}
@@ -0,0 +1,89 @@
+use super::*;
+use std::{sync::Arc, vec::Vec};
+
+#[test]
+fn index_encodes_shard() {
+    let map = ConcurrentHashSlabMap::with_shards(4);
+    for i in 0..100 {
+        let (index, old) = map.insert_full(i, i * 2);
+        assert_eq!(old, None);
+        assert_eq!(map.get_index_of(&i), Some(index));
+        let shard = index % 4;
+        assert_eq!(map.shard_of(&i), shard);
+    }
+    assert_eq!(map.len(), 100);
+
+    let index = map.get_index_of(&42).unwrap();
+    let entry = map.get_index(index).unwrap();
+    assert_eq!(entry.pair(), (&42, &84));
+    assert_eq!(entry.index(), index);
+    drop(entry);
+
+    assert_eq!(map.remove_index(index), Some((42, 84)));
+    assert!(map.get_index(index).is_none());
+    assert!(!map.contains_index(index));
+    assert_eq!(map.remove_index(index), None);
+    assert_eq!(map.len(), 99);
+
+    // A freed local index is reused by the next key of the same shard
+    let key = (100..).find(|k| map.shard_of(k) == index % 4).unwrap();
+    assert_eq!(map.insert_full(key, 0).0, index);
+}
+
+#[test]
+fn guards() {
+    let map = ConcurrentHashSlabMap::with_shards(2);
+    map.insert("a", 1);
+    map.insert("b", 2);
+
+    *map.get_mut("a").unwrap() += 10;
+    assert_eq!(*map.get("a").unwrap(), 11);
+    {
+        let mut entry = map.get_mut("b").unwrap();
+        assert_eq!(entry.key(), &"b");
+        *entry.value_mut() = 20;
+    }
+    assert_eq!(map.get("b").map(|r| *r), Some(20));
+    assert!(map.get("c").is_none());
+
+    map.for_each_mut(|_, _, v| *v += 1);
+    let mut entries = Vec::new();
+    map.for_each(|index, &k, &v| entries.push((index, k, v)));
+    entries.sort_unstable();
+    let mut expected = [
+        (map.get_index_of("a").unwrap(), "a", 12),
+        (map.get_index_of("b").unwrap(), "b", 21),
+    ];
+    expected.sort_unstable();
+    assert_eq!(entries, expected);
+
+    map.retain(|&k, _| k == "a");
+    assert_eq!(map.remove("a"), Some(12));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn concurrent_inserts_and_removals() {
+    let map = Arc::new(ConcurrentHashSlabMap::with_shards(8));
+    let handles: Vec<_> = (0..8)
+        .map(|t| {
+            let map = Arc::clone(&map);
+            std::thread::spawn(move || {
+                let mut indices = Vec::new();
+                for i in 0..1000 {
+                    indices.push(map.insert_full(t * 1000 + i, t).0);
+                }
+                for (i, index) in indices.into_iter().enumerate() {
+                    if i % 2 == 0 {
+                        assert_eq!(map.remove_index(index), Some((t * 1000 + i, t)));
+                    }
+                }
+            })
+        })
+        .collect();
+    handles.into_iter().for_each(|h| h.join().unwrap());
+
+    assert_eq!(map.len(), 4000);
+    let shards = Arc::try_unwrap(map).unwrap().into_shards();
+    assert_eq!(shards.iter().map(HashSlabMap::len).sum::<usize>(), 4000);
+}
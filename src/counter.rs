@@ -0,0 +1,553 @@
+//! A counter (multiset) implemented using [`HashSlabMap`]
+use alloc::vec::Vec;
+use core::{
+    cmp::Reverse,
+    fmt,
+    hash::{BuildHasher, Hash},
+    iter::FusedIterator,
+    ops::{AddAssign, BitAnd, BitOr, Sub},
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+
+use crate::{map, map::Entry, HashSlabMap};
+
+#[cfg(test)]
+mod tests;
+
+/// A counter (bag, multiset) of hashable keys with stable indices.
+///
+/// Every distinct key gets an index when it is first added, and keeps it
+/// as long as its count is above zero. Once the count drops to zero the key
+/// is removed and its index may be reused.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::HashSlabCounter;
+/// let mut letters = HashSlabCounter::new();
+/// for ch in "a short treatise on fungi".chars() {
+///     letters.add(ch);
+/// }
+///
+/// assert_eq!(letters.count(&'s'), 2);
+/// assert_eq!(letters.count(&'t'), 3);
+/// assert_eq!(letters.count(&'y'), 0);
+/// assert_eq!(letters.get_index_of(&'a'), Some(0));
+/// assert_eq!(letters.most_common(1), [(&' ', 4)]);
+///
+/// letters.remove_one(&'u');
+/// assert!(!letters.contains(&'u'));
+/// ```
+#[cfg(feature = "std")]
+pub struct HashSlabCounter<K, S = RandomState> {
+    map: HashSlabMap<K, usize, S>,
+    total: usize,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct HashSlabCounter<K, S> {
+    map: HashSlabMap<K, usize, S>,
+    total: usize,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K> HashSlabCounter<K> {
+    /// Creates an empty `HashSlabCounter`.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty `HashSlabCounter` with room for `n` distinct keys.
+    pub fn with_capacity(n: usize) -> Self {
+        Self::with_capacity_and_hasher(n, Default::default())
+    }
+}
+
+impl<K, S> HashSlabCounter<K, S> {
+    /// Creates an empty `HashSlabCounter` with room for `n` distinct keys,
+    /// using `hash_builder` to hash the keys.
+    pub fn with_capacity_and_hasher(n: usize, hash_builder: S) -> Self {
+        Self {
+            map: HashSlabMap::with_capacity_and_hasher(n, hash_builder),
+            total: 0,
+        }
+    }
+
+    /// Creates an empty `HashSlabCounter` which will use the given hash builder.
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            map: HashSlabMap::with_hasher(hash_builder),
+            total: 0,
+        }
+    }
+
+    /// Return the number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the counter contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Return the sum of all counts.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Return the underlying map of counts.
+    pub fn as_map(&self) -> &HashSlabMap<K, usize, S> {
+        &self.map
+    }
+
+    /// Get a key and its count by index.
+    pub fn get_index(&self, index: usize) -> Option<(&K, usize)> {
+        self.map.get_index(index).map(|(key, &count)| (key, count))
+    }
+
+    /// An iterator visiting all key-count pairs in arbitrary order.
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter::new(self.iter_full())
+    }
+
+    /// An iterator visiting all index-key-count triples in arbitrary order.
+    pub fn iter_full(&self) -> IterFull<'_, K> {
+        IterFull::new(self.map.iter_full())
+    }
+
+    /// Return up to `n` keys with the highest counts, most common first.
+    ///
+    /// Keys with equal counts are ordered by their index.
+    pub fn most_common(&self, n: usize) -> Vec<(&K, usize)> {
+        let mut entries: Vec<_> = self.iter_full().collect();
+        entries.sort_unstable_by_key(|&(index, _, count)| (Reverse(count), index));
+        entries
+            .into_iter()
+            .take(n)
+            .map(|(_, key, count)| (key, count))
+            .collect()
+    }
+
+    /// Retains only the keys specified by the predicate.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, usize) -> bool,
+    {
+        let total = &mut self.total;
+        self.map.retain(|key, &mut count| {
+            let keep = f(key, count);
+            if !keep {
+                *total -= count;
+            }
+            keep
+        });
+    }
+
+    /// Remove all keys, while preserving the capacity.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.total = 0;
+    }
+}
+
+impl<K, S> HashSlabCounter<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Increment the count of `key` by one and return its index.
+    pub fn add(&mut self, key: K) -> usize {
+        let entry = self.map.entry(key);
+        let index = entry.index();
+        *entry.or_insert(0) += 1;
+        self.total += 1;
+        index
+    }
+
+    /// Increment the count of `key` by `n` and return its index.
+    ///
+    /// Adding zero never inserts a key, so `None` is returned if `n` is zero
+    /// and the key was absent.
+    pub fn add_n(&mut self, key: K, n: usize) -> Option<usize> {
+        if n == 0 {
+            return self.map.get_index_of(&key);
+        }
+        let entry = self.map.entry(key);
+        let index = entry.index();
+        *entry.or_insert(0) += n;
+        self.total += n;
+        Some(index)
+    }
+
+    /// Decrement the count of `key` by one and return the remaining count.
+    ///
+    /// When the count reaches zero the key is removed and its index is freed.
+    /// Returns `None` if the key is absent.
+    pub fn remove_one<Q>(&mut self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_n(key, 1)
+    }
+
+    /// Decrement the count of `key` by at most `n` and return the remaining count.
+    ///
+    /// When the count reaches zero the key is removed and its index is freed.
+    /// Returns `None` if the key is absent.
+    pub fn remove_n<Q>(&mut self, key: &Q, n: usize) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let (index, _, count) = self.map.get_full_mut(key)?;
+        let removed = n.min(*count);
+        *count -= removed;
+        let remaining = *count;
+        self.total -= removed;
+        if remaining == 0 {
+            self.map.remove_index(index);
+        }
+        Some(remaining)
+    }
+
+    /// Remove `key` completely and return its count.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let count = self.map.remove(key)?;
+        self.total -= count;
+        Some(count)
+    }
+
+    /// Remove a key by index and return it together with its count.
+    pub fn remove_index(&mut self, index: usize) -> Option<(K, usize)> {
+        let (key, count) = self.map.remove_index(index)?;
+        self.total -= count;
+        Some((key, count))
+    }
+
+    /// Return the count of `key`, zero if it is absent.
+    pub fn count<Q>(&self, key: &Q) -> usize
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.map.get(key).copied().unwrap_or(0)
+    }
+
+    /// Returns `true` if the counter contains `key`.
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Return the index of `key`, if it exists.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.map.get_index_of(key)
+    }
+
+    // Set the count of a key, removing it when the count is zero.
+    fn set_count(&mut self, key: K, count: usize) {
+        match self.map.entry(key) {
+            Entry::Occupied(mut entry) => {
+                self.total -= *entry.get();
+                if count == 0 {
+                    entry.remove();
+                } else {
+                    entry.insert(count);
+                }
+            }
+            Entry::Vacant(entry) => {
+                if count > 0 {
+                    entry.insert(count);
+                }
+            }
+        }
+        self.total += count;
+    }
+}
+
+impl<K, S> Clone for HashSlabCounter<K, S>
+where
+    K: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            total: self.total,
+        }
+    }
+}
+
+impl<K, S> fmt::Debug for HashSlabCounter<K, S>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, S> Default for HashSlabCounter<K, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_capacity_and_hasher(0, S::default())
+    }
+}
+
+impl<K, S1, S2> PartialEq<HashSlabCounter<K, S2>> for HashSlabCounter<K, S1>
+where
+    K: Hash + Eq,
+    S1: BuildHasher,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &HashSlabCounter<K, S2>) -> bool {
+        self.total == other.total && self.map == other.map
+    }
+}
+
+impl<K, S> Eq for HashSlabCounter<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, S> Extend<K> for HashSlabCounter<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Increment the count of every key from the iterable.
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|key| {
+            self.add(key);
+        });
+    }
+}
+
+impl<K, S> Extend<(K, usize)> for HashSlabCounter<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Increment the counts of keys by the paired amounts.
+    fn extend<I: IntoIterator<Item = (K, usize)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, n)| {
+            self.add_n(key, n);
+        });
+    }
+}
+
+impl<K, S> FromIterator<K> for HashSlabCounter<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iterable: I) -> Self {
+        let mut counter = Self::default();
+        counter.extend(iterable);
+        counter
+    }
+}
+
+impl<K, S1, S2> BitOr<&HashSlabCounter<K, S2>> for &HashSlabCounter<K, S1>
+where
+    K: Hash + Eq + Clone,
+    S1: BuildHasher + Default,
+    S2: BuildHasher,
+{
+    type Output = HashSlabCounter<K, S1>;
+
+    /// Returns the multiset union: the maximum of the counts of each key.
+    fn bitor(self, other: &HashSlabCounter<K, S2>) -> Self::Output {
+        let mut result: HashSlabCounter<K, S1> = self.iter().map(|(k, n)| (k.clone(), n)).collect();
+        for (key, n) in other.iter() {
+            if n > result.count(key) {
+                result.set_count(key.clone(), n);
+            }
+        }
+        result
+    }
+}
+
+impl<K, S1, S2> BitAnd<&HashSlabCounter<K, S2>> for &HashSlabCounter<K, S1>
+where
+    K: Hash + Eq + Clone,
+    S1: BuildHasher + Default,
+    S2: BuildHasher,
+{
+    type Output = HashSlabCounter<K, S1>;
+
+    /// Returns the multiset intersection: the minimum of the counts of each key.
+    fn bitand(self, other: &HashSlabCounter<K, S2>) -> Self::Output {
+        self.iter()
+            .map(|(key, n)| (key.clone(), n.min(other.count(key))))
+            .collect()
+    }
+}
+
+impl<K, S1, S2> Sub<&HashSlabCounter<K, S2>> for &HashSlabCounter<K, S1>
+where
+    K: Hash + Eq + Clone,
+    S1: BuildHasher + Default,
+    S2: BuildHasher,
+{
+    type Output = HashSlabCounter<K, S1>;
+
+    /// Returns the multiset difference: counts of `other` are subtracted,
+    /// keeping only positive results.
+    fn sub(self, other: &HashSlabCounter<K, S2>) -> Self::Output {
+        self.iter()
+            .map(|(key, n)| (key.clone(), n.saturating_sub(other.count(key))))
+            .collect()
+    }
+}
+
+// `Add` is not implemented for references, since its `add` method would
+// shadow `HashSlabCounter::add` in method calls.
+impl<K, S1, S2> AddAssign<&HashSlabCounter<K, S2>> for HashSlabCounter<K, S1>
+where
+    K: Hash + Eq + Clone,
+    S1: BuildHasher,
+{
+    /// Adds the counts of `other` to `self`, the multiset sum.
+    fn add_assign(&mut self, other: &HashSlabCounter<K, S2>) {
+        self.extend(other.iter().map(|(key, n)| (key.clone(), n)));
+    }
+}
+
+impl<K, S> FromIterator<(K, usize)> for HashSlabCounter<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    /// Create a counter from key-count pairs, adding up counts of equal keys.
+    fn from_iter<I: IntoIterator<Item = (K, usize)>>(iterable: I) -> Self {
+        let mut counter = Self::default();
+        counter.extend(iterable);
+        counter
+    }
+}
+
+/// An iterator over the index-key-count triples of a [`HashSlabCounter`].
+///
+/// This `struct` is created by the [`HashSlabCounter::iter_full`] method.
+/// See its documentation for more.
+pub struct IterFull<'a, K> {
+    iter_full: map::IterFull<'a, K, usize>,
+}
+
+impl<'a, K> IterFull<'a, K> {
+    fn new(iter_full: map::IterFull<'a, K, usize>) -> Self {
+        Self { iter_full }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K> Clone for IterFull<'_, K> {
+    fn clone(&self) -> Self {
+        IterFull {
+            iter_full: self.iter_full.clone(),
+        }
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for IterFull<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K> Iterator for IterFull<'a, K> {
+    type Item = (usize, &'a K, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter_full
+            .next()
+            .map(|(index, key, &count)| (index, key, count))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter_full.size_hint()
+    }
+}
+
+impl<K> ExactSizeIterator for IterFull<'_, K> {
+    fn len(&self) -> usize {
+        self.iter_full.len()
+    }
+}
+
+impl<K> FusedIterator for IterFull<'_, K> {}
+
+/// An iterator over the key-count pairs of a [`HashSlabCounter`].
+///
+/// This `struct` is created by the [`HashSlabCounter::iter`] method.
+/// See its documentation for more.
+pub struct Iter<'a, K> {
+    iter_full: IterFull<'a, K>,
+}
+
+impl<'a, K> Iter<'a, K> {
+    fn new(iter_full: IterFull<'a, K>) -> Self {
+        Self { iter_full }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K> Clone for Iter<'_, K> {
+    fn clone(&self) -> Self {
+        Iter {
+            iter_full: self.iter_full.clone(),
+        }
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for Iter<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = (&'a K, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter_full.next().map(|(_, key, count)| (key, count))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter_full.size_hint()
+    }
+}
+
+impl<K> ExactSizeIterator for Iter<'_, K> {
+    fn len(&self) -> usize {
+        self.iter_full.len()
+    }
+}
+
+impl<K> FusedIterator for Iter<'_, K> {}
+
+impl<'a, K, S> IntoIterator for &'a HashSlabCounter<K, S> {
+    type Item = (&'a K, usize);
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
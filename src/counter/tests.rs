@@ -0,0 +1,73 @@
+use super::*;
+use std::{vec, vec::Vec};
+
+type Counter = HashSlabCounter<char>;
+
+fn counts(counter: &Counter) -> Vec<(char, usize)> {
+    let mut vec: Vec<_> = counter.iter().map(|(&k, n)| (k, n)).collect();
+    vec.sort_unstable();
+    vec
+}
+
+#[test]
+fn add_and_remove() {
+    let mut counter = Counter::new();
+    let a = counter.add('a');
+    assert_eq!(counter.add('a'), a);
+    assert_eq!(counter.add_n('b', 3), Some(1));
+    assert_eq!(counter.add_n('c', 0), None);
+    assert_eq!(counter.add_n('a', 0), Some(a));
+    assert_eq!(counter.total(), 5);
+    assert_eq!(counter.len(), 2);
+
+    assert_eq!(counter.remove_one(&'a'), Some(1));
+    assert_eq!(counter.get_index_of(&'a'), Some(a));
+    assert_eq!(counter.remove_one(&'a'), Some(0));
+    assert_eq!(counter.get_index_of(&'a'), None);
+    assert_eq!(counter.remove_one(&'a'), None);
+
+    // Freed index is reused
+    assert_eq!(counter.add('z'), a);
+
+    assert_eq!(counter.remove_n(&'b', 10), Some(0));
+    assert_eq!(counter.total(), 1);
+    assert_eq!(counter.remove(&'z'), Some(1));
+    assert!(counter.is_empty());
+    assert_eq!(counter.total(), 0);
+}
+
+#[test]
+fn most_common() {
+    let counter: Counter = "abracadabra".chars().collect();
+    assert_eq!(counter.most_common(3), [(&'a', 5), (&'b', 2), (&'r', 2)]);
+    assert_eq!(counter.most_common(100).len(), 5);
+    assert_eq!(counter.most_common(0), []);
+}
+
+#[test]
+fn multiset_operations() {
+    let a: Counter = "aaabbc".chars().collect();
+    let b: Counter = "abbbd".chars().collect();
+
+    assert_eq!(counts(&(&a | &b)), [('a', 3), ('b', 3), ('c', 1), ('d', 1)]);
+    assert_eq!(counts(&(&a & &b)), [('a', 1), ('b', 2)]);
+    assert_eq!(counts(&(&a - &b)), [('a', 2), ('c', 1)]);
+    assert_eq!(counts(&(&b - &a)), [('b', 1), ('d', 1)]);
+    assert_eq!((&a & &b).total(), 3);
+
+    let mut sum = a.clone();
+    sum += &b;
+    assert_eq!(counts(&sum), [('a', 4), ('b', 5), ('c', 1), ('d', 1)]);
+    assert_eq!(sum.total(), a.total() + b.total());
+}
+
+#[test]
+fn retain_and_equality() {
+    let mut a: Counter = "hello world".chars().collect();
+    a.retain(|_, n| n > 1);
+    assert_eq!(counts(&a), [('l', 3), ('o', 2)]);
+    assert_eq!(a.total(), 5);
+
+    let b: Counter = vec![('o', 2), ('l', 1), ('l', 2)].into_iter().collect();
+    assert_eq!(a, b);
+}
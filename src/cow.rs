@@ -0,0 +1,558 @@
+//! A copy-on-write hash map with cheap snapshots
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    iter::FusedIterator,
+    mem,
+    ops::Index,
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+
+#[cfg(test)]
+mod tests;
+
+// Entries and buckets are stored in reference-counted chunks of this size.
+const CHUNK_BITS: u32 = 5;
+const CHUNK: usize = 1 << CHUNK_BITS;
+const CHUNK_MASK: usize = CHUNK - 1;
+
+#[derive(Clone)]
+enum Slot<K, V> {
+    Occupied { hash: u64, key: K, value: V },
+    // Vacant slots form a free list, most recently freed first
+    Vacant(usize),
+}
+
+type Chunks<T> = Arc<Vec<Arc<Vec<T>>>>;
+
+/// A hash map with stable indices and `O(1)` snapshots.
+///
+/// Entries and the hash index are stored in reference-counted chunks of 32
+/// elements. [`snapshot`][Self::snapshot] (and [`Clone`]) only copies two
+/// pointers, and the snapshot and the original map share all chunks. A
+/// write copies the chunks it touches if they are shared, plus, for the
+/// first write after a snapshot, the list of chunk pointers. Snapshots are
+/// complete maps: they can be read and modified independently.
+///
+/// Indices are assigned like in [`HashSlabMap`](crate::HashSlabMap): the
+/// most recently freed index is reused first.
+///
+/// Keys and values are cloned when a shared chunk is copied, so modifying
+/// the map requires `K: Clone` and `V: Clone`.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::CowHashSlabMap;
+/// let mut map: CowHashSlabMap<u32, String> = (0..1000).map(|i| (i, i.to_string())).collect();
+/// let snapshot = map.snapshot();
+///
+/// map.insert(7, "seven".to_string());
+/// map.remove(&8);
+///
+/// assert_eq!(map.get(&7).unwrap(), "seven");
+/// assert_eq!(snapshot.get(&7).unwrap(), "7");
+/// assert_eq!(snapshot.get_index(8), Some((&8, &"8".to_string())));
+/// assert_eq!(snapshot.iter().count(), 1000);
+/// assert_eq!(map.len(), 999);
+/// ```
+#[cfg(feature = "std")]
+pub struct CowHashSlabMap<K, V, S = RandomState> {
+    slots: Chunks<Slot<K, V>>,
+    // Number of slots, occupied or vacant
+    slots_len: usize,
+    // Head of the free list, `slots_len` if there are no vacant slots
+    next: usize,
+    len: usize,
+    // Indices of the entries, by the low bits of their hash
+    buckets: Chunks<Vec<usize>>,
+    builder: S,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct CowHashSlabMap<K, V, S> {
+    slots: Chunks<Slot<K, V>>,
+    slots_len: usize,
+    next: usize,
+    len: usize,
+    buckets: Chunks<Vec<usize>>,
+    builder: S,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V> CowHashSlabMap<K, V> {
+    /// Creates an empty `CowHashSlabMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<K, V, S> CowHashSlabMap<K, V, S> {
+    /// Creates an empty `CowHashSlabMap` which will use the given hash builder.
+    pub fn with_hasher(builder: S) -> Self {
+        Self {
+            slots: Arc::default(),
+            slots_len: 0,
+            next: 0,
+            len: 0,
+            buckets: Arc::default(),
+            builder,
+        }
+    }
+
+    /// Return a reference to the map's [`BuildHasher`].
+    pub fn hasher(&self) -> &S {
+        &self.builder
+    }
+
+    /// Return the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return a snapshot of the map, sharing its storage.
+    ///
+    /// Computes in **O(1)** time.
+    pub fn snapshot(&self) -> Self
+    where
+        S: Clone,
+    {
+        Self {
+            slots: Arc::clone(&self.slots),
+            slots_len: self.slots_len,
+            next: self.next,
+            len: self.len,
+            buckets: Arc::clone(&self.buckets),
+            builder: self.builder.clone(),
+        }
+    }
+
+    /// Returns `true` if both maps share all their storage, as a map and its
+    /// unmodified snapshot do.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.slots, &other.slots) && Arc::ptr_eq(&self.buckets, &other.buckets)
+    }
+
+    /// Get a key-value pair by index.
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        match self.slot(index)? {
+            Slot::Occupied { key, value, .. } => Some((key, value)),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    /// Returns `true` if the map contains an entry at the given index.
+    pub fn contains_index(&self, index: usize) -> bool {
+        self.get_index(index).is_some()
+    }
+
+    /// An iterator visiting all key-value pairs in index order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(self.iter_full())
+    }
+
+    /// An iterator visiting all index-key-value triples in index order.
+    pub fn iter_full(&self) -> IterFull<'_, K, V> {
+        IterFull::new(&self.slots, self.slots_len, self.len)
+    }
+
+    /// Removes all entries.
+    ///
+    /// Snapshots keep their entries.
+    pub fn clear(&mut self) {
+        self.slots = Arc::default();
+        self.slots_len = 0;
+        self.next = 0;
+        self.len = 0;
+        self.buckets = Arc::default();
+    }
+
+    fn slot(&self, index: usize) -> Option<&Slot<K, V>> {
+        self.slots
+            .get(index >> CHUNK_BITS)
+            .and_then(|chunk| chunk.get(index & CHUNK_MASK))
+    }
+
+    fn bucket(&self, hash: u64) -> Option<&Vec<usize>> {
+        let bucket = hash as usize & (self.buckets.len() * CHUNK).checked_sub(1)?;
+        Some(&self.buckets[bucket >> CHUNK_BITS][bucket & CHUNK_MASK])
+    }
+
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        self.bucket(hash)?.iter().copied().find(|&index| {
+            matches!(
+                self.slot(index),
+                Some(Slot::Occupied { hash: h, key: k, .. }) if *h == hash && key.equivalent(k)
+            )
+        })
+    }
+}
+
+impl<K, V, S> CowHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Return a reference to the value of `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Return the index, key and value of `key`.
+    pub fn get_full<Q>(&self, key: &Q) -> Option<(usize, &K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.get_index_of(key)?;
+        self.get_index(index)
+            .map(|(key, value)| (index, key, value))
+    }
+
+    /// Return the index of `key`.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find(self.builder.hash_one(key), key)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_index_of(key).is_some()
+    }
+}
+
+impl<K, V, S> CowHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the key is already present, its value is replaced and the old value
+    /// is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Inserts a key-value pair into the map, and returns its index together
+    /// with the old value if the key was present.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        let hash = self.builder.hash_one(&key);
+        if let Some(index) = self.find(hash, &key) {
+            let old = match self.slot_mut(index) {
+                Slot::Occupied { value: old, .. } => mem::replace(old, value),
+                Slot::Vacant(_) => unreachable!(),
+            };
+            return (index, Some(old));
+        }
+
+        let index = self.next;
+        let slot = Slot::Occupied { hash, key, value };
+        if index == self.slots_len {
+            if index & CHUNK_MASK == 0 {
+                Arc::make_mut(&mut self.slots).push(Arc::new(Vec::with_capacity(CHUNK)));
+            }
+            let chunks = Arc::make_mut(&mut self.slots);
+            if let Some(chunk) = chunks.last_mut() {
+                Arc::make_mut(chunk).push(slot);
+            }
+            self.slots_len += 1;
+            self.next = self.slots_len;
+        } else {
+            match mem::replace(self.slot_mut(index), slot) {
+                Slot::Vacant(next) => self.next = next,
+                Slot::Occupied { .. } => unreachable!(),
+            }
+        }
+        self.len += 1;
+
+        // Keep at most one entry per bucket on average
+        if self.len > self.buckets.len() * CHUNK {
+            self.rebuild_buckets((self.len * 2).next_power_of_two().max(CHUNK));
+        } else {
+            self.bucket_mut(hash).push(index);
+        }
+        (index, None)
+    }
+
+    /// Get a key and a mutable reference to the value by index.
+    ///
+    /// The chunk holding the entry is copied if it is shared with a snapshot.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)> {
+        self.get_index(index)?;
+        match self.slot_mut(index) {
+            Slot::Occupied { key, value, .. } => Some((key, value)),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    /// Return a mutable reference to the value of `key`.
+    ///
+    /// The chunk holding the entry is copied if it is shared with a snapshot.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.get_index_of(key)?;
+        self.get_index_mut(index).map(|(_, value)| value)
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Removes a key from the map, returning its index, key and value if it
+    /// was present.
+    pub fn remove_full<Q>(&mut self, key: &Q) -> Option<(usize, K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.get_index_of(key)?;
+        self.remove_index(index)
+            .map(|(key, value)| (index, key, value))
+    }
+
+    /// Removes the entry at the given index, returning its key and value.
+    pub fn remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        self.get_index(index)?;
+        let next = self.next;
+        let (hash, key, value) = match mem::replace(self.slot_mut(index), Slot::Vacant(next)) {
+            Slot::Occupied { hash, key, value } => (hash, key, value),
+            Slot::Vacant(_) => unreachable!(),
+        };
+        self.next = index;
+        self.len -= 1;
+        let bucket = self.bucket_mut(hash);
+        if let Some(position) = bucket.iter().position(|&i| i == index) {
+            bucket.swap_remove(position);
+        }
+        Some((key, value))
+    }
+
+    fn slot_mut(&mut self, index: usize) -> &mut Slot<K, V> {
+        let chunk = &mut Arc::make_mut(&mut self.slots)[index >> CHUNK_BITS];
+        &mut Arc::make_mut(chunk)[index & CHUNK_MASK]
+    }
+
+    fn bucket_mut(&mut self, hash: u64) -> &mut Vec<usize> {
+        let bucket = hash as usize & (self.buckets.len() * CHUNK - 1);
+        let chunk = &mut Arc::make_mut(&mut self.buckets)[bucket >> CHUNK_BITS];
+        &mut Arc::make_mut(chunk)[bucket & CHUNK_MASK]
+    }
+
+    fn rebuild_buckets(&mut self, count: usize) {
+        let mut buckets: Vec<Vec<Vec<usize>>> = (0..count / CHUNK)
+            .map(|_| (0..CHUNK).map(|_| Vec::new()).collect())
+            .collect();
+        for (index, _, _) in self.iter_full() {
+            let hash = match self.slot(index) {
+                Some(Slot::Occupied { hash, .. }) => *hash,
+                _ => unreachable!(),
+            };
+            let bucket = hash as usize & (count - 1);
+            buckets[bucket >> CHUNK_BITS][bucket & CHUNK_MASK].push(index);
+        }
+        self.buckets = Arc::new(buckets.into_iter().map(Arc::new).collect());
+    }
+}
+
+impl<K, V, S: Clone> Clone for CowHashSlabMap<K, V, S> {
+    /// Returns a [`snapshot`][Self::snapshot] of the map.
+    fn clone(&self) -> Self {
+        self.snapshot()
+    }
+}
+
+impl<K, V, S> fmt::Debug for CowHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter_full().map(|(i, k, v)| (i, (k, v))))
+            .finish()
+    }
+}
+
+impl<K, V, S> Default for CowHashSlabMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+/// Access [`CowHashSlabMap`] values by index.
+///
+/// ***Panics***
+///
+/// If there is no entry at the index.
+impl<K, V, S> Index<usize> for CowHashSlabMap<K, V, S> {
+    type Output = V;
+
+    fn index(&self, index: usize) -> &V {
+        self.get_index(index)
+            .expect("CowHashSlabMap: index out of bounds")
+            .1
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for CowHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for CowHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iterable: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iterable);
+        map
+    }
+}
+
+/// An iterator over the entries of a [`CowHashSlabMap`].
+///
+/// This `struct` is created by the [`CowHashSlabMap::iter_full`] method.
+/// See its documentation for more.
+pub struct IterFull<'a, K, V> {
+    chunks: &'a [Arc<Vec<Slot<K, V>>>],
+    index: usize,
+    end: usize,
+    remaining: usize,
+}
+
+impl<'a, K, V> IterFull<'a, K, V> {
+    fn new(chunks: &'a [Arc<Vec<Slot<K, V>>>], end: usize, remaining: usize) -> Self {
+        Self {
+            chunks,
+            index: 0,
+            end,
+            remaining,
+        }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K, V> Clone for IterFull<'_, K, V> {
+    fn clone(&self) -> Self {
+        IterFull { ..*self }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for IterFull<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K, V> Iterator for IterFull<'a, K, V> {
+    type Item = (usize, &'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.end {
+            let index = self.index;
+            self.index += 1;
+            if let Slot::Occupied { key, value, .. } =
+                &self.chunks[index >> CHUNK_BITS][index & CHUNK_MASK]
+            {
+                self.remaining -= 1;
+                return Some((index, key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterFull<'_, K, V> {}
+
+impl<K, V> FusedIterator for IterFull<'_, K, V> {}
+
+/// An iterator over the key-value pairs of a [`CowHashSlabMap`].
+///
+/// This `struct` is created by the [`CowHashSlabMap::iter`] method.
+/// See its documentation for more.
+pub struct Iter<'a, K, V> {
+    inner: IterFull<'a, K, V>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn new(inner: IterFull<'a, K, V>) -> Self {
+        Self { inner }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Iter<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, key, value)| (key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
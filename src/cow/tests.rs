@@ -0,0 +1,68 @@
+use super::*;
+use std::{string::ToString, vec::Vec};
+
+#[test]
+fn snapshot_is_isolated() {
+    let mut map: CowHashSlabMap<u32, u32> = (0..100).map(|i| (i, i)).collect();
+    let snapshot = map.snapshot();
+    assert!(map.ptr_eq(&snapshot));
+
+    assert_eq!(map.insert(5, 50), Some(5));
+    assert_eq!(map.remove(&6), Some(6));
+    assert_eq!(map.insert_full(1000, 1), (6, None));
+    *map.get_mut(&7).unwrap() += 70;
+    assert!(!map.ptr_eq(&snapshot));
+
+    assert_eq!(snapshot.len(), 100);
+    assert_eq!(snapshot.get(&5), Some(&5));
+    assert_eq!(snapshot.get_index(6), Some((&6, &6)));
+    assert_eq!(snapshot.get(&7), Some(&7));
+    assert!(!snapshot.contains_key(&1000));
+    assert_eq!(
+        snapshot.iter_full().map(|(i, _, _)| i).collect::<Vec<_>>(),
+        (0..100).collect::<Vec<_>>()
+    );
+
+    assert_eq!(map.len(), 100);
+    assert_eq!(map.get_full(&1000), Some((6, &1000, &1)));
+    assert_eq!(map[7], 77);
+}
+
+#[test]
+fn writes_copy_touched_chunks_only() {
+    let mut map: CowHashSlabMap<u32, u32> = (0..128).map(|i| (i, i)).collect();
+    let snapshot = map.snapshot();
+    map.get_index_mut(0).unwrap().1.clone_from(&1);
+
+    let shared = map
+        .slots
+        .iter()
+        .zip(snapshot.slots.iter())
+        .filter(|(a, b)| Arc::ptr_eq(a, b))
+        .count();
+    assert_eq!(shared, map.slots.len() - 1);
+    // The buckets were not touched
+    assert!(Arc::ptr_eq(&map.buckets, &snapshot.buckets));
+}
+
+#[test]
+fn indices_are_reused() {
+    let mut map = CowHashSlabMap::new();
+    for i in 0..40 {
+        map.insert(i.to_string(), i);
+    }
+    assert_eq!(map.remove_index(3), Some(("3".to_string(), 3)));
+    assert_eq!(map.remove_full("35"), Some((35, "35".to_string(), 35)));
+    assert_eq!(map.remove_index(3), None);
+    assert!(!map.contains_index(35));
+    assert_eq!(map.insert_full("a".to_string(), 0).0, 35);
+    assert_eq!(map.insert_full("b".to_string(), 0).0, 3);
+    assert_eq!(map.insert_full("c".to_string(), 0).0, 40);
+    assert_eq!(map.get_index_of("b"), Some(3));
+
+    let snapshot = map.clone();
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(snapshot.len(), 41);
+    assert_eq!(snapshot.iter().len(), 41);
+}
@@ -0,0 +1,359 @@
+//! Dictionary encoding of values into dense `u32` codes using [`HashSlabSet`]
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    cmp::Reverse,
+    fmt,
+    hash::{BuildHasher, Hash},
+    ops::Index,
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+use thiserror::Error;
+
+use crate::HashSlabSet;
+
+#[cfg(test)]
+mod tests;
+
+/// The error type for decoding codes with unknown values.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum DecodeError {
+    #[error("unknown dictionary code {code} at position {position}")]
+    UnknownCode { code: u32, position: usize },
+}
+
+/// A dictionary encoder, mapping each distinct value to a dense `u32` code.
+///
+/// The code of a value is its index in the underlying [`HashSlabSet`].
+/// Values are never removed from the dictionary, so codes are assigned
+/// sequentially starting from zero and stay valid for the encoder lifetime.
+/// The encoder also counts how many times each value was encoded.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::DictionaryEncoder;
+/// let mut encoder = DictionaryEncoder::new();
+/// let codes = encoder.encode(["red", "green", "red", "blue", "red"]);
+/// assert_eq!(codes, [0, 1, 0, 2, 0]);
+///
+/// let values = encoder.decode(&[2, 0]).unwrap();
+/// assert_eq!(values, [&"blue", &"red"]);
+/// assert!(encoder.decode(&[3]).is_err());
+///
+/// assert_eq!(encoder.frequency(&"red"), 3);
+/// assert_eq!(encoder.most_frequent(1), [(0, &"red", 3)]);
+/// ```
+#[cfg(feature = "std")]
+pub struct DictionaryEncoder<T, S = RandomState> {
+    dict: HashSlabSet<T, S>,
+    // Number of times each value was encoded, indexed by code
+    counts: Vec<usize>,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct DictionaryEncoder<T, S> {
+    dict: HashSlabSet<T, S>,
+    counts: Vec<usize>,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<T> DictionaryEncoder<T> {
+    /// Creates an empty `DictionaryEncoder`.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty `DictionaryEncoder` with room for `n` distinct values.
+    pub fn with_capacity(n: usize) -> Self {
+        Self::with_capacity_and_hasher(n, Default::default())
+    }
+}
+
+impl<T, S> DictionaryEncoder<T, S> {
+    /// Creates an empty `DictionaryEncoder` with room for `n` distinct
+    /// values, using `hash_builder` to hash them.
+    pub fn with_capacity_and_hasher(n: usize, hash_builder: S) -> Self {
+        Self {
+            dict: HashSlabSet::with_capacity_and_hasher(n, hash_builder),
+            counts: Vec::with_capacity(n),
+        }
+    }
+
+    /// Creates an empty `DictionaryEncoder` which will use the given hash builder.
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            dict: HashSlabSet::with_hasher(hash_builder),
+            counts: Vec::new(),
+        }
+    }
+
+    /// Return the number of distinct values in the dictionary.
+    pub fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    /// Returns true if the dictionary contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.dict.is_empty()
+    }
+
+    /// Return the underlying set of values, indexed by code.
+    pub fn as_set(&self) -> &HashSlabSet<T, S> {
+        &self.dict
+    }
+
+    /// Get a value by its code.
+    pub fn value(&self, code: u32) -> Option<&T> {
+        self.dict.get_index(code as usize)
+    }
+
+    /// Decode a slice of codes back to references to their values.
+    ///
+    /// Returns an error for the first code missing from the dictionary.
+    pub fn decode(&self, codes: &[u32]) -> Result<Vec<&T>, DecodeError> {
+        decode_with(codes, |code| self.value(code))
+    }
+
+    /// Return the number of times each value was encoded, indexed by code.
+    pub fn frequencies(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Return up to `n` values with the highest frequencies, as code-value-frequency
+    /// triples, most frequent first.
+    ///
+    /// Values with equal frequencies are ordered by their code.
+    pub fn most_frequent(&self, n: usize) -> Vec<(u32, &T, usize)> {
+        let mut codes: Vec<_> = (0..self.counts.len()).collect();
+        codes.sort_by_key(|&code| Reverse(self.counts[code]));
+        codes
+            .into_iter()
+            .take(n)
+            .map(|code| (code as u32, &self[code as u32], self.counts[code]))
+            .collect()
+    }
+
+    /// Export the dictionary as a read-only [`FrozenDictionary`].
+    pub fn freeze(&self) -> FrozenDictionary<T>
+    where
+        T: Clone,
+    {
+        let values = (0..self.len() as u32)
+            .map(|code| self[code].clone())
+            .collect();
+        FrozenDictionary { values }
+    }
+
+    /// Remove all values and reset the frequencies.
+    pub fn clear(&mut self) {
+        self.dict.clear();
+        self.counts.clear();
+    }
+}
+
+impl<T, S> DictionaryEncoder<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Encode a single value, adding it to the dictionary if needed, and
+    /// return its code.
+    ///
+    /// ***Panics*** if the number of distinct values exceeds `u32::MAX + 1`.
+    pub fn encode_one(&mut self, value: T) -> u32 {
+        let (index, inserted) = self.dict.insert_full(value);
+        let code = u32::try_from(index).expect("DictionaryEncoder: code overflow");
+        if inserted {
+            self.counts.push(0);
+        }
+        self.counts[index] += 1;
+        code
+    }
+
+    /// Encode all values from the iterable, returning their codes in order.
+    ///
+    /// ***Panics*** if the number of distinct values exceeds `u32::MAX + 1`.
+    pub fn encode<I>(&mut self, iterable: I) -> Vec<u32>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        iterable
+            .into_iter()
+            .map(|value| self.encode_one(value))
+            .collect()
+    }
+
+    /// Return the code of a value, if it's in the dictionary.
+    pub fn code_of<Q>(&self, value: &Q) -> Option<u32>
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.dict.get_index_of(value).map(|index| index as u32)
+    }
+
+    /// Return the number of times a value was encoded, zero if it's absent.
+    pub fn frequency<Q>(&self, value: &Q) -> usize
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.dict
+            .get_index_of(value)
+            .map_or(0, |index| self.counts[index])
+    }
+
+    /// Merge the dictionary of another encoder into this one.
+    ///
+    /// Values missing from this dictionary get new codes, and frequencies are
+    /// added up. Returns a remapping table: the code `c` of `other` becomes
+    /// `remap[c]` in this encoder.
+    ///
+    /// ***Panics*** if the number of distinct values exceeds `u32::MAX + 1`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::DictionaryEncoder;
+    /// let mut left = DictionaryEncoder::new();
+    /// left.encode(["a", "b"]);
+    /// let mut right = DictionaryEncoder::new();
+    /// let codes = right.encode(["c", "a"]);
+    ///
+    /// let remap = left.merge(&right);
+    /// assert_eq!(remap, [2, 0]);
+    /// let codes: Vec<_> = codes.iter().map(|&c| remap[c as usize]).collect();
+    /// assert_eq!(left.decode(&codes).unwrap(), [&"c", &"a"]);
+    /// assert_eq!(left.frequency(&"a"), 2);
+    /// ```
+    pub fn merge<S2>(&mut self, other: &DictionaryEncoder<T, S2>) -> Vec<u32>
+    where
+        T: Clone,
+    {
+        (0..other.len() as u32)
+            .map(|code| {
+                let (index, inserted) = self.dict.insert_full(other[code].clone());
+                if inserted {
+                    self.counts.push(0);
+                }
+                self.counts[index] += other.counts[code as usize];
+                u32::try_from(index).expect("DictionaryEncoder: code overflow")
+            })
+            .collect()
+    }
+}
+
+impl<T, S> Clone for DictionaryEncoder<T, S>
+where
+    T: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            dict: self.dict.clone(),
+            counts: self.counts.clone(),
+        }
+    }
+}
+
+impl<T, S> fmt::Debug for DictionaryEncoder<T, S>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries((0..self.len() as u32).map(|code| (code, &self[code])))
+            .finish()
+    }
+}
+
+impl<T, S> Default for DictionaryEncoder<T, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_capacity_and_hasher(0, S::default())
+    }
+}
+
+impl<T, S> Index<u32> for DictionaryEncoder<T, S> {
+    type Output = T;
+
+    /// Returns a reference to the value with the given code.
+    ///
+    /// ***Panics*** if the code is not in the dictionary.
+    fn index(&self, code: u32) -> &T {
+        self.value(code)
+            .expect("DictionaryEncoder: code out of bounds")
+    }
+}
+
+/// A read-only dictionary exported from a [`DictionaryEncoder`].
+///
+/// Values are stored contiguously by code, so decoding is a plain slice lookup.
+///
+/// This `struct` is created by the [`DictionaryEncoder::freeze`] method.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct FrozenDictionary<T> {
+    values: Box<[T]>,
+}
+
+impl<T> FrozenDictionary<T> {
+    /// Return the number of values in the dictionary.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if the dictionary contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Get a value by its code.
+    pub fn get(&self, code: u32) -> Option<&T> {
+        self.values.get(code as usize)
+    }
+
+    /// Return all values as a slice indexed by code.
+    pub fn as_slice(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Decode a slice of codes back to references to their values.
+    ///
+    /// Returns an error for the first code missing from the dictionary.
+    pub fn decode(&self, codes: &[u32]) -> Result<Vec<&T>, DecodeError> {
+        decode_with(codes, |code| self.get(code))
+    }
+
+    /// Convert into the values, indexed by code.
+    pub fn into_vec(self) -> Vec<T> {
+        self.values.into_vec()
+    }
+}
+
+impl<T> Index<u32> for FrozenDictionary<T> {
+    type Output = T;
+
+    /// Returns a reference to the value with the given code.
+    ///
+    /// ***Panics*** if the code is not in the dictionary.
+    fn index(&self, code: u32) -> &T {
+        self.get(code)
+            .expect("FrozenDictionary: code out of bounds")
+    }
+}
+
+fn decode_with<'a, T: 'a>(
+    codes: &[u32],
+    value: impl Fn(u32) -> Option<&'a T>,
+) -> Result<Vec<&'a T>, DecodeError> {
+    codes
+        .iter()
+        .enumerate()
+        .map(|(position, &code)| value(code).ok_or(DecodeError::UnknownCode { code, position }))
+        .collect()
+}
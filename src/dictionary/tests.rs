@@ -0,0 +1,56 @@
+use super::*;
+use std::{string::String, vec::Vec};
+
+#[test]
+fn encode_and_decode() {
+    let column = ["x", "y", "x", "z", "y", "x"].map(String::from);
+    let mut encoder = DictionaryEncoder::new();
+    let codes = encoder.encode(column.clone());
+    assert_eq!(codes, [0, 1, 0, 2, 1, 0]);
+    assert_eq!(encoder.len(), 3);
+    assert_eq!(encoder.code_of("z"), Some(2));
+    assert_eq!(encoder.code_of("w"), None);
+
+    let decoded: Vec<_> = encoder.decode(&codes).unwrap();
+    assert!(decoded.iter().zip(&column).all(|(a, b)| *a == b));
+    assert_eq!(
+        encoder.decode(&[0, 7, 9]),
+        Err(DecodeError::UnknownCode {
+            code: 7,
+            position: 1
+        })
+    );
+}
+
+#[test]
+fn frequencies() {
+    let mut encoder = DictionaryEncoder::new();
+    encoder.encode("mississippi".chars());
+    assert_eq!(encoder.frequencies(), [1, 4, 4, 2]);
+    assert_eq!(encoder.frequency(&'s'), 4);
+    assert_eq!(encoder.frequency(&'q'), 0);
+    assert_eq!(
+        encoder.most_frequent(3),
+        [(1, &'i', 4), (2, &'s', 4), (3, &'p', 2)]
+    );
+}
+
+#[test]
+fn freeze_and_merge() {
+    let mut a = DictionaryEncoder::new();
+    a.encode([10, 20, 30]);
+    let mut b = DictionaryEncoder::new();
+    let b_codes = b.encode([30, 40, 30, 10]);
+
+    let remap = a.merge(&b);
+    assert_eq!(remap, [2, 3, 0]);
+    assert_eq!(a.frequencies(), [2, 1, 3, 1]);
+    let a_codes: Vec<_> = b_codes.iter().map(|&c| remap[c as usize]).collect();
+    assert_eq!(a.decode(&a_codes).unwrap(), [&30, &40, &30, &10]);
+
+    let frozen = a.freeze();
+    assert_eq!(frozen.as_slice(), [10, 20, 30, 40]);
+    assert_eq!(frozen[3], 40);
+    assert_eq!(frozen.get(4), None);
+    assert_eq!(frozen.decode(&a_codes).unwrap(), [&30, &40, &30, &10]);
+}
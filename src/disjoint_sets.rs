@@ -0,0 +1,523 @@
+//! Disjoint-set (union-find) forest over [`HashSlabSet`] elements
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    iter::FusedIterator,
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+
+use crate::{set, HashSlabSet};
+
+#[cfg(test)]
+mod tests;
+
+// Per-element bookkeeping, indexed by the element index in the set.
+//
+// Members of a group are linked in a circular doubly linked list, which
+// makes iteration over a group and removal of an element cheap. `size` is
+// only meaningful for the root of a group.
+#[derive(Debug, Clone, Copy, Default)]
+struct Node {
+    parent: usize,
+    size: usize,
+    next: usize,
+    prev: usize,
+}
+
+/// A disjoint-set forest (union-find) over hashable elements.
+///
+/// Each element is stored in a [`HashSlabSet`] and is identified by its
+/// stable index. Groups are identified by the index of their representative
+/// (root) element, which may change after [`union`][Self::union] or
+/// [`remove`][Self::remove].
+///
+/// [`find`][Self::find] uses path compression and [`union`][Self::union]
+/// merges by size, so both run in nearly constant amortized time.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::DisjointSets;
+/// let mut sets = DisjointSets::new();
+/// for host in ["a.local", "b.local", "c.local", "d.local"] {
+///     sets.make_set(host);
+/// }
+///
+/// sets.union(&"a.local", &"b.local");
+/// sets.union(&"c.local", &"b.local");
+///
+/// assert!(sets.same_set(&"a.local", &"c.local"));
+/// assert!(!sets.same_set(&"a.local", &"d.local"));
+/// assert_eq!(sets.set_size(&"b.local"), Some(3));
+/// assert_eq!(sets.set_count(), 2);
+///
+/// sets.remove(&"b.local");
+/// assert!(sets.same_set(&"a.local", &"c.local"));
+/// assert_eq!(sets.set_size(&"a.local"), Some(2));
+/// ```
+#[cfg(feature = "std")]
+pub struct DisjointSets<T, S = RandomState> {
+    set: HashSlabSet<T, S>,
+    nodes: Vec<Node>,
+    count: usize,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct DisjointSets<T, S> {
+    set: HashSlabSet<T, S>,
+    nodes: Vec<Node>,
+    count: usize,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<T> DisjointSets<T> {
+    /// Creates an empty `DisjointSets`.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty `DisjointSets` with the specified capacity.
+    pub fn with_capacity(n: usize) -> Self {
+        Self::with_capacity_and_hasher(n, Default::default())
+    }
+}
+
+impl<T, S> DisjointSets<T, S> {
+    /// Creates an empty `DisjointSets` with the specified capacity, using
+    /// `hash_builder` to hash the elements.
+    pub fn with_capacity_and_hasher(n: usize, hash_builder: S) -> Self {
+        Self {
+            set: HashSlabSet::with_capacity_and_hasher(n, hash_builder),
+            nodes: Vec::with_capacity(n),
+            count: 0,
+        }
+    }
+
+    /// Creates an empty `DisjointSets` which will use the given hash builder.
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            set: HashSlabSet::with_hasher(hash_builder),
+            nodes: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// Return the number of elements.
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Returns true if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Return the number of disjoint groups.
+    pub fn set_count(&self) -> usize {
+        self.count
+    }
+
+    /// Return the underlying set of elements.
+    pub fn as_set(&self) -> &HashSlabSet<T, S> {
+        &self.set
+    }
+
+    /// Get an element by index.
+    pub fn get_index(&self, index: usize) -> Option<&T> {
+        self.set.get_index(index)
+    }
+
+    /// Return the index of the group representative for the element at `index`.
+    ///
+    /// Compresses the path from the element to the root along the way.
+    pub fn find_index(&mut self, index: usize) -> Option<usize> {
+        if !self.set.map.slab.contains(index) {
+            return None;
+        }
+        let mut root = index;
+        while self.nodes[root].parent != root {
+            root = self.nodes[root].parent;
+        }
+        let mut current = index;
+        while current != root {
+            let parent = self.nodes[current].parent;
+            self.nodes[current].parent = root;
+            current = parent;
+        }
+        Some(root)
+    }
+
+    /// Merge the groups containing elements at indices `a` and `b`.
+    ///
+    /// Returns the index of the representative of the merged group, or `None`
+    /// if either index is not present.
+    pub fn union_index(&mut self, a: usize, b: usize) -> Option<usize> {
+        let a = self.find_index(a)?;
+        let b = self.find_index(b)?;
+        if a == b {
+            return Some(a);
+        }
+        let (root, child) = if self.nodes[a].size < self.nodes[b].size {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        self.nodes[child].parent = root;
+        self.nodes[root].size += self.nodes[child].size;
+        // Splice the two circular member lists
+        let root_next = self.nodes[root].next;
+        let child_next = self.nodes[child].next;
+        self.nodes[root].next = child_next;
+        self.nodes[child_next].prev = root;
+        self.nodes[child].next = root_next;
+        self.nodes[root_next].prev = child;
+        self.count -= 1;
+        Some(root)
+    }
+
+    /// Returns `true` if elements at both indices belong to the same group.
+    pub fn same_set_index(&mut self, a: usize, b: usize) -> bool {
+        match (self.find_index(a), self.find_index(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Return the number of elements in the group containing `index`.
+    pub fn set_size_index(&mut self, index: usize) -> Option<usize> {
+        self.find_index(index).map(|root| self.nodes[root].size)
+    }
+
+    /// An iterator over the index-element pairs of the group containing `index`.
+    ///
+    /// The iterator is empty if there is no element with such index.
+    pub fn members(&self, index: usize) -> Members<'_, T, S> {
+        let remaining = if self.set.map.slab.contains(index) {
+            let mut root = index;
+            while self.nodes[root].parent != root {
+                root = self.nodes[root].parent;
+            }
+            self.nodes[root].size
+        } else {
+            0
+        };
+        Members {
+            sets: self,
+            current: index,
+            remaining,
+        }
+    }
+
+    /// An iterator over all groups, each one produced as a [`Members`] iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::DisjointSets;
+    /// let mut sets: DisjointSets<_> = (1..=6).collect();
+    /// for n in 1..=6 {
+    ///     // group numbers by parity
+    ///     sets.union(&n, &(2 - n % 2));
+    /// }
+    /// let mut groups: Vec<Vec<i32>> = sets
+    ///     .sets()
+    ///     .map(|members| {
+    ///         let mut group: Vec<_> = members.map(|(_, &n)| n).collect();
+    ///         group.sort();
+    ///         group
+    ///     })
+    ///     .collect();
+    /// groups.sort();
+    /// assert_eq!(groups, [vec![1, 3, 5], vec![2, 4, 6]]);
+    /// ```
+    pub fn sets(&self) -> Sets<'_, T, S> {
+        Sets {
+            sets: self,
+            iter: self.set.iter_full(),
+        }
+    }
+
+    /// Remove all elements.
+    pub fn clear(&mut self) {
+        self.set.clear();
+        self.nodes.clear();
+        self.count = 0;
+    }
+}
+
+impl<T, S> DisjointSets<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Add an element as a new single-element group and return its index.
+    ///
+    /// If an equivalent element already exists, it is left in its group and
+    /// its index is returned.
+    pub fn make_set(&mut self, value: T) -> usize {
+        let (index, new) = self.set.insert_full(value);
+        if new {
+            if index >= self.nodes.len() {
+                self.nodes.resize(index + 1, Node::default());
+            }
+            self.nodes[index] = Node {
+                parent: index,
+                size: 1,
+                next: index,
+                prev: index,
+            };
+            self.count += 1;
+        }
+        index
+    }
+
+    /// Return the index of an element, if it exists.
+    pub fn get_index_of<Q>(&self, value: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.set.get_index_of(value)
+    }
+
+    /// Returns `true` if the element exists.
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.set.contains(value)
+    }
+
+    /// Return the index of the group representative for `value`.
+    pub fn find<Q>(&mut self, value: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        let index = self.set.get_index_of(value)?;
+        self.find_index(index)
+    }
+
+    /// Merge the groups containing `a` and `b`.
+    ///
+    /// Returns the index of the representative of the merged group, or `None`
+    /// if either element is not present.
+    pub fn union<Q>(&mut self, a: &Q, b: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        let a = self.set.get_index_of(a)?;
+        let b = self.set.get_index_of(b)?;
+        self.union_index(a, b)
+    }
+
+    /// Returns `true` if `a` and `b` belong to the same group.
+    pub fn same_set<Q>(&mut self, a: &Q, b: &Q) -> bool
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        match (self.set.get_index_of(a), self.set.get_index_of(b)) {
+            (Some(a), Some(b)) => self.same_set_index(a, b),
+            _ => false,
+        }
+    }
+
+    /// Return the number of elements in the group containing `value`.
+    pub fn set_size<Q>(&mut self, value: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        let index = self.set.get_index_of(value)?;
+        self.set_size_index(index)
+    }
+
+    /// Remove the element at `index` and return it.
+    ///
+    /// Other elements of its group stay together. Removing an element from a
+    /// group with other members takes time proportional to the group size.
+    pub fn remove_index(&mut self, index: usize) -> Option<T> {
+        let root = self.find_index(index)?;
+        let size = self.nodes[root].size;
+        if size == 1 {
+            self.count -= 1;
+        } else {
+            let Node { next, prev, .. } = self.nodes[index];
+            self.nodes[prev].next = next;
+            self.nodes[next].prev = prev;
+            let root = if root == index { next } else { root };
+            // Members may point to the removed element, so flatten the whole group
+            let mut member = root;
+            loop {
+                self.nodes[member].parent = root;
+                member = self.nodes[member].next;
+                if member == root {
+                    break;
+                }
+            }
+            self.nodes[root].size = size - 1;
+        }
+        self.set.map.remove_index(index).map(|(value, _)| value)
+    }
+
+    /// Remove an element and return it.
+    pub fn remove<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        let index = self.set.get_index_of(value)?;
+        self.remove_index(index)
+    }
+}
+
+impl<T, S> Clone for DisjointSets<T, S>
+where
+    T: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set.clone(),
+            nodes: self.nodes.clone(),
+            count: self.count,
+        }
+    }
+}
+
+impl<T, S> fmt::Debug for DisjointSets<T, S>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.sets()).finish()
+    }
+}
+
+impl<T, S> Default for DisjointSets<T, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_capacity_and_hasher(0, S::default())
+    }
+}
+
+impl<T, S> Extend<T> for DisjointSets<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Add every element as a new single-element group.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|value| {
+            self.make_set(value);
+        });
+    }
+}
+
+impl<T, S> FromIterator<T> for DisjointSets<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iterable: I) -> Self {
+        let mut sets = Self::default();
+        sets.extend(iterable);
+        sets
+    }
+}
+
+/// An iterator over the index-element pairs of a single group of a [`DisjointSets`].
+///
+/// This `struct` is created by the [`DisjointSets::members`] method.
+/// See its documentation for more.
+pub struct Members<'a, T, S> {
+    sets: &'a DisjointSets<T, S>,
+    current: usize,
+    remaining: usize,
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<T, S> Clone for Members<'_, T, S> {
+    fn clone(&self) -> Self {
+        Members {
+            sets: self.sets,
+            current: self.current,
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for Members<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T, S> Iterator for Members<'a, T, S> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let index = self.current;
+        self.current = self.sets.nodes[index].next;
+        self.sets.get_index(index).map(|value| (index, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, S> ExactSizeIterator for Members<'_, T, S> {
+    fn len(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<T, S> FusedIterator for Members<'_, T, S> {}
+
+/// An iterator over the groups of a [`DisjointSets`].
+///
+/// This `struct` is created by the [`DisjointSets::sets`] method.
+/// See its documentation for more.
+pub struct Sets<'a, T, S> {
+    sets: &'a DisjointSets<T, S>,
+    iter: set::IterFull<'a, T>,
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<T, S> Clone for Sets<'_, T, S> {
+    fn clone(&self) -> Self {
+        Sets {
+            sets: self.sets,
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for Sets<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T, S> Iterator for Sets<'a, T, S> {
+    type Item = Members<'a, T, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sets = self.sets;
+        self.iter
+            .by_ref()
+            .find(|&(index, _)| sets.nodes[index].parent == index)
+            .map(|(index, _)| sets.members(index))
+    }
+}
+
+impl<T, S> FusedIterator for Sets<'_, T, S> {}
@@ -0,0 +1,107 @@
+use super::*;
+use std::{format, vec::Vec};
+
+fn group<T: Copy + Ord, S>(sets: &DisjointSets<T, S>, index: usize) -> Vec<T> {
+    let mut vec: Vec<_> = sets.members(index).map(|(_, &v)| v).collect();
+    vec.sort_unstable();
+    vec
+}
+
+#[test]
+fn make_set_is_idempotent() {
+    let mut sets = DisjointSets::new();
+    let a = sets.make_set('a');
+    let b = sets.make_set('b');
+    sets.union_index(a, b);
+    assert_eq!(sets.make_set('a'), a);
+    assert_eq!(sets.set_count(), 1);
+    assert_eq!(sets.set_size(&'a'), Some(2));
+
+    assert_eq!(format!("{:?}", sets.sets()), "[{0: 'a', 1: 'b'}]");
+}
+
+#[test]
+fn union_and_find() {
+    let mut sets: DisjointSets<_> = (0..10).collect();
+    assert_eq!(sets.set_count(), 10);
+
+    for n in 1..5 {
+        sets.union(&0, &n);
+    }
+    sets.union(&7, &8);
+    sets.union(&8, &9);
+
+    assert_eq!(sets.set_count(), 4);
+    assert_eq!(sets.find(&3), sets.find(&0));
+    assert_ne!(sets.find(&3), sets.find(&7));
+    assert_eq!(sets.find(&42), None);
+    assert_eq!(sets.union(&0, &42), None);
+    assert_eq!(group(&sets, sets.get_index_of(&9).unwrap()), [7, 8, 9]);
+    assert_eq!(group(&sets, 2), [0, 1, 2, 3, 4]);
+    assert_eq!(group(&sets, 5), [5]);
+
+    // Union of the same group is a no-op
+    let root = sets.find(&1);
+    assert_eq!(sets.union(&1, &4), root);
+    assert_eq!(sets.set_count(), 4);
+    assert_eq!(sets.sets().count(), 4);
+}
+
+#[test]
+fn path_compression() {
+    let mut sets: DisjointSets<_> = (0..4).collect();
+    // Build a chain by always attaching the bigger group to a single element
+    sets.union_index(0, 1);
+    sets.union_index(2, 3);
+    sets.union_index(0, 2);
+    let root = sets.find_index(3).unwrap();
+    for index in 0..4 {
+        assert_eq!(sets.nodes[index].parent, root);
+    }
+}
+
+#[test]
+fn remove_root_keeps_group() {
+    let mut sets: DisjointSets<_> = (0..5).collect();
+    for n in 1..5 {
+        sets.union(&0, &n);
+    }
+    let root = sets.find(&0).unwrap();
+    let removed = *sets.get_index(root).unwrap();
+    assert_eq!(sets.remove_index(root), Some(removed));
+
+    assert_eq!(sets.len(), 4);
+    assert_eq!(sets.set_count(), 1);
+    let members: Vec<_> = (0..5).filter(|&n| n != removed).collect();
+    for &n in &members {
+        assert!(sets.same_set(&members[0], &n));
+        assert_eq!(sets.set_size(&n), Some(4));
+    }
+    assert_eq!(
+        group(&sets, sets.get_index_of(&members[0]).unwrap()),
+        members
+    );
+}
+
+#[test]
+fn remove_inner_and_single() {
+    let mut sets: DisjointSets<_> = (0..6).collect();
+    sets.union(&0, &1);
+    sets.union(&2, &3);
+    sets.union(&0, &2);
+    assert_eq!(sets.set_count(), 3);
+
+    assert_eq!(sets.remove(&2), Some(2));
+    assert_eq!(sets.set_count(), 3);
+    assert!(sets.same_set(&1, &3));
+    assert_eq!(sets.set_size(&0), Some(3));
+
+    assert_eq!(sets.remove(&5), Some(5));
+    assert_eq!(sets.set_count(), 2);
+    assert_eq!(sets.remove(&5), None);
+
+    // Freed index is reused by a new single-element group
+    let index = sets.make_set(10);
+    assert_eq!(sets.set_size_index(index), Some(1));
+    assert_eq!(sets.set_count(), 3);
+}
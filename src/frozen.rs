@@ -0,0 +1,410 @@
+//! A read-only [`HashSlabMap`] frozen into a flat byte buffer
+//!
+//! A map with [plain-old-data](Pod) keys and values can be frozen with
+//! [`HashSlabMap::to_frozen_bytes`] or [`HashSlabMap::write_frozen`], and
+//! looked up in place with a [`FrozenHashSlabMap`], e.g. over a memory-mapped
+//! file shared by many processes. Entries keep their indices.
+//!
+//! # Format
+//!
+//! A frozen map starts with a 40-byte header, with integers in little-endian
+//! byte order:
+//!
+//! | Bytes | Content                                                 |
+//! |-------|---------------------------------------------------------|
+//! | 4     | Magic `HSFZ`                                            |
+//! | 2     | Format version, currently `1`                           |
+//! | 1     | Byte order of keys and values: `0` little, `1` big      |
+//! | 1     | Reserved, `0`                                           |
+//! | 4     | Size of a key                                           |
+//! | 4     | Size of a value                                         |
+//! | 8     | Number of entries                                       |
+//! | 8     | End of the indices, one past the highest occupied index |
+//! | 8     | Number of buckets                                       |
+//!
+//! It is followed by a bitmap of the occupied indices, one bit per index,
+//! least significant bit first, then the records of all indices up to the end,
+//! each the bytes of the key followed by the bytes of the value, zeroed for
+//! vacant indices. The file ends with the buckets of
+//! an open-addressing hash table, as `u64` indices plus one, or zero for an
+//! empty bucket. Keys are hashed with 64-bit FNV-1a over their bytes, and
+//! probing starts at the bucket of the hash modulo the number of buckets.
+use alloc::{vec, vec::Vec};
+use core::{fmt, iter::FusedIterator, marker::PhantomData, mem, ptr, slice};
+
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use thiserror::Error;
+
+use crate::HashSlabMap;
+
+#[cfg(test)]
+mod tests;
+
+const MAGIC: &[u8; 4] = b"HSFZ";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 40;
+
+#[cfg(target_endian = "little")]
+pub(crate) const ENDIAN: u8 = 0;
+#[cfg(target_endian = "big")]
+pub(crate) const ENDIAN: u8 = 1;
+
+/// A plain-old-data type, which is stored in a frozen map by its bytes.
+///
+/// # Safety
+///
+/// The type must have no padding bytes, and every bit pattern of its size
+/// must be a valid value. Equal values must have the same bytes.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {$(
+        // SAFETY: primitive integers have no padding and no invalid values
+        unsafe impl Pod for $ty {}
+    )*};
+}
+
+impl_pod!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+// SAFETY: arrays of `Pod` have no padding between elements
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+pub(crate) fn bytes_of<T: Pod>(value: &T) -> &[u8] {
+    // SAFETY: `Pod` types have no padding, so every byte is initialized
+    unsafe { slice::from_raw_parts((value as *const T).cast::<u8>(), mem::size_of::<T>()) }
+}
+
+pub(crate) fn read<T: Pod>(bytes: &[u8]) -> T {
+    assert!(bytes.len() >= mem::size_of::<T>());
+    // SAFETY: the bytes are in bounds and every bit pattern is a valid `T`
+    unsafe { ptr::read_unaligned(bytes.as_ptr().cast::<T>()) }
+}
+
+// 64-bit FNV-1a, which does not depend on the platform
+pub(crate) fn hash_bytes(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub(crate) fn bucket_of(hash: u64, buckets: u64) -> usize {
+    (hash % buckets) as usize
+}
+
+/// The error type for opening a [`FrozenHashSlabMap`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrozenError {
+    #[error("not a frozen hashslab map")]
+    BadMagic,
+    #[error("unsupported frozen map version {0}")]
+    UnsupportedVersion(u16),
+    #[error("frozen map was written with another byte order")]
+    ByteOrder,
+    #[error("frozen map has {key_size}-byte keys and {value_size}-byte values")]
+    TypeMismatch { key_size: u32, value_size: u32 },
+    #[error("frozen map header is inconsistent")]
+    InvalidHeader,
+    #[error("frozen map is {found} bytes long instead of {expected}")]
+    Length { expected: u64, found: u64 },
+}
+
+// Write the frozen form of `map` through `write`.
+fn freeze<K, V, S, E>(
+    map: &HashSlabMap<K, V, S>,
+    mut write: impl FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E>
+where
+    K: Pod,
+    V: Pod,
+{
+    let end = map
+        .iter_full()
+        .map(|(index, _, _)| index + 1)
+        .max()
+        .unwrap_or(0);
+    // At most half full, so probing always ends on an empty bucket
+    let mut buckets = vec![0u64; (map.len() * 2).next_power_of_two()];
+    let mut bitmap = vec![0u8; end.div_ceil(8)];
+    for (index, key, _) in map.iter_full() {
+        bitmap[index / 8] |= 1 << (index % 8);
+        let mut bucket = bucket_of(hash_bytes(bytes_of(key)), buckets.len() as u64);
+        while buckets[bucket] != 0 {
+            bucket = (bucket + 1) % buckets.len();
+        }
+        buckets[bucket] = index as u64 + 1;
+    }
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&[ENDIAN, 0]);
+    header.extend_from_slice(&(mem::size_of::<K>() as u32).to_le_bytes());
+    header.extend_from_slice(&(mem::size_of::<V>() as u32).to_le_bytes());
+    for field in [map.len(), end, buckets.len()] {
+        header.extend_from_slice(&(field as u64).to_le_bytes());
+    }
+    write(&header)?;
+    write(&bitmap)?;
+
+    let vacant = vec![0; mem::size_of::<K>() + mem::size_of::<V>()];
+    for index in 0..end {
+        match map.get_index(index) {
+            Some((key, value)) => {
+                write(bytes_of(key))?;
+                write(bytes_of(value))?;
+            }
+            None => write(&vacant)?,
+        }
+    }
+    let buckets: Vec<u8> = buckets
+        .iter()
+        .flat_map(|bucket| bucket.to_le_bytes())
+        .collect();
+    write(&buckets)
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Pod,
+    V: Pod,
+{
+    /// Return the map frozen into the [`frozen`](crate::frozen) format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::{FrozenHashSlabMap, HashSlabMap};
+    /// let mut map = HashSlabMap::new();
+    /// map.insert(10u32, [1u8; 4]);
+    /// map.insert(20, [2; 4]);
+    /// map.remove(&10);
+    ///
+    /// let bytes = map.to_frozen_bytes();
+    /// let frozen = FrozenHashSlabMap::<u32, [u8; 4]>::new(&bytes).unwrap();
+    /// assert_eq!(frozen.get(&20), Some([2; 4]));
+    /// assert_eq!(frozen.get_index_of(&20), Some(1));
+    /// assert_eq!(frozen.get_index(0), None);
+    /// ```
+    pub fn to_frozen_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let _ = freeze(self, |chunk| {
+            bytes.extend_from_slice(chunk);
+            Ok::<_, ()>(())
+        });
+        bytes
+    }
+
+    /// Write the map frozen into the [`frozen`](crate::frozen) format to
+    /// `writer`, e.g. a file to memory-map afterwards.
+    ///
+    /// The records are written one by one, so `writer` should be buffered.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn write_frozen<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        freeze(self, |chunk| writer.write_all(chunk))?;
+        writer.flush()
+    }
+}
+
+/// A read-only view of a [frozen](crate::frozen) [`HashSlabMap`] over a byte
+/// buffer.
+///
+/// Keys and values are copied out of the buffer, which needs no particular
+/// alignment.
+pub struct FrozenHashSlabMap<'a, K, V> {
+    bitmap: &'a [u8],
+    records: &'a [u8],
+    buckets: &'a [u8],
+    len: usize,
+    end: usize,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> Clone for FrozenHashSlabMap<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for FrozenHashSlabMap<'_, K, V> {}
+
+impl<'a, K: Pod, V: Pod> FrozenHashSlabMap<'a, K, V> {
+    const RECORD: usize = mem::size_of::<K>() + mem::size_of::<V>();
+
+    /// Open a frozen map in `bytes`, checking its header and length.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, FrozenError> {
+        let found = bytes.len() as u64;
+        let short = FrozenError::Length {
+            expected: HEADER_LEN as u64,
+            found,
+        };
+        let header = bytes.get(..HEADER_LEN).ok_or(short)?;
+        if &header[..4] != MAGIC {
+            return Err(FrozenError::BadMagic);
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != VERSION {
+            return Err(FrozenError::UnsupportedVersion(version));
+        }
+        if header[6] != ENDIAN {
+            return Err(FrozenError::ByteOrder);
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(read(&header[at..]));
+        let u64_at = |at: usize| u64::from_le_bytes(read(&header[at..]));
+        let (key_size, value_size) = (u32_at(8), u32_at(12));
+        if (key_size as usize, value_size as usize) != (mem::size_of::<K>(), mem::size_of::<V>()) {
+            return Err(FrozenError::TypeMismatch {
+                key_size,
+                value_size,
+            });
+        }
+        let (len, end, buckets) = (u64_at(16), u64_at(24), u64_at(32));
+
+        let bitmap_len = end.div_ceil(8);
+        let records_len = end.checked_mul(Self::RECORD as u64);
+        let buckets_len = buckets.checked_mul(8);
+        let expected = records_len.zip(buckets_len).and_then(|(records, buckets)| {
+            (HEADER_LEN as u64)
+                .checked_add(bitmap_len)?
+                .checked_add(records)?
+                .checked_add(buckets)
+        });
+        if expected != Some(found) {
+            return Err(FrozenError::Length {
+                expected: expected.unwrap_or(u64::MAX),
+                found,
+            });
+        }
+        if len > end || (buckets == 0 && len > 0) {
+            return Err(FrozenError::InvalidHeader);
+        }
+
+        // The lengths fit in the buffer, so they fit in `usize`
+        let (bytes, buckets) = bytes.split_at(bytes.len() - buckets_len.unwrap_or(0) as usize);
+        let (bitmap, records) = bytes[HEADER_LEN..].split_at(bitmap_len as usize);
+        Ok(Self {
+            bitmap,
+            records,
+            buckets,
+            len: len as usize,
+            end: end as usize,
+            marker: PhantomData,
+        })
+    }
+
+    /// Return the number of key-value pairs in the map.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn record(&self, index: usize) -> Option<&'a [u8]> {
+        if index >= self.end || self.bitmap[index / 8] & (1 << (index % 8)) == 0 {
+            return None;
+        }
+        let start = index * Self::RECORD;
+        Some(&self.records[start..start + Self::RECORD])
+    }
+
+    /// Get a key-value pair by index.
+    pub fn get_index(&self, index: usize) -> Option<(K, V)> {
+        let record = self.record(index)?;
+        let (key, value) = record.split_at(mem::size_of::<K>());
+        Some((read(key), read(value)))
+    }
+
+    /// Return `true` if the index is occupied.
+    pub fn contains_index(&self, index: usize) -> bool {
+        self.record(index).is_some()
+    }
+
+    /// Return the index of `key`, if it is present.
+    pub fn get_index_of(&self, key: &K) -> Option<usize> {
+        let key = bytes_of(key);
+        let buckets = (self.buckets.len() / 8) as u64;
+        if buckets == 0 {
+            return None;
+        }
+        let start = bucket_of(hash_bytes(key), buckets);
+        // Bounded, as the buffer is not checked for empty buckets
+        (0..buckets as usize)
+            .map(|probe| {
+                let at = (start + probe) % buckets as usize * 8;
+                u64::from_le_bytes(read(&self.buckets[at..]))
+            })
+            .take_while(|&bucket| bucket != 0)
+            .filter_map(|bucket| usize::try_from(bucket - 1).ok())
+            .find(|&index| {
+                self.record(index)
+                    .is_some_and(|record| &record[..key.len()] == key)
+            })
+    }
+
+    /// Return the index, key and value stored for `key`, if it is present.
+    pub fn get_full(&self, key: &K) -> Option<(usize, K, V)> {
+        let index = self.get_index_of(key)?;
+        let (key, value) = self.get_index(index)?;
+        Some((index, key, value))
+    }
+
+    /// Return the value stored for `key`, if it is present.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Return `true` if an equivalent to `key` exists in the map.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get_index_of(key).is_some()
+    }
+
+    /// An iterator visiting all key-value pairs with their indices, in index
+    /// order.
+    pub fn iter_full(&self) -> IterFull<'a, K, V> {
+        IterFull {
+            map: *self,
+            index: 0,
+        }
+    }
+}
+
+impl<K: Pod + fmt::Debug, V: Pod + fmt::Debug> fmt::Debug for FrozenHashSlabMap<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter_full().map(|(i, k, v)| ((i, k), v)))
+            .finish()
+    }
+}
+
+/// An iterator over the entries of a [`FrozenHashSlabMap`] with their
+/// indices.
+pub struct IterFull<'a, K, V> {
+    map: FrozenHashSlabMap<'a, K, V>,
+    index: usize,
+}
+
+impl<K: Pod, V: Pod> Iterator for IterFull<'_, K, V> {
+    type Item = (usize, K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.map.end {
+            let index = self.index;
+            self.index += 1;
+            if let Some((key, value)) = self.map.get_index(index) {
+                return Some((index, key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.map.end - self.index))
+    }
+}
+
+impl<K: Pod, V: Pod> FusedIterator for IterFull<'_, K, V> {}
@@ -0,0 +1,72 @@
+use super::*;
+use std::{env, format, fs, io::BufWriter, vec::Vec};
+
+fn sample() -> HashSlabMap<u64, [u32; 3]> {
+    let mut map: HashSlabMap<u64, [u32; 3]> =
+        (0..1000u64).map(|i| (i * 7919, [i as u32, 1, 2])).collect();
+    map.retain(|k, _| k % 3 != 0);
+    map
+}
+
+#[test]
+fn frozen_lookups() {
+    let map = sample();
+    let path = env::temp_dir().join(format!("hashslab-frozen-{}", std::process::id()));
+    map.write_frozen(BufWriter::new(fs::File::create(&path).unwrap()))
+        .unwrap();
+    let bytes = fs::read(&path).unwrap();
+    let _ = fs::remove_file(&path);
+    assert_eq!(bytes, map.to_frozen_bytes());
+
+    let frozen = FrozenHashSlabMap::<u64, [u32; 3]>::new(&bytes).unwrap();
+    assert_eq!(frozen.len(), map.len());
+    for (index, key, value) in map.iter_full() {
+        assert_eq!(frozen.get_full(key), Some((index, *key, *value)));
+        assert_eq!(frozen.get_index(index), Some((*key, *value)));
+    }
+    assert_eq!(frozen.get(&0), None);
+    assert_eq!(frozen.get_index(0), None);
+    assert!(!frozen.contains_index(1000));
+
+    let mut expected: Vec<_> = map.iter_full().map(|(i, k, v)| (i, *k, *v)).collect();
+    expected.sort_unstable();
+    assert_eq!(frozen.iter_full().collect::<Vec<_>>(), expected);
+
+    let empty = HashSlabMap::<u64, u64>::new().to_frozen_bytes();
+    let frozen = FrozenHashSlabMap::<u64, u64>::new(&empty).unwrap();
+    assert!(frozen.is_empty());
+    assert_eq!(frozen.get(&1), None);
+}
+
+#[test]
+fn rejects_mismatching_buffers() {
+    let bytes = sample().to_frozen_bytes();
+    assert_eq!(
+        FrozenHashSlabMap::<u64, [u32; 2]>::new(&bytes).unwrap_err(),
+        FrozenError::TypeMismatch {
+            key_size: 8,
+            value_size: 12
+        }
+    );
+    assert!(matches!(
+        FrozenHashSlabMap::<u64, [u32; 3]>::new(&bytes[..bytes.len() - 1]),
+        Err(FrozenError::Length { .. })
+    ));
+    assert!(matches!(
+        FrozenHashSlabMap::<u64, [u32; 3]>::new(&bytes[..10]),
+        Err(FrozenError::Length { .. })
+    ));
+    let mut other = bytes.clone();
+    other[0] = b'X';
+    assert_eq!(
+        FrozenHashSlabMap::<u64, [u32; 3]>::new(&other).unwrap_err(),
+        FrozenError::BadMagic
+    );
+    // Absurd lengths in the header do not overflow
+    let mut other = bytes.clone();
+    other[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(
+        FrozenHashSlabMap::<u64, [u32; 3]>::new(&other),
+        Err(FrozenError::Length { .. })
+    ));
+}
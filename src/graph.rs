@@ -0,0 +1,426 @@
+//! A directed graph with stable node and edge indices
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+use slab::Slab;
+
+use crate::HashSlabMap;
+
+mod iter;
+pub use iter::{Bfs, Dfs, Edges, EdgesFull, Neighbors, Nodes, Topo};
+
+#[cfg(test)]
+mod tests;
+
+/// Edge direction relative to a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Edges going from the node.
+    Outgoing,
+    /// Edges coming to the node.
+    Incoming,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Adjacency {
+    outgoing: Vec<usize>,
+    incoming: Vec<usize>,
+}
+
+impl Adjacency {
+    fn get(&self, dir: Direction) -> &[usize] {
+        match dir {
+            Direction::Outgoing => &self.outgoing,
+            Direction::Incoming => &self.incoming,
+        }
+    }
+
+    fn detach(&mut self, dir: Direction, edge: usize) {
+        let edges = match dir {
+            Direction::Outgoing => &mut self.outgoing,
+            Direction::Incoming => &mut self.incoming,
+        };
+        if let Some(pos) = edges.iter().position(|&e| e == edge) {
+            edges.swap_remove(pos);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EdgeData<E> {
+    source: usize,
+    target: usize,
+    weight: E,
+}
+
+/// A directed graph with hashable node keys and stable indices.
+///
+/// Nodes are stored in a [`HashSlabMap`], so each node can be found both by
+/// its key and by its index. Edges are stored in a slab of their own and carry
+/// a weight of type `E`. Node and edge indices never change when other nodes
+/// or edges are removed, although the index of a removed item may be reused
+/// by a later insertion.
+///
+/// Parallel edges and self-loops are allowed.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::HashSlabGraph;
+/// let mut graph = HashSlabGraph::new();
+///
+/// let db = graph.add_node("db");
+/// let api = graph.add_node("api");
+/// let web = graph.add_node("web");
+///
+/// graph.add_edge(web, api, "http");
+/// graph.add_edge(api, db, "sql");
+///
+/// let order: Vec<_> = graph.topo().map(|i| graph[i]).collect();
+/// assert_eq!(order, ["web", "api", "db"]);
+///
+/// // Removing a node removes all of its edges, other indices stay valid.
+/// graph.remove_node(&"api");
+/// assert_eq!(graph.edge_count(), 0);
+/// assert_eq!(graph.node_index(&"web"), Some(web));
+/// ```
+#[cfg(feature = "std")]
+pub struct HashSlabGraph<N, E, S = RandomState> {
+    nodes: HashSlabMap<N, Adjacency, S>,
+    edges: Slab<EdgeData<E>>,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct HashSlabGraph<N, E, S> {
+    nodes: HashSlabMap<N, Adjacency, S>,
+    edges: Slab<EdgeData<E>>,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<N, E> HashSlabGraph<N, E> {
+    /// Creates an empty `HashSlabGraph`.
+    pub fn new() -> Self {
+        Self::with_capacity(0, 0)
+    }
+
+    /// Creates an empty `HashSlabGraph` with room for `nodes` nodes and `edges` edges.
+    pub fn with_capacity(nodes: usize, edges: usize) -> Self {
+        Self::with_capacity_and_hasher(nodes, edges, Default::default())
+    }
+}
+
+impl<N, E, S> HashSlabGraph<N, E, S> {
+    /// Creates an empty `HashSlabGraph` with the specified capacities, using
+    /// `builder` to hash the node keys.
+    pub fn with_capacity_and_hasher(nodes: usize, edges: usize, builder: S) -> Self {
+        Self {
+            nodes: HashSlabMap::with_capacity_and_hasher(nodes, builder),
+            edges: Slab::with_capacity(edges),
+        }
+    }
+
+    /// Creates an empty `HashSlabGraph` which will use the given hash builder.
+    pub const fn with_hasher(builder: S) -> Self {
+        Self {
+            nodes: HashSlabMap::with_hasher(builder),
+            edges: Slab::new(),
+        }
+    }
+
+    /// Return the number of nodes in the graph.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Return the number of edges in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Returns true if the graph contains no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Get a node key by index.
+    pub fn node(&self, index: usize) -> Option<&N> {
+        self.nodes.get_index(index).map(|(node, _)| node)
+    }
+
+    /// Return `true` if a node is associated with the given index.
+    pub fn contains_node_index(&self, index: usize) -> bool {
+        self.nodes.slab.contains(index)
+    }
+
+    /// Get an edge weight by index.
+    pub fn edge(&self, edge: usize) -> Option<&E> {
+        self.edges.get(edge).map(|data| &data.weight)
+    }
+
+    /// Get a mutable reference to an edge weight by index.
+    pub fn edge_mut(&mut self, edge: usize) -> Option<&mut E> {
+        self.edges.get_mut(edge).map(|data| &mut data.weight)
+    }
+
+    /// Return the source and target node indices of an edge.
+    pub fn edge_endpoints(&self, edge: usize) -> Option<(usize, usize)> {
+        self.edges.get(edge).map(|data| (data.source, data.target))
+    }
+
+    /// Return the index of an edge from `source` to `target`, if there is one.
+    ///
+    /// If the graph has parallel edges, any one of them is returned.
+    pub fn find_edge(&self, source: usize, target: usize) -> Option<usize> {
+        self.nodes
+            .get_index_value(source)?
+            .outgoing
+            .iter()
+            .copied()
+            .find(|&edge| self.edges[edge].target == target)
+    }
+
+    /// An iterator over the index-node pairs in arbitrary order.
+    pub fn nodes(&self) -> Nodes<'_, N> {
+        Nodes::new(self.nodes.full_keys())
+    }
+
+    /// An iterator over all edges in arbitrary order.
+    /// The iterator element type is `(usize, usize, usize, &'a E)`: the edge
+    /// index, its source, its target and its weight.
+    pub fn edges_full(&self) -> EdgesFull<'_, E> {
+        EdgesFull::new(self.edges.iter())
+    }
+
+    /// An iterator over the nodes connected to `index` by an outgoing edge.
+    ///
+    /// A node is produced once per connecting edge. The iterator is empty if
+    /// there is no node with such index.
+    pub fn neighbors(&self, index: usize) -> Neighbors<'_, E> {
+        self.neighbors_directed(index, Direction::Outgoing)
+    }
+
+    /// An iterator over the nodes connected to `index` in the given direction.
+    ///
+    /// With [`Direction::Outgoing`] the targets of the outgoing edges are
+    /// produced, with [`Direction::Incoming`] the sources of the incoming ones.
+    pub fn neighbors_directed(&self, index: usize, dir: Direction) -> Neighbors<'_, E> {
+        Neighbors::new(self.edges_directed(index, dir))
+    }
+
+    /// An iterator over the outgoing edges of `index`.
+    /// The iterator element type is `(usize, usize, &'a E)`: the edge index,
+    /// the target node index and the edge weight.
+    pub fn edges(&self, index: usize) -> Edges<'_, E> {
+        self.edges_directed(index, Direction::Outgoing)
+    }
+
+    /// An iterator over the edges of `index` in the given direction.
+    /// The iterator element type is `(usize, usize, &'a E)`: the edge index,
+    /// the node index on the other end and the edge weight.
+    pub fn edges_directed(&self, index: usize, dir: Direction) -> Edges<'_, E> {
+        let adjacent = self
+            .nodes
+            .get_index_value(index)
+            .map_or(&[][..], |adjacency| adjacency.get(dir));
+        Edges::new(adjacent.iter(), &self.edges, dir)
+    }
+
+    /// Add an edge from `source` to `target` and return its index.
+    ///
+    /// ***Panics*** if either node index is not present in the graph.
+    pub fn add_edge(&mut self, source: usize, target: usize, weight: E) -> usize {
+        assert!(
+            self.contains_node_index(source) && self.contains_node_index(target),
+            "HashSlabGraph: node index out of bounds"
+        );
+        let edge = self.edges.insert(EdgeData {
+            source,
+            target,
+            weight,
+        });
+        self.nodes.slab[source].value.outgoing.push(edge);
+        self.nodes.slab[target].value.incoming.push(edge);
+        edge
+    }
+
+    /// Remove an edge and return its weight.
+    pub fn remove_edge(&mut self, edge: usize) -> Option<E> {
+        let EdgeData {
+            source,
+            target,
+            weight,
+        } = self.edges.try_remove(edge)?;
+        self.nodes.slab[source]
+            .value
+            .detach(Direction::Outgoing, edge);
+        self.nodes.slab[target]
+            .value
+            .detach(Direction::Incoming, edge);
+        Some(weight)
+    }
+
+    /// Remove all nodes and edges, while preserving the capacity.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.edges.clear();
+    }
+
+    /// Remove all edges, keeping the nodes.
+    pub fn clear_edges(&mut self) {
+        self.edges.clear();
+        for adjacency in self.nodes.values_mut() {
+            adjacency.outgoing.clear();
+            adjacency.incoming.clear();
+        }
+    }
+
+    /// Breadth-first traversal of the nodes reachable from `start`.
+    pub fn bfs(&self, start: usize) -> Bfs<'_, N, E, S> {
+        Bfs::new(self, start)
+    }
+
+    /// Depth-first (preorder) traversal of the nodes reachable from `start`.
+    pub fn dfs(&self, start: usize) -> Dfs<'_, N, E, S> {
+        Dfs::new(self, start)
+    }
+
+    /// Topological order of the nodes, following the edge direction.
+    ///
+    /// Nodes which are part of a cycle, or reachable from one, are never
+    /// produced, so the graph is acyclic exactly when the iterator yields
+    /// [`node_count`][Self::node_count] nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::HashSlabGraph;
+    /// let mut graph = HashSlabGraph::<_, ()>::new();
+    /// let a = graph.add_node('a');
+    /// let b = graph.add_node('b');
+    /// graph.add_edge(a, b, ());
+    /// assert_eq!(graph.topo().count(), 2);
+    ///
+    /// graph.add_edge(b, a, ());
+    /// assert_eq!(graph.topo().count(), 0);
+    /// ```
+    pub fn topo(&self) -> Topo<'_, N, E, S> {
+        Topo::new(self)
+    }
+}
+
+impl<N, E, S> HashSlabGraph<N, E, S>
+where
+    N: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Add a node to the graph and return its index.
+    ///
+    /// If an equivalent node already exists, the graph is left unchanged and
+    /// the index of the existing node is returned.
+    pub fn add_node(&mut self, node: N) -> usize {
+        let entry = self.nodes.entry(node);
+        let index = entry.index();
+        entry.or_default();
+        index
+    }
+
+    /// Return the index of a node, if it exists in the graph.
+    pub fn node_index<Q>(&self, node: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<N> + ?Sized,
+    {
+        self.nodes.get_index_of(node)
+    }
+
+    /// Returns `true` if the graph contains the node.
+    pub fn contains_node<Q>(&self, node: &Q) -> bool
+    where
+        Q: Hash + Equivalent<N> + ?Sized,
+    {
+        self.nodes.contains_key(node)
+    }
+
+    /// Remove a node together with all of its edges and return the node key.
+    pub fn remove_node<Q>(&mut self, node: &Q) -> Option<N>
+    where
+        Q: Hash + Equivalent<N> + ?Sized,
+    {
+        let index = self.nodes.get_index_of(node)?;
+        self.remove_node_index(index)
+    }
+
+    /// Remove a node by index together with all of its edges and return the node key.
+    pub fn remove_node_index(&mut self, index: usize) -> Option<N> {
+        let (node, adjacency) = self.nodes.remove_index(index)?;
+        for edge in adjacency.outgoing {
+            // Self-loops are listed in both directions of the removed node
+            if let Some(EdgeData { target, .. }) = self.edges.try_remove(edge) {
+                if target != index {
+                    self.nodes.slab[target]
+                        .value
+                        .detach(Direction::Incoming, edge);
+                }
+            }
+        }
+        for edge in adjacency.incoming {
+            if let Some(EdgeData { source, .. }) = self.edges.try_remove(edge) {
+                self.nodes.slab[source]
+                    .value
+                    .detach(Direction::Outgoing, edge);
+            }
+        }
+        Some(node)
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<N: Clone, E: Clone, S: Clone> Clone for HashSlabGraph<N, E, S> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            edges: self.edges.clone(),
+        }
+    }
+}
+
+impl<N, E, S> fmt::Debug for HashSlabGraph<N, E, S>
+where
+    N: fmt::Debug,
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashSlabGraph")
+            .field("nodes", &self.nodes().collect::<Vec<_>>())
+            .field("edges", &self.edges_full().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<N, E, S> Default for HashSlabGraph<N, E, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_capacity_and_hasher(0, 0, S::default())
+    }
+}
+
+/// Access [`HashSlabGraph`] nodes by index.
+///
+/// ***Panics*** if `index` is not present in the graph.
+impl<N, E, S> core::ops::Index<usize> for HashSlabGraph<N, E, S> {
+    type Output = N;
+
+    fn index(&self, index: usize) -> &N {
+        self.node(index)
+            .expect("HashSlabGraph: node index out of bounds")
+    }
+}
@@ -0,0 +1,398 @@
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{fmt, iter::FusedIterator, slice};
+
+use slab::Slab;
+
+use crate::map;
+
+use super::{Direction, EdgeData, HashSlabGraph};
+
+/// An iterator over the index-node pairs of a [`HashSlabGraph`].
+///
+/// This `struct` is created by the [`HashSlabGraph::nodes`] method.
+/// See its documentation for more.
+pub struct Nodes<'a, N> {
+    full_keys: map::FullKeys<'a, N>,
+}
+
+impl<'a, N> Nodes<'a, N> {
+    pub(super) fn new(full_keys: map::FullKeys<'a, N>) -> Self {
+        Self { full_keys }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<N> Clone for Nodes<'_, N> {
+    fn clone(&self) -> Self {
+        Nodes {
+            full_keys: self.full_keys.clone(),
+        }
+    }
+}
+
+impl<N: fmt::Debug> fmt::Debug for Nodes<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, N> Iterator for Nodes<'a, N> {
+    type Item = (usize, &'a N);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.full_keys.next()
+    }
+}
+
+impl<N> ExactSizeIterator for Nodes<'_, N> {
+    fn len(&self) -> usize {
+        self.full_keys.len()
+    }
+}
+
+impl<N> FusedIterator for Nodes<'_, N> {}
+
+/// An iterator over all edges of a [`HashSlabGraph`].
+///
+/// This `struct` is created by the [`HashSlabGraph::edges_full`] method.
+/// See its documentation for more.
+pub struct EdgesFull<'a, E> {
+    iter: slab::Iter<'a, EdgeData<E>>,
+}
+
+impl<'a, E> EdgesFull<'a, E> {
+    pub(super) fn new(iter: slab::Iter<'a, EdgeData<E>>) -> Self {
+        Self { iter }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<E> Clone for EdgesFull<'_, E> {
+    fn clone(&self) -> Self {
+        EdgesFull {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for EdgesFull<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, E> Iterator for EdgesFull<'a, E> {
+    type Item = (usize, usize, usize, &'a E);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(
+            |(
+                edge,
+                EdgeData {
+                    source,
+                    target,
+                    weight,
+                },
+            )| (edge, *source, *target, weight),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<E> ExactSizeIterator for EdgesFull<'_, E> {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl<E> FusedIterator for EdgesFull<'_, E> {}
+
+/// An iterator over the edges of a single node of a [`HashSlabGraph`].
+///
+/// This `struct` is created by the [`HashSlabGraph::edges`] and
+/// [`HashSlabGraph::edges_directed`] methods. See their documentation for more.
+pub struct Edges<'a, E> {
+    iter: slice::Iter<'a, usize>,
+    edges: &'a Slab<EdgeData<E>>,
+    dir: Direction,
+}
+
+impl<'a, E> Edges<'a, E> {
+    pub(super) fn new(
+        iter: slice::Iter<'a, usize>,
+        edges: &'a Slab<EdgeData<E>>,
+        dir: Direction,
+    ) -> Self {
+        Self { iter, edges, dir }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<E> Clone for Edges<'_, E> {
+    fn clone(&self) -> Self {
+        Edges {
+            iter: self.iter.clone(),
+            edges: self.edges,
+            dir: self.dir,
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for Edges<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, E> Iterator for Edges<'a, E> {
+    type Item = (usize, usize, &'a E);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|&edge| {
+            let EdgeData {
+                source,
+                target,
+                weight,
+            } = &self.edges[edge];
+            match self.dir {
+                Direction::Outgoing => (edge, *target, weight),
+                Direction::Incoming => (edge, *source, weight),
+            }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<E> ExactSizeIterator for Edges<'_, E> {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl<E> FusedIterator for Edges<'_, E> {}
+
+/// An iterator over the neighbors of a node of a [`HashSlabGraph`].
+///
+/// This `struct` is created by the [`HashSlabGraph::neighbors`] and
+/// [`HashSlabGraph::neighbors_directed`] methods. See their documentation for more.
+pub struct Neighbors<'a, E> {
+    edges: Edges<'a, E>,
+}
+
+impl<'a, E> Neighbors<'a, E> {
+    pub(super) fn new(edges: Edges<'a, E>) -> Self {
+        Self { edges }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<E> Clone for Neighbors<'_, E> {
+    fn clone(&self) -> Self {
+        Neighbors {
+            edges: self.edges.clone(),
+        }
+    }
+}
+
+impl<E> fmt::Debug for Neighbors<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<E> Iterator for Neighbors<'_, E> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.edges.next().map(|(_, node, _)| node)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.edges.size_hint()
+    }
+}
+
+impl<E> ExactSizeIterator for Neighbors<'_, E> {
+    fn len(&self) -> usize {
+        self.edges.len()
+    }
+}
+
+impl<E> FusedIterator for Neighbors<'_, E> {}
+
+// Node indices are slab keys, so a plain vector indexed by them is enough
+// to mark visited nodes.
+#[derive(Debug, Default)]
+struct Visited(Vec<bool>);
+
+impl Visited {
+    /// Mark index as visited, returns `true` if it was not visited before.
+    fn insert(&mut self, index: usize) -> bool {
+        if index >= self.0.len() {
+            self.0.resize(index + 1, false);
+        }
+        !core::mem::replace(&mut self.0[index], true)
+    }
+}
+
+/// A breadth-first traversal of a [`HashSlabGraph`].
+///
+/// This `struct` is created by the [`HashSlabGraph::bfs`] method.
+/// See its documentation for more.
+pub struct Bfs<'a, N, E, S> {
+    graph: &'a HashSlabGraph<N, E, S>,
+    queue: VecDeque<usize>,
+    visited: Visited,
+}
+
+impl<'a, N, E, S> Bfs<'a, N, E, S> {
+    pub(super) fn new(graph: &'a HashSlabGraph<N, E, S>, start: usize) -> Self {
+        let mut queue = VecDeque::new();
+        let mut visited = Visited::default();
+        if graph.contains_node_index(start) {
+            visited.insert(start);
+            queue.push_back(start);
+        }
+        Self {
+            graph,
+            queue,
+            visited,
+        }
+    }
+}
+
+impl<N, E, S> fmt::Debug for Bfs<'_, N, E, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bfs").field("queue", &self.queue).finish()
+    }
+}
+
+impl<N, E, S> Iterator for Bfs<'_, N, E, S> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.queue.pop_front()?;
+        for next in self.graph.neighbors(index) {
+            if self.visited.insert(next) {
+                self.queue.push_back(next);
+            }
+        }
+        Some(index)
+    }
+}
+
+impl<N, E, S> FusedIterator for Bfs<'_, N, E, S> {}
+
+/// A depth-first traversal of a [`HashSlabGraph`], producing nodes in preorder.
+///
+/// This `struct` is created by the [`HashSlabGraph::dfs`] method.
+/// See its documentation for more.
+pub struct Dfs<'a, N, E, S> {
+    graph: &'a HashSlabGraph<N, E, S>,
+    stack: Vec<usize>,
+    visited: Visited,
+}
+
+impl<'a, N, E, S> Dfs<'a, N, E, S> {
+    pub(super) fn new(graph: &'a HashSlabGraph<N, E, S>, start: usize) -> Self {
+        let mut stack = Vec::new();
+        if graph.contains_node_index(start) {
+            stack.push(start);
+        }
+        Self {
+            graph,
+            stack,
+            visited: Visited::default(),
+        }
+    }
+}
+
+impl<N, E, S> fmt::Debug for Dfs<'_, N, E, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dfs").field("stack", &self.stack).finish()
+    }
+}
+
+impl<N, E, S> Iterator for Dfs<'_, N, E, S> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(index) = self.stack.pop() {
+            if self.visited.insert(index) {
+                // Push in reverse, so neighbors are visited in insertion order
+                let neighbors = self.graph.neighbors(index);
+                let len = self.stack.len();
+                self.stack.extend(neighbors);
+                self.stack[len..].reverse();
+                return Some(index);
+            }
+        }
+        None
+    }
+}
+
+impl<N, E, S> FusedIterator for Dfs<'_, N, E, S> {}
+
+/// A topological order traversal of a [`HashSlabGraph`].
+///
+/// This `struct` is created by the [`HashSlabGraph::topo`] method.
+/// See its documentation for more.
+pub struct Topo<'a, N, E, S> {
+    graph: &'a HashSlabGraph<N, E, S>,
+    // Number of incoming edges not yet visited, indexed by node index
+    in_degree: Vec<usize>,
+    ready: VecDeque<usize>,
+}
+
+impl<'a, N, E, S> Topo<'a, N, E, S> {
+    pub(super) fn new(graph: &'a HashSlabGraph<N, E, S>) -> Self {
+        let mut in_degree = Vec::new();
+        let mut ready = VecDeque::new();
+        for (index, adjacency) in graph.nodes.iter_full().map(|(i, _, a)| (i, a)) {
+            if index >= in_degree.len() {
+                in_degree.resize(index + 1, 0);
+            }
+            in_degree[index] = adjacency.incoming.len();
+            if adjacency.incoming.is_empty() {
+                ready.push_back(index);
+            }
+        }
+        // Start from the lowest indices to make the order predictable
+        ready.make_contiguous().sort_unstable();
+        Self {
+            graph,
+            in_degree,
+            ready,
+        }
+    }
+}
+
+impl<N, E, S> fmt::Debug for Topo<'_, N, E, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Topo").field("ready", &self.ready).finish()
+    }
+}
+
+impl<N, E, S> Iterator for Topo<'_, N, E, S> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.ready.pop_front()?;
+        for next in self.graph.neighbors(index) {
+            let degree = &mut self.in_degree[next];
+            *degree -= 1;
+            if *degree == 0 {
+                self.ready.push_back(next);
+            }
+        }
+        Some(index)
+    }
+}
+
+impl<N, E, S> FusedIterator for Topo<'_, N, E, S> {}
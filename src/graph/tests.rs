@@ -0,0 +1,145 @@
+use super::*;
+use std::vec::Vec;
+
+fn sorted<I: IntoIterator<Item = usize>>(iter: I) -> Vec<usize> {
+    let mut vec: Vec<_> = iter.into_iter().collect();
+    vec.sort_unstable();
+    vec
+}
+
+#[test]
+fn add_node_returns_existing_index() {
+    let mut graph = HashSlabGraph::<_, ()>::new();
+    let a = graph.add_node("a");
+    let b = graph.add_node("b");
+    assert_eq!(graph.add_node("a"), a);
+    assert_eq!(graph.node_count(), 2);
+    assert_eq!(graph.node(b), Some(&"b"));
+    assert_eq!(graph.node_index(&"b"), Some(b));
+}
+
+#[test]
+fn neighbors_in_both_directions() {
+    let mut graph = HashSlabGraph::new();
+    let a = graph.add_node('a');
+    let b = graph.add_node('b');
+    let c = graph.add_node('c');
+    let ab = graph.add_edge(a, b, 1);
+    graph.add_edge(a, c, 2);
+    graph.add_edge(c, b, 3);
+
+    assert_eq!(sorted(graph.neighbors(a)), [b, c]);
+    assert_eq!(
+        sorted(graph.neighbors_directed(b, Direction::Incoming)),
+        [a, c]
+    );
+    assert_eq!(graph.find_edge(a, b), Some(ab));
+    assert_eq!(graph.find_edge(b, a), None);
+    assert_eq!(graph.edge_endpoints(ab), Some((a, b)));
+
+    let weights: Vec<_> = graph.edges(a).map(|(_, _, &w)| w).collect();
+    assert_eq!(sorted(weights), [1, 2]);
+}
+
+#[test]
+fn remove_node_removes_edges() {
+    let mut graph = HashSlabGraph::new();
+    let a = graph.add_node('a');
+    let b = graph.add_node('b');
+    let c = graph.add_node('c');
+    graph.add_edge(a, b, ());
+    graph.add_edge(b, c, ());
+    graph.add_edge(c, a, ());
+    graph.add_edge(b, b, ());
+    let ca = graph.find_edge(c, a).unwrap();
+
+    assert_eq!(graph.remove_node(&'b'), Some('b'));
+    assert_eq!(graph.edge_count(), 1);
+    assert_eq!(graph.neighbors(a).count(), 0);
+    assert_eq!(graph.neighbors_directed(c, Direction::Incoming).count(), 0);
+    assert_eq!(graph.edge_endpoints(ca), Some((c, a)));
+    // Remaining indices are stable
+    assert_eq!(graph.node_index(&'c'), Some(c));
+    // and the freed one is reused
+    assert_eq!(graph.add_node('d'), b);
+    assert_eq!(graph.neighbors(b).count(), 0);
+}
+
+#[test]
+fn remove_edge() {
+    let mut graph = HashSlabGraph::new();
+    let a = graph.add_node(1);
+    let b = graph.add_node(2);
+    let e1 = graph.add_edge(a, b, "x");
+    let e2 = graph.add_edge(a, b, "y");
+
+    assert_eq!(graph.remove_edge(e1), Some("x"));
+    assert_eq!(graph.remove_edge(e1), None);
+    assert_eq!(graph.edges(a).collect::<Vec<_>>(), [(e2, b, &"y")]);
+    assert_eq!(
+        graph.edges_directed(b, Direction::Incoming).collect::<Vec<_>>(),
+        [(e2, a, &"y")]
+    );
+}
+
+#[test]
+#[should_panic]
+fn add_edge_to_missing_node() {
+    let mut graph = HashSlabGraph::new();
+    let a = graph.add_node(1);
+    graph.add_edge(a, 10, ());
+}
+
+#[test]
+fn traversals() {
+    //   0 -> 1 -> 3
+    //   |         ^
+    //   +--> 2 ---+      4 (unreachable)
+    let mut graph = HashSlabGraph::new();
+    let n: Vec<_> = (0..5).map(|i| graph.add_node(i)).collect();
+    graph.add_edge(n[0], n[1], ());
+    graph.add_edge(n[0], n[2], ());
+    graph.add_edge(n[1], n[3], ());
+    graph.add_edge(n[2], n[3], ());
+
+    assert_eq!(graph.bfs(n[0]).collect::<Vec<_>>(), [0, 1, 2, 3]);
+    assert_eq!(graph.dfs(n[0]).collect::<Vec<_>>(), [0, 1, 3, 2]);
+    assert_eq!(graph.bfs(n[3]).collect::<Vec<_>>(), [3]);
+    assert_eq!(graph.dfs(42).count(), 0);
+
+    let topo: Vec<_> = graph.topo().collect();
+    assert_eq!(topo.len(), 5);
+    let position = |i| topo.iter().position(|&x| x == i).unwrap();
+    for (_, source, target, _) in graph.edges_full() {
+        assert!(position(source) < position(target));
+    }
+}
+
+#[test]
+fn topo_skips_cycles() {
+    let mut graph = HashSlabGraph::new();
+    let a = graph.add_node('a');
+    let b = graph.add_node('b');
+    let c = graph.add_node('c');
+    let d = graph.add_node('d');
+    graph.add_edge(a, b, ());
+    graph.add_edge(b, c, ());
+    graph.add_edge(c, b, ());
+    graph.add_edge(c, d, ());
+
+    assert_eq!(graph.topo().collect::<Vec<_>>(), [a]);
+
+    graph.remove_edge(graph.find_edge(c, b).unwrap());
+    assert_eq!(graph.topo().collect::<Vec<_>>(), [a, b, c, d]);
+}
+
+#[test]
+fn clear_edges() {
+    let mut graph = HashSlabGraph::new();
+    let a = graph.add_node('a');
+    graph.add_edge(a, a, ());
+    graph.clear_edges();
+    assert_eq!(graph.edge_count(), 0);
+    assert_eq!(graph.node_count(), 1);
+    assert_eq!(graph.neighbors(a).count(), 0);
+}
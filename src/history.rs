@@ -0,0 +1,331 @@
+//! A [`HashSlabMap`] with multi-step undo and redo
+use alloc::{string::String, vec, vec::Vec};
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    ops::Deref,
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+
+use crate::{map::Change, HashSlabMap};
+
+#[cfg(test)]
+mod tests;
+
+struct Step<K, V> {
+    name: String,
+    // Changes which revert the step, in the order they were made
+    changes: Vec<Change<K, V>>,
+}
+
+impl<K, V> Step<K, V> {
+    // Revert all changes, returning the step which reverts them back.
+    fn revert<S>(self, map: &mut HashSlabMap<K, V, S>) -> Self {
+        let changes = self
+            .changes
+            .into_iter()
+            .rev()
+            .map(|change| change.revert(map))
+            .collect();
+        Self {
+            name: self.name,
+            changes,
+        }
+    }
+}
+
+/// A [`HashSlabMap`] which records its changes in named steps, which can be
+/// undone and redone.
+///
+/// Undo and redo restore entries to their original indices, so indices can be
+/// used as stable ids across the history. Changes made between
+/// [`begin_step`][Self::begin_step] and [`end_step`][Self::end_step] form one
+/// step; any other change forms a step of its own, with an empty name. Making
+/// a change discards the steps which could be redone.
+///
+/// The map can be read through [`Deref`]. Replaced and removed values are kept
+/// in the history, so they are only returned by reference.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::HistoryHashSlabMap;
+/// let mut shapes = HistoryHashSlabMap::new();
+/// let circle = shapes.insert_full("circle", 1).0;
+///
+/// shapes.begin_step("replace circle");
+/// shapes.remove_index(circle);
+/// let square = shapes.insert_full("square", 4).0;
+/// *shapes.get_index_mut(square).unwrap().1 += 1;
+/// shapes.end_step();
+/// assert_eq!(shapes.get_index(circle), Some((&"square", &5)));
+///
+/// assert_eq!(shapes.undo(), Some("replace circle"));
+/// assert_eq!(shapes.get_index(circle), Some((&"circle", &1)));
+/// assert_eq!(shapes.redo(), Some("replace circle"));
+/// assert_eq!(shapes.get_index(square), Some((&"square", &5)));
+///
+/// shapes.undo();
+/// shapes.undo();
+/// assert!(shapes.is_empty());
+/// assert_eq!(shapes.undo(), None);
+/// ```
+#[cfg(feature = "std")]
+pub struct HistoryHashSlabMap<K, V, S = RandomState> {
+    map: HashSlabMap<K, V, S>,
+    undo: Vec<Step<K, V>>,
+    redo: Vec<Step<K, V>>,
+    // The last undo step is still being recorded
+    open: bool,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct HistoryHashSlabMap<K, V, S> {
+    map: HashSlabMap<K, V, S>,
+    undo: Vec<Step<K, V>>,
+    redo: Vec<Step<K, V>>,
+    open: bool,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V> HistoryHashSlabMap<K, V> {
+    /// Creates an empty `HistoryHashSlabMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<K, V, S> HistoryHashSlabMap<K, V, S> {
+    /// Creates an empty `HistoryHashSlabMap` which will use the given hash
+    /// builder.
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self::from_map(HashSlabMap::with_hasher(hash_builder))
+    }
+
+    /// Creates a `HistoryHashSlabMap` with the content of `map` and an empty
+    /// history.
+    pub const fn from_map(map: HashSlabMap<K, V, S>) -> Self {
+        Self {
+            map,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            open: false,
+        }
+    }
+
+    /// Start a step named `name`, ending the current one.
+    pub fn begin_step(&mut self, name: impl Into<String>) {
+        self.end_step();
+        self.undo.push(Step {
+            name: name.into(),
+            changes: Vec::new(),
+        });
+        self.open = true;
+    }
+
+    /// End the current step. Steps without changes are discarded.
+    pub fn end_step(&mut self) {
+        if self.open && self.undo.last().is_some_and(|step| step.changes.is_empty()) {
+            self.undo.pop();
+        }
+        self.open = false;
+    }
+
+    /// Undo the last step, ending the current one, and return its name.
+    pub fn undo(&mut self) -> Option<&str> {
+        self.end_step();
+        let step = self.undo.pop()?.revert(&mut self.map);
+        self.redo.push(step);
+        self.redo.last().map(|step| step.name.as_str())
+    }
+
+    /// Redo the last undone step and return its name.
+    pub fn redo(&mut self) -> Option<&str> {
+        self.end_step();
+        let step = self.redo.pop()?.revert(&mut self.map);
+        self.undo.push(step);
+        self.undo.last().map(|step| step.name.as_str())
+    }
+
+    /// Return the name of the step [`undo`][Self::undo] would revert.
+    pub fn peek_undo(&self) -> Option<&str> {
+        self.undo.last().map(|step| step.name.as_str())
+    }
+
+    /// Return the name of the step [`redo`][Self::redo] would apply.
+    pub fn peek_redo(&self) -> Option<&str> {
+        self.redo.last().map(|step| step.name.as_str())
+    }
+
+    /// Return the number of steps which can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Return the number of steps which can be redone.
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Forget all steps, keeping the map as it is.
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = false;
+    }
+
+    /// Return the underlying map.
+    pub fn as_map(&self) -> &HashSlabMap<K, V, S> {
+        &self.map
+    }
+
+    /// Consumes the history, returning the map.
+    pub fn into_inner(self) -> HashSlabMap<K, V, S> {
+        self.map
+    }
+
+    // Record a change in the current step and return it.
+    fn record(&mut self, change: Change<K, V>) -> &Change<K, V> {
+        self.redo.clear();
+        match self.undo.last_mut() {
+            Some(step) if self.open => step.changes.push(change),
+            _ => self.undo.push(Step {
+                name: String::new(),
+                changes: vec![change],
+            }),
+        }
+        match self.undo.last().and_then(|step| step.changes.last()) {
+            Some(change) => change,
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<K, V, S> HistoryHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Insert a key-value pair in the map.
+    ///
+    /// If an equivalent key already exists, its value is replaced and the old
+    /// value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<&V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Insert a key-value pair in the map, and get its index together with
+    /// the old value if an equivalent key already existed.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<&V>) {
+        match self.map.insert_full(key, value) {
+            (index, Some(old)) => match self.record(Change::Replace(index, old)) {
+                Change::Replace(_, old) => (index, Some(old)),
+                _ => unreachable!(),
+            },
+            (index, None) => {
+                self.record(Change::Insert(index));
+                (index, None)
+            }
+        }
+    }
+
+    /// Get a key and a mutable reference to the value by index, recording the
+    /// current value to restore it on undo.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)>
+    where
+        V: Clone,
+    {
+        let old = self.map.slab.get(index)?.value.clone();
+        self.record(Change::Replace(index, old));
+        self.map.get_index_mut(index)
+    }
+
+    /// Get a mutable reference to the value of `key`, recording the current
+    /// value to restore it on undo.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        V: Clone,
+    {
+        let index = self.map.get_index_of(key)?;
+        self.get_index_mut(index).map(|(_, value)| value)
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.map.get_index_of(key)?;
+        self.remove_index(index).map(|(_, value)| value)
+    }
+
+    /// Remove the key-value pair by index.
+    pub fn remove_index(&mut self, index: usize) -> Option<(&K, &V)> {
+        let hash = self.map.slab.get(index)?.hash;
+        let (key, value) = self.map.remove_index(index)?;
+        match self.record(Change::Remove {
+            index,
+            key,
+            value,
+            hash,
+        }) {
+            Change::Remove { key, value, .. } => Some((key, value)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<K, V, S> Deref for HistoryHashSlabMap<K, V, S> {
+    type Target = HashSlabMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, S> fmt::Debug for HistoryHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HistoryHashSlabMap")
+            .field("map", &self.map.iter_full())
+            .field("undo", &self.undo_len())
+            .field("redo", &self.redo_len())
+            .finish()
+    }
+}
+
+impl<K, V, S> Default for HistoryHashSlabMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> From<HashSlabMap<K, V, S>> for HistoryHashSlabMap<K, V, S> {
+    fn from(map: HashSlabMap<K, V, S>) -> Self {
+        Self::from_map(map)
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for HistoryHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
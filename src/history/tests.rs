@@ -0,0 +1,75 @@
+use super::*;
+use std::{string::ToString, vec::Vec};
+
+fn entries(map: &HistoryHashSlabMap<char, u32>) -> Vec<(usize, char, u32)> {
+    let mut vec: Vec<_> = map.iter_full().map(|(i, &k, &v)| (i, k, v)).collect();
+    vec.sort_unstable();
+    vec
+}
+
+#[test]
+fn undo_redo_restores_indices() {
+    let mut map = HistoryHashSlabMap::new();
+    map.begin_step("setup");
+    map.extend([('a', 1), ('b', 2), ('c', 3)]);
+    map.end_step();
+    let setup = entries(&map);
+
+    map.begin_step("edit");
+    assert_eq!(map.remove(&'b'), Some(&2));
+    assert_eq!(map.remove_index(0), Some((&'a', &1)));
+    // Freed slots are reused within the step
+    assert_eq!(map.insert_full('d', 4), (0, None));
+    assert_eq!(map.insert('c', 30), Some(&3));
+    *map.get_mut(&'d').unwrap() += 40;
+    map.end_step();
+    let edited = entries(&map);
+    assert_eq!(edited, [(0, 'd', 44), (2, 'c', 30)]);
+
+    assert_eq!(map.peek_undo(), Some("edit"));
+    assert_eq!(map.undo(), Some("edit"));
+    assert_eq!(entries(&map), setup);
+    assert_eq!(map.undo(), Some("setup"));
+    assert!(map.is_empty());
+    assert_eq!(map.undo(), None);
+    assert_eq!(map.redo_len(), 2);
+
+    assert_eq!(map.redo(), Some("setup"));
+    assert_eq!(entries(&map), setup);
+    assert_eq!(map.redo(), Some("edit"));
+    assert_eq!(entries(&map), edited);
+    assert_eq!(map.redo(), None);
+
+    // Repeated cycles keep the same indices
+    map.undo();
+    map.redo();
+    assert_eq!(entries(&map), edited);
+    assert_eq!(map.insert_full('e', 5), (1, None));
+}
+
+#[test]
+fn steps() {
+    let mut map = HistoryHashSlabMap::new();
+    map.insert('a', 1);
+    map.insert('b', 2);
+    assert_eq!(map.undo_len(), 2);
+    assert_eq!(map.peek_undo(), Some(""));
+
+    // Empty steps are discarded
+    map.begin_step("empty");
+    map.begin_step("one".to_string());
+    map.get_index_mut(0).unwrap().1.clone_from(&10);
+    assert_eq!(map.undo_len(), 3);
+    assert_eq!(map.undo(), Some("one"));
+    assert_eq!(map.get(&'a'), Some(&1));
+
+    // A new change discards the redo steps
+    assert_eq!(map.peek_redo(), Some("one"));
+    map.remove(&'b');
+    assert_eq!(map.redo_len(), 0);
+    assert_eq!(map.redo(), None);
+
+    map.clear_history();
+    assert_eq!(map.undo(), None);
+    assert_eq!(map.into_inner().len(), 1);
+}
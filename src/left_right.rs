@@ -0,0 +1,403 @@
+//! Snapshot-isolated reader handles for a [`HashSlabMap`] with a single writer
+//!
+//! The map is kept in two copies. Readers access the published copy without
+//! locking, while the writer modifies the other one and records its
+//! operations. [`WriteHandle::refresh`] publishes the modified copy, waits
+//! until no reader is left in the previous one, and replays the recorded
+//! operations on it, so both copies stay identical, down to entry indices.
+//!
+//! # Examples
+//!
+//! ```
+//! use hashslab::left_right;
+//! use std::thread;
+//!
+//! let (mut writer, reader) = left_right::new();
+//! let index = writer.insert("a", 1);
+//! // Not visible to readers until published
+//! assert!(reader.enter().is_empty());
+//!
+//! writer.refresh();
+//! let reader2 = reader.clone();
+//! thread::spawn(move || {
+//!     let map = reader2.enter();
+//!     assert_eq!(map.get_index(index), Some((&"a", &1)));
+//! })
+//! .join()
+//! .unwrap();
+//!
+//! writer.remove_index(index);
+//! let snapshot = reader.enter();
+//! assert_eq!(snapshot.get(&"a"), Some(&1));
+//! ```
+use std::{
+    cell::{Cell, UnsafeCell},
+    fmt,
+    hash::{BuildHasher, Hash, RandomState},
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    vec::Vec,
+};
+
+use hashbrown::Equivalent;
+use slab::Slab;
+
+use crate::HashSlabMap;
+
+#[cfg(test)]
+mod tests;
+
+/// Creates an empty map, returning its write handle and a first read handle.
+pub fn new<K, V>() -> (WriteHandle<K, V>, ReadHandle<K, V>)
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    with_hasher(RandomState::new())
+}
+
+/// Creates an empty map which will use the given hash builder, returning its
+/// write handle and a first read handle.
+pub fn with_hasher<K, V, S>(builder: S) -> (WriteHandle<K, V, S>, ReadHandle<K, V, S>)
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    from_map(HashSlabMap::with_hasher(builder))
+}
+
+/// Creates a map with the content of `map`, returning its write handle and a
+/// first read handle.
+///
+/// Entries keep their indices.
+pub fn from_map<K, V, S>(map: HashSlabMap<K, V, S>) -> (WriteHandle<K, V, S>, ReadHandle<K, V, S>)
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    let shared = Arc::new(Shared {
+        maps: [UnsafeCell::new(map.clone()), UnsafeCell::new(map)],
+        published: AtomicUsize::new(0),
+        epochs: Mutex::new(Slab::new()),
+    });
+    let reader = ReadHandle::new(Arc::clone(&shared));
+    let writer = WriteHandle {
+        shared,
+        write: 1,
+        log: Vec::new(),
+    };
+    (writer, reader)
+}
+
+struct Shared<K, V, S> {
+    maps: [UnsafeCell<HashSlabMap<K, V, S>>; 2],
+    // Index of the copy readers should enter
+    published: AtomicUsize,
+    // Epoch counters of all read handles, odd while a reader is inside a copy
+    epochs: Mutex<Slab<Arc<AtomicUsize>>>,
+}
+
+// SAFETY: readers only access the published copy through shared references,
+// and the writer only mutates the other copy once all readers left it.
+unsafe impl<K, V, S> Sync for Shared<K, V, S>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    S: Send + Sync,
+{
+}
+
+enum Op<K, V> {
+    Insert(K, V),
+    RemoveIndex(usize),
+    Clear,
+}
+
+impl<K, V> Op<K, V> {
+    fn apply<S>(self, map: &mut HashSlabMap<K, V, S>)
+    where
+        K: Hash + Eq,
+        S: BuildHasher,
+    {
+        match self {
+            Op::Insert(key, value) => {
+                map.insert(key, value);
+            }
+            Op::RemoveIndex(index) => {
+                map.remove_index(index);
+            }
+            Op::Clear => map.clear(),
+        }
+    }
+}
+
+/// The single writer of a map shared with [`ReadHandle`]s.
+///
+/// Modifications are applied to a private copy of the map right away, and
+/// become visible to readers after [`refresh`][Self::refresh]. Operations
+/// which were not published are lost when the handle is dropped.
+///
+/// This `struct` is created by the [`new`], [`with_hasher`] and [`from_map`]
+/// functions.
+pub struct WriteHandle<K, V, S = RandomState> {
+    shared: Arc<Shared<K, V, S>>,
+    // Index of the copy owned by the writer
+    write: usize,
+    // Operations to replay on the published copy on the next refresh
+    log: Vec<Op<K, V>>,
+}
+
+impl<K, V, S> WriteHandle<K, V, S> {
+    /// Return the writer's copy of the map, including unpublished changes.
+    pub fn map(&self) -> &HashSlabMap<K, V, S> {
+        // SAFETY: readers never access the writer's copy
+        unsafe { &*self.shared.maps[self.write].get() }
+    }
+
+    /// Return the number of operations waiting to be published.
+    pub fn pending(&self) -> usize {
+        self.log.len()
+    }
+
+    /// Create a new read handle.
+    pub fn reader(&self) -> ReadHandle<K, V, S> {
+        ReadHandle::new(Arc::clone(&self.shared))
+    }
+
+    fn map_mut(&mut self) -> &mut HashSlabMap<K, V, S> {
+        // SAFETY: readers never access the writer's copy, and the writer is
+        // borrowed mutably
+        unsafe { &mut *self.shared.maps[self.write].get() }
+    }
+}
+
+impl<K, V, S> WriteHandle<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair and returns its index.
+    ///
+    /// If the key is already present, its value is replaced.
+    pub fn insert(&mut self, key: K, value: V) -> usize {
+        self.log.push(Op::Insert(key.clone(), value.clone()));
+        self.map_mut().insert_full(key, value).0
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let (index, _, value) = self.map_mut().remove_full(key)?;
+        self.log.push(Op::RemoveIndex(index));
+        Some(value)
+    }
+
+    /// Removes the entry at the given index, returning its key and value.
+    pub fn remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let entry = self.map_mut().remove_index(index)?;
+        self.log.push(Op::RemoveIndex(index));
+        Some(entry)
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        // Earlier operations are pointless once the copy is cleared
+        self.log.clear();
+        self.log.push(Op::Clear);
+        self.map_mut().clear();
+    }
+
+    /// Publish all pending operations to readers.
+    ///
+    /// Readers which entered the map before the call keep their snapshot,
+    /// and this method blocks until all of them leave it.
+    pub fn refresh(&mut self) {
+        if self.log.is_empty() {
+            return;
+        }
+        let shared = &*self.shared;
+        shared.published.store(self.write, Ordering::SeqCst);
+        let previous = 1 - self.write;
+
+        // Readers registered from now on see the new copy, so it's enough to
+        // wait for the ones known at this point.
+        let epochs: Vec<_> = shared
+            .epochs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(_, epoch)| Arc::clone(epoch))
+            .collect();
+        for epoch in epochs {
+            let start = epoch.load(Ordering::SeqCst);
+            if start % 2 == 1 {
+                while epoch.load(Ordering::SeqCst) == start {
+                    thread::yield_now();
+                }
+            }
+        }
+
+        self.write = previous;
+        for op in std::mem::take(&mut self.log) {
+            op.apply(self.map_mut());
+        }
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for WriteHandle<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
+
+impl<K, V, S> fmt::Debug for WriteHandle<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteHandle")
+            .field("map", &self.map().iter())
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+/// A handle to read the latest published state of a map.
+///
+/// Reading doesn't lock and never waits for the writer. A handle can be
+/// sent to another thread, and cloned to get one handle per thread.
+///
+/// This `struct` is created by the [`new`], [`with_hasher`] and [`from_map`]
+/// functions, and by [`WriteHandle::reader`].
+pub struct ReadHandle<K, V, S = RandomState> {
+    shared: Arc<Shared<K, V, S>>,
+    epoch: Arc<AtomicUsize>,
+    // Key of the epoch in the registry
+    key: usize,
+    // Number of live guards, and the copy they point to. Being `Cell`s, they
+    // also keep the handle from being shared between threads.
+    depth: Cell<usize>,
+    entered: Cell<usize>,
+}
+
+impl<K, V, S> ReadHandle<K, V, S> {
+    fn new(shared: Arc<Shared<K, V, S>>) -> Self {
+        let epoch = Arc::new(AtomicUsize::new(0));
+        let key = shared
+            .epochs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(Arc::clone(&epoch));
+        Self {
+            shared,
+            epoch,
+            key,
+            depth: Cell::new(0),
+            entered: Cell::new(0),
+        }
+    }
+
+    /// Enter the latest published state of the map.
+    ///
+    /// The returned guard dereferences to a [`HashSlabMap`] which doesn't
+    /// change while the guard is alive. Keeping the guard blocks the next
+    /// [`WriteHandle::refresh`], so don't hold it longer than needed.
+    pub fn enter(&self) -> ReadGuard<'_, K, V, S> {
+        let depth = self.depth.get();
+        if depth == 0 {
+            self.epoch.fetch_add(1, Ordering::SeqCst);
+            self.entered
+                .set(self.shared.published.load(Ordering::SeqCst));
+        }
+        self.depth.set(depth + 1);
+        // SAFETY: the writer doesn't modify the copy until the epoch changes
+        let map = unsafe { &*self.shared.maps[self.entered.get()].get() };
+        ReadGuard { map, handle: self }
+    }
+
+    fn exit(&self) {
+        let depth = self.depth.get() - 1;
+        self.depth.set(depth);
+        if depth == 0 {
+            self.epoch.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<K, V, S> Clone for ReadHandle<K, V, S> {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.shared))
+    }
+}
+
+impl<K, V, S> Drop for ReadHandle<K, V, S> {
+    fn drop(&mut self) {
+        self.shared
+            .epochs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(self.key);
+    }
+}
+
+impl<K, V, S> fmt::Debug for ReadHandle<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReadHandle")
+            .field(&self.enter().iter())
+            .finish()
+    }
+}
+
+/// A guard giving access to a published state of a map.
+///
+/// This `struct` is created by the [`ReadHandle::enter`] method.
+/// See its documentation for more.
+pub struct ReadGuard<'a, K, V, S> {
+    map: &'a HashSlabMap<K, V, S>,
+    handle: &'a ReadHandle<K, V, S>,
+}
+
+impl<K, V, S> Deref for ReadGuard<'_, K, V, S> {
+    type Target = HashSlabMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        self.map
+    }
+}
+
+impl<K, V, S> Drop for ReadGuard<'_, K, V, S> {
+    fn drop(&mut self) {
+        self.handle.exit();
+    }
+}
+
+impl<K, V, S> fmt::Debug for ReadGuard<'_, K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReadGuard").field(&self.map.iter()).finish()
+    }
+}
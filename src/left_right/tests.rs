@@ -0,0 +1,121 @@
+use super::*;
+use std::{sync::mpsc, thread, time::Duration, vec};
+
+#[test]
+fn publish_on_refresh() {
+    let (mut writer, reader) = new();
+    let a = writer.insert('a', 1);
+    let b = writer.insert('b', 2);
+    assert_eq!(writer.pending(), 2);
+    assert_eq!(writer.map().len(), 2);
+    assert!(reader.enter().is_empty());
+
+    writer.refresh();
+    assert_eq!(writer.pending(), 0);
+    assert_eq!(reader.enter().get_index(a), Some((&'a', &1)));
+
+    assert_eq!(writer.remove(&'a'), Some(1));
+    assert_eq!(writer.remove_index(a), None);
+    let c = writer.insert('c', 3);
+    // Slots are reused the same way in both copies
+    assert_eq!(c, a);
+    writer.insert('b', 4);
+    {
+        let map = reader.enter();
+        assert_eq!(map.get(&'b'), Some(&2));
+        assert_eq!(map.get_index_of(&'a'), Some(a));
+    }
+
+    writer.refresh();
+    writer.insert('d', 5);
+    writer.refresh();
+    let map = reader.enter();
+    let mut entries: Vec<_> = map.iter_full().collect();
+    entries.sort();
+    assert_eq!(entries, vec![(a, &'c', &3), (b, &'b', &4), (2, &'d', &5)]);
+    let mut written: Vec<_> = writer.map().iter_full().collect();
+    written.sort();
+    assert_eq!(entries, written);
+}
+
+#[test]
+fn clear_and_from_map() {
+    let mut map = HashSlabMap::new();
+    map.insert(1, "one");
+    map.insert(2, "two");
+    map.remove(&1);
+    let (mut writer, reader) = from_map(map);
+    assert_eq!(reader.enter().get_index(1), Some((&2, &"two")));
+    // The vacant slot is reused in both copies
+    assert_eq!(writer.insert(3, "three"), 0);
+    writer.refresh();
+    assert_eq!(reader.enter().get_index(0), Some((&3, &"three")));
+
+    writer.insert(4, "four");
+    writer.clear();
+    assert_eq!(writer.pending(), 1);
+    writer.refresh();
+    assert!(reader.enter().is_empty());
+    writer.insert(5, "five");
+    writer.refresh();
+    assert_eq!(reader.enter().get_index(0), Some((&5, &"five")));
+    assert_eq!(writer.reader().enter().len(), 1);
+}
+
+#[test]
+fn refresh_waits_for_readers() {
+    let (mut writer, reader) = new();
+    writer.insert(0, 0);
+    writer.refresh();
+
+    let nested = reader.enter();
+    let guard = reader.enter();
+    drop(nested);
+    writer.insert(1, 1);
+    let (done, refreshed) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        writer.refresh();
+        done.send(()).unwrap();
+        writer
+    });
+    thread::sleep(Duration::from_millis(50));
+    // The snapshot doesn't change while the writer waits
+    assert!(refreshed.try_recv().is_err());
+    assert_eq!(guard.len(), 1);
+    drop(guard);
+    refreshed.recv().unwrap();
+    let writer = handle.join().unwrap();
+    assert_eq!(reader.enter().len(), 2);
+    assert_eq!(writer.pending(), 0);
+}
+
+#[test]
+fn concurrent_readers() {
+    let (mut writer, reader) = new();
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let reader = reader.clone();
+            thread::spawn(move || {
+                let mut seen = 0;
+                while seen < 1000 {
+                    let map = reader.enter();
+                    // Every published state is a prefix of the insertions
+                    for (index, &key, &value) in map.iter_full() {
+                        assert_eq!(index, key);
+                        assert_eq!(value, key * 2);
+                    }
+                    seen = map.len();
+                }
+            })
+        })
+        .collect();
+    for i in 0..1000usize {
+        writer.insert(i, i * 2);
+        if i % 10 == 9 {
+            writer.refresh();
+        }
+    }
+    for handle in readers {
+        handle.join().unwrap();
+    }
+}
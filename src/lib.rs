@@ -19,6 +19,121 @@ pub mod set;
 #[doc(inline)]
 pub use set::HashSlabSet;
 
+pub mod graph;
+#[doc(inline)]
+pub use graph::HashSlabGraph;
+
+pub mod disjoint_sets;
+#[doc(inline)]
+pub use disjoint_sets::DisjointSets;
+
+pub mod counter;
+#[doc(inline)]
+pub use counter::HashSlabCounter;
+
+pub mod scoped;
+#[doc(inline)]
+pub use scoped::ScopedHashSlabMap;
+
+pub mod dictionary;
+#[doc(inline)]
+pub use dictionary::DictionaryEncoder;
+
+pub mod observed;
+#[doc(inline)]
+pub use observed::ObservedHashSlabMap;
+
+pub mod history;
+#[doc(inline)]
+pub use history::HistoryHashSlabMap;
+
+pub mod cow;
+#[doc(inline)]
+pub use cow::CowHashSlabMap;
+
+pub mod tracked;
+#[doc(inline)]
+pub use tracked::TrackedHashSlabMap;
+
+pub mod oplog;
+#[doc(inline)]
+pub use oplog::LoggedHashSlabMap;
+
+pub mod codec;
+
+pub mod snapshot;
+
+pub mod frozen;
+#[doc(inline)]
+pub use frozen::FrozenHashSlabMap;
+
+pub mod relocatable;
+#[doc(inline)]
+pub use relocatable::RelocatableHashSlabMap;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod concurrent;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use concurrent::ConcurrentHashSlabMap;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod append_only;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use append_only::AppendOnlyHashSlabMap;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod left_right;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod slot_locked;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use slot_locked::SlotLockedHashSlabMap;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod watch;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use watch::WatchedHashSlabMap;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod persistent;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use persistent::PersistentHashSlabMap;
+
+#[cfg(feature = "rayon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub mod rayon;
+
+#[cfg(feature = "rkyv")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rkyv")))]
+pub mod rkyv;
+
+#[cfg(feature = "serde")]
+mod serde;
+
+#[cfg(feature = "borsh")]
+mod borsh;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+
+#[cfg(feature = "proptest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "proptest")))]
+pub mod proptest;
+
+#[cfg(feature = "quickcheck")]
+mod quickcheck;
+
 #[derive(Debug, Clone)]
 struct ValueData<V> {
     value: V,
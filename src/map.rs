@@ -31,6 +31,17 @@ pub use drain::{Drain, DrainFull};
 mod entry;
 pub use entry::{Entry, OccupiedEntry, VacantEntry};
 
+mod diff;
+pub use diff::{diff, Patch, PatchError, PatchOp};
+
+mod layout;
+#[cfg(any(feature = "serde", feature = "borsh"))]
+pub(crate) use layout::index_bound;
+
+mod transaction;
+pub(crate) use transaction::Change;
+pub use transaction::{Checkpoint, Transaction};
+
 #[cfg(test)]
 mod tests;
 
@@ -137,9 +148,9 @@ pub struct HashSlabMap<K, V, S = RandomState> {
 
 #[cfg(not(feature = "std"))]
 pub struct HashSlabMap<K, V, S> {
-    table: HashTable<KeyData<K>>,
-    slab: Slab<ValueData<V>>,
-    builder: S,
+    pub(crate) table: HashTable<KeyData<K>>,
+    pub(crate) slab: Slab<ValueData<V>>,
+    pub(crate) builder: S,
 }
 
 #[cfg(feature = "std")]
@@ -642,6 +653,37 @@ impl<K, V, S> HashSlabMap<K, V, S> {
         Drain::new(self.drain_full())
     }
 
+    /// Start a transaction, whose changes are reverted unless it is committed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::HashSlabMap;
+    /// let mut config = HashSlabMap::new();
+    /// config.insert("port", 80);
+    /// config.insert("host", 1);
+    ///
+    /// let mut tx = config.begin();
+    /// tx.remove("host");
+    /// tx.insert("port", 8080);
+    /// tx.insert("debug", 1);
+    /// assert_eq!(tx.get_index_of("debug"), Some(1));
+    /// // Validation failed, changes are reverted on drop
+    /// drop(tx);
+    ///
+    /// assert_eq!(config.get_full("host"), Some((1, &"host", &1)));
+    /// assert_eq!(config["port"], 80);
+    /// assert!(!config.contains_key("debug"));
+    ///
+    /// let mut tx = config.begin();
+    /// tx.insert("port", 8080);
+    /// tx.commit();
+    /// assert_eq!(config["port"], 8080);
+    /// ```
+    pub fn begin(&mut self) -> Transaction<'_, K, V, S> {
+        Transaction::new(self)
+    }
+
     /// Retains only the elements specified by the predicate. Keeps the
     /// allocated memory for reuse.
     ///
@@ -1100,7 +1142,7 @@ impl<K: Clone, V: Clone, S: Clone> Clone for HashSlabMap<K, V, S> {
     }
 }
 
-impl<K, V> fmt::Debug for HashSlabMap<K, V>
+impl<K, V, S> fmt::Debug for HashSlabMap<K, V, S>
 where
     K: fmt::Debug,
     V: fmt::Debug,
@@ -1342,7 +1384,7 @@ where
         let reserve = if self.is_empty() {
             iter.size_hint().0
         } else {
-            (iter.size_hint().0 + 1) / 2
+            iter.size_hint().0.div_ceil(2)
         };
         self.reserve(reserve);
         iter.for_each(move |(k, v)| {
@@ -1429,6 +1471,8 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 impl<K, V, const N: usize> From<[(K, V); N]> for HashSlabMap<K, V, RandomState>
 where
     K: Hash + Eq,
@@ -1460,7 +1504,7 @@ where
         }
 
         self.iter()
-            .all(|(key, value)| other.get(key).map_or(false, |v| *value == *v))
+            .all(|(key, value)| other.get(key).is_some_and(|v| *value == *v))
     }
 }
 
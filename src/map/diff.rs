@@ -0,0 +1,388 @@
+use alloc::vec::Vec;
+use core::{
+    hash::{BuildHasher, Hash},
+    mem,
+};
+
+use hashbrown::{hash_table, Equivalent, HashTable};
+use thiserror::Error;
+
+use crate::{KeyData, ValueData};
+
+use super::{layout::SPARE_INDICES, HashSlabMap};
+
+/// One difference between two maps.
+///
+/// Indices of [`Removed`](Self::Removed) entries and the `from` index of
+/// [`Moved`](Self::Moved) ones refer to the old map, all other indices to the
+/// new one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PatchOp<K, V> {
+    /// The key was removed.
+    Removed {
+        /// The index of the key in the old map.
+        index: usize,
+        /// The removed key.
+        key: K,
+    },
+    /// The key was moved to another index.
+    Moved {
+        /// The index of the key in the old map.
+        from: usize,
+        /// The index of the key in the new map.
+        to: usize,
+        /// The moved key.
+        key: K,
+    },
+    /// The value of the key changed.
+    Changed {
+        /// The index of the key in the new map.
+        index: usize,
+        /// The key.
+        key: K,
+        /// The new value.
+        value: V,
+    },
+    /// The key was added.
+    Added {
+        /// The index of the key in the new map.
+        index: usize,
+        /// The added key.
+        key: K,
+        /// The value of the key.
+        value: V,
+    },
+}
+
+/// The differences between two maps, which turn the old one into the new one.
+///
+/// Removed entries come first, then moved, changed and added ones, each
+/// kind in index order.
+///
+/// This `struct` is created by the [`diff`] function. See its documentation
+/// for more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Patch<K, V> {
+    ops: Vec<PatchOp<K, V>>,
+}
+
+impl<K, V> Patch<K, V> {
+    /// Return the differences.
+    pub fn ops(&self) -> &[PatchOp<K, V>] {
+        &self.ops
+    }
+
+    /// Return the number of differences.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns `true` if both maps were equal, with the same indices.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+impl<K, V> From<Vec<PatchOp<K, V>>> for Patch<K, V> {
+    fn from(ops: Vec<PatchOp<K, V>>) -> Self {
+        Self { ops }
+    }
+}
+
+impl<K, V> IntoIterator for Patch<K, V> {
+    type Item = PatchOp<K, V>;
+    type IntoIter = alloc::vec::IntoIter<PatchOp<K, V>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ops.into_iter()
+    }
+}
+
+/// The error returned by [`HashSlabMap::apply_patch`] if the map is not in
+/// the state the patch was computed from. The map is left unchanged.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchError {
+    /// The entry at the index does not have the key named by the patch.
+    #[error("the entry at index {0} does not match the patch")]
+    KeyMismatch(usize),
+    /// An entry would be placed at an index which stays occupied.
+    #[error("index {0} is already occupied")]
+    Occupied(usize),
+    /// A key added or moved to the index already exists in the map, or is
+    /// placed at another index by the patch too.
+    #[error("the key placed at index {0} already exists")]
+    DuplicateKey(usize),
+    /// An entry would be placed at an index beyond the capacity of the map
+    /// plus the number of placed entries and 65536 spare indices.
+    #[error("index {0} is out of range")]
+    OutOfRange(usize),
+}
+
+/// Compute the differences which turn `old` into `new`, keeping the indices
+/// of `new`.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::HashSlabMap;
+/// # use hashslab::map::{diff, PatchOp};
+/// let old = HashSlabMap::from([("a", 1), ("b", 2), ("c", 3)]);
+/// let mut new = old.clone();
+/// new.remove("a");
+/// new.insert("b", 20);
+/// new.insert("d", 4);
+///
+/// let patch = diff(&old, &new);
+/// assert_eq!(
+///     patch.ops(),
+///     [
+///         PatchOp::Removed { index: 0, key: "a" },
+///         PatchOp::Changed { index: 1, key: "b", value: 20 },
+///         PatchOp::Added { index: 0, key: "d", value: 4 },
+///     ]
+/// );
+///
+/// let mut copy = old.clone();
+/// copy.apply_patch(patch).unwrap();
+/// assert_eq!(copy.get_index(0), Some((&"d", &4)));
+/// assert_eq!(copy, new);
+/// ```
+pub fn diff<K, V, S1, S2>(old: &HashSlabMap<K, V, S1>, new: &HashSlabMap<K, V, S2>) -> Patch<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: PartialEq + Clone,
+    S1: BuildHasher,
+    S2: BuildHasher,
+{
+    let mut removed = Vec::new();
+    let mut moved = Vec::new();
+    let mut changed = Vec::new();
+    let mut added = Vec::new();
+    for (index, key, value) in old.iter_full() {
+        match new.get_full(key) {
+            None => removed.push(PatchOp::Removed {
+                index,
+                key: key.clone(),
+            }),
+            Some((to, _, new_value)) => {
+                if to != index {
+                    moved.push(PatchOp::Moved {
+                        from: index,
+                        to,
+                        key: key.clone(),
+                    });
+                }
+                if new_value != value {
+                    changed.push(PatchOp::Changed {
+                        index: to,
+                        key: key.clone(),
+                        value: new_value.clone(),
+                    });
+                }
+            }
+        }
+    }
+    for (index, key, value) in new.iter_full() {
+        if !old.contains_key(key) {
+            added.push(PatchOp::Added {
+                index,
+                key: key.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    let position = |op: &PatchOp<K, V>| match op {
+        PatchOp::Removed { index, .. } => *index,
+        PatchOp::Moved { to, .. } => *to,
+        PatchOp::Changed { index, .. } => *index,
+        PatchOp::Added { index, .. } => *index,
+    };
+    let mut ops = Vec::new();
+    for mut kind in [removed, moved, changed, added] {
+        kind.sort_unstable_by_key(position);
+        ops.append(&mut kind);
+    }
+    Patch { ops }
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Apply a patch computed by [`diff`] from a map with the same entries at
+    /// the same indices, placing the entries at the indices they have in the
+    /// new map.
+    ///
+    /// Entries end up at the same indices as in the new map, but vacant
+    /// indices may be reused by later insertions in a different order.
+    ///
+    /// # Errors
+    ///
+    /// If the map does not match the old map of the patch, it is left
+    /// unchanged and a [`PatchError`] is returned.
+    pub fn apply_patch(&mut self, patch: Patch<K, V>) -> Result<(), PatchError> {
+        self.check_patch(&patch)?;
+
+        let mut placed = Vec::new();
+        let mut changed = Vec::new();
+        for op in patch {
+            match op {
+                PatchOp::Removed { index, .. } => {
+                    self.remove_index(index);
+                }
+                PatchOp::Moved { from, to, .. } => {
+                    let (key, value) = self
+                        .remove_index(from)
+                        .expect("checked patch moves an existing entry");
+                    placed.push((to, key, value));
+                }
+                PatchOp::Changed { index, value, .. } => changed.push((index, value)),
+                PatchOp::Added { index, key, value } => placed.push((index, key, value)),
+            }
+        }
+        self.place(placed);
+        for (index, value) in changed {
+            self.slab[index].value = value;
+        }
+        Ok(())
+    }
+
+    // Check the patch against the map without changing it.
+    fn check_patch(&self, patch: &Patch<K, V>) -> Result<(), PatchError> {
+        let mut freed = Vec::new();
+        let mut targets = Vec::new();
+        let mut moves = Vec::new();
+        for op in patch.ops() {
+            match op {
+                PatchOp::Removed { index, key } => {
+                    self.check_key(*index, key)?;
+                    freed.push(*index);
+                }
+                PatchOp::Moved { from, to, key } => {
+                    self.check_key(*from, key)?;
+                    freed.push(*from);
+                    targets.push(*to);
+                    moves.push((*to, key));
+                }
+                PatchOp::Added { index, .. } => targets.push(*index),
+                PatchOp::Changed { .. } => {}
+            }
+        }
+        freed.sort_unstable();
+        targets.sort_unstable();
+        moves.sort_unstable_by_key(|&(to, _)| to);
+        let is_freed = |index: &usize| freed.binary_search(index).is_ok();
+
+        // An entry can only be removed or moved away once
+        if let Some(pair) = freed.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(PatchError::KeyMismatch(pair[0]));
+        }
+        // and a key placed once
+        let mut placed = HashTable::with_capacity(targets.len());
+        for op in patch.ops() {
+            let (PatchOp::Added { index, key, .. } | PatchOp::Moved { to: index, key, .. }) = op
+            else {
+                continue;
+            };
+            let hash = self.builder.hash_one(key);
+            match placed.entry(
+                hash,
+                |&other| other == key,
+                |&other| self.builder.hash_one(other),
+            ) {
+                hash_table::Entry::Occupied(_) => return Err(PatchError::DuplicateKey(*index)),
+                hash_table::Entry::Vacant(entry) => {
+                    entry.insert(key);
+                }
+            }
+        }
+
+        // Placing an entry allocates the slab up to its index
+        let bound = self
+            .slab
+            .capacity()
+            .saturating_add(targets.len())
+            .saturating_add(SPARE_INDICES);
+        if let Some(&index) = targets.last().filter(|&&index| index >= bound) {
+            return Err(PatchError::OutOfRange(index));
+        }
+        if let Some(pair) = targets.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(PatchError::Occupied(pair[0]));
+        }
+        if let Some(&index) = targets
+            .iter()
+            .find(|&index| self.contains_index(*index) && !is_freed(index))
+        {
+            return Err(PatchError::Occupied(index));
+        }
+        for op in patch.ops() {
+            match op {
+                PatchOp::Added { index, key, .. }
+                    if self.get_index_of(key).is_some_and(|i| !is_freed(&i)) =>
+                {
+                    return Err(PatchError::DuplicateKey(*index));
+                }
+                PatchOp::Changed { index, key, .. } => {
+                    let in_place = self.get_index_of(key) == Some(*index) && !is_freed(index);
+                    let moved_here = moves
+                        .binary_search_by_key(index, |&(to, _)| to)
+                        .is_ok_and(|i| moves[i].1 == key);
+                    if !in_place && !moved_here {
+                        return Err(PatchError::KeyMismatch(*index));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn check_key<Q>(&self, index: usize, key: &Q) -> Result<(), PatchError>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        match self.get_index_of(key) {
+            Some(i) if i == index => Ok(()),
+            _ => Err(PatchError::KeyMismatch(index)),
+        }
+    }
+
+    // Insert entries at free indices of the slab.
+    pub(crate) fn place(&mut self, mut entries: Vec<(usize, K, V)>) {
+        entries.sort_unstable_by_key(|&(index, _, _)| index);
+        let mut entries = entries.into_iter().peekable();
+        // Take the fast path while the slab hands out the wanted indices
+        while let Some((index, key, value)) = entries.next_if(|e| e.0 == self.slab.vacant_key()) {
+            let (_, old) = self.insert_full(key, value);
+            debug_assert!(old.is_none() && self.contains_index(index));
+        }
+        let rest: Vec<_> = entries
+            .map(|(index, key, value)| {
+                let hash = self.builder.hash_one(&key);
+                (index, key, hash, value)
+            })
+            .collect();
+        if rest.is_empty() {
+            return;
+        }
+        let mut keys = Vec::with_capacity(rest.len());
+        let values = rest.into_iter().map(|(index, key, hash, value)| {
+            keys.push((index, key, hash));
+            (index, ValueData::new(value, hash))
+        });
+        self.slab = mem::take(&mut self.slab)
+            .into_iter()
+            .chain(values)
+            .collect();
+        let slab = &self.slab;
+        for (index, key, hash) in keys {
+            self.table
+                .insert_unique(hash, KeyData::new(key, index), |e| slab[e.index].hash);
+        }
+    }
+}
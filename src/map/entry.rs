@@ -1,4 +1,4 @@
-use std::{fmt, hash::Hash, mem};
+use core::{fmt, hash::Hash, mem};
 
 use hashbrown::hash_table;
 use slab::Slab;
@@ -0,0 +1,206 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use slab::Slab;
+
+use crate::{KeyData, ValueData};
+
+use super::HashSlabMap;
+
+// The vacant indices a deserialized map or a patch may add beyond one per
+// entry.
+pub(super) const SPARE_INDICES: usize = 1 << 16;
+
+// The bound on the indices of a deserialized map with `len` entries. Maps
+// with higher indices are not serialized, so everything written can be read
+// back.
+#[cfg(any(feature = "serde", feature = "borsh"))]
+pub(crate) fn index_bound(len: usize) -> usize {
+    len.saturating_mul(2).saturating_add(SPARE_INDICES)
+}
+
+impl<K, V, S> HashSlabMap<K, V, S> {
+    // The vacant indices in the order the slab reuses them, without the tail
+    // of indices it would hand out in sequence anyway.
+    //
+    // The slab only exposes the head of its free list, and a vacant slot can
+    // only be found by filling the ones before it, so the list is read by
+    // filling a copy of the slab with clones of a value. A map without entries
+    // has no value to clone, and its free list is reported as empty; it could
+    // not be rebuilt without a value either.
+    pub(crate) fn free_list(&self) -> Vec<usize>
+    where
+        V: Clone,
+    {
+        let Some(last) = self.slab.iter().map(|(index, _)| index).max() else {
+            return Vec::new();
+        };
+        let filler = &self.slab[last];
+        let mut probe = self.slab.clone();
+        let mut list = Vec::new();
+        // Once the probe is full, every vacant slot was handed out
+        while probe.len() < probe.capacity() {
+            list.push(probe.insert(filler.clone()));
+        }
+
+        let mut highest = Vec::with_capacity(list.len());
+        list.iter().fold(last, |max, &index| {
+            highest.push(max);
+            max.max(index)
+        });
+        while list
+            .last()
+            .zip(highest.last())
+            .is_some_and(|(&index, &max)| index == max + 1)
+        {
+            list.pop();
+            highest.pop();
+        }
+        list
+    }
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    // Build a map with the entries at their indices and the slab reusing the
+    // vacant indices in the order of `free_list`, with room for up to
+    // `capacity` entries but no more than twice the highest index. Every index
+    // below the highest one must be either occupied or free, exactly once, and
+    // keys must be unique. Returns `None` if the layout is inconsistent.
+    pub(crate) fn from_layout(
+        entries: Vec<(usize, K, V)>,
+        free_list: &[usize],
+        capacity: usize,
+        builder: S,
+    ) -> Option<Self>
+    where
+        V: Clone,
+    {
+        let end = entries
+            .iter()
+            .map(|&(index, _, _)| index)
+            .chain(free_list.iter().copied())
+            .max()
+            .map_or(0, |max| max + 1);
+        if end != entries.len() + free_list.len() {
+            return None;
+        }
+        // The capacity is only a hint, and should not allocate far beyond the
+        // entries
+        let capacity = capacity.min(end.saturating_mul(2));
+        let mut used = Vec::new();
+        used.resize(end, false);
+        for index in entries
+            .iter()
+            .map(|&(index, _, _)| index)
+            .chain(free_list.iter().copied())
+        {
+            if core::mem::replace(&mut used[index], true) {
+                return None;
+            }
+        }
+
+        let mut map = Self::with_capacity_and_hasher(0, builder);
+        let mut keys = Vec::with_capacity(entries.len());
+        let values: Vec<_> = entries
+            .into_iter()
+            .map(|(index, key, value)| {
+                let hash = map.builder.hash_one(&key);
+                keys.push((index, key, hash));
+                (index, ValueData::new(value, hash))
+            })
+            .collect();
+        let filler = values.first().map(|(_, value)| value.clone());
+        if filler.is_none() && !free_list.is_empty() {
+            return None;
+        }
+        // Fill the vacant slots, and free them so the last freed one, the head
+        // of the list, is reused first
+        let holes = free_list
+            .iter()
+            .filter_map(|&index| Some((index, filler.clone()?)));
+        map.slab = values.into_iter().chain(holes).collect::<Slab<_>>();
+        for &index in free_list.iter().rev() {
+            map.slab.remove(index);
+        }
+
+        if !map.insert_keys(keys) {
+            return None;
+        }
+        map.try_reserve(capacity.saturating_sub(map.len())).ok()?;
+        Some(map)
+    }
+
+    // Build a map with the entries at their indices, as decoded from a
+    // serialization format. Returns `None` if an index or a key is repeated,
+    // or if an index is not below `index_bound`, so a forged index can't make
+    // the slab allocate memory out of proportion to the input.
+    //
+    // The slab is collected in index order, which links the vacant indices
+    // highest first.
+    #[cfg(any(feature = "serde", feature = "borsh"))]
+    pub(crate) fn from_indexed(mut entries: Vec<(usize, K, V)>, builder: S) -> Option<Self> {
+        entries.sort_unstable_by_key(|&(index, _, _)| index);
+        if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return None;
+        }
+        if entries
+            .last()
+            .is_some_and(|&(index, _, _)| index >= index_bound(entries.len()))
+        {
+            return None;
+        }
+        let mut map = Self::with_capacity_and_hasher(0, builder);
+        let mut keys = Vec::with_capacity(entries.len());
+        map.slab = entries
+            .into_iter()
+            .map(|(index, key, value)| {
+                let hash = map.builder.hash_one(&key);
+                keys.push((index, key, hash));
+                (index, ValueData::new(value, hash))
+            })
+            .collect();
+        map.insert_keys(keys).then_some(map)
+    }
+
+    // Insert the keys of the entries in the slab, returning `false` if a key
+    // is repeated.
+    fn insert_keys(&mut self, keys: Vec<(usize, K, u64)>) -> bool {
+        self.table.reserve(keys.len(), |e| self.slab[e.index].hash);
+        for (index, key, hash) in keys {
+            let slab = &self.slab;
+            let duplicate = self
+                .table
+                .find(hash, |e| slab[e.index].hash == hash && e.key == key)
+                .is_some();
+            if duplicate {
+                return false;
+            }
+            self.table
+                .insert_unique(hash, KeyData::new(key, index), |e| slab[e.index].hash);
+        }
+        true
+    }
+
+    // Insert a generated entry, then remove up to two entries as the churn
+    // byte says, so vacant indices pile up like in long-lived maps. The
+    // choice depends only on the indices, so replaying the same keys and
+    // churn bytes yields the same layout with any hasher.
+    #[cfg(any(feature = "arbitrary", feature = "proptest", feature = "quickcheck"))]
+    pub(crate) fn churn(&mut self, key: K, value: V, churn: u8) {
+        self.insert(key, value);
+        let removals = (churn >> 6).saturating_sub(1);
+        for pick in 0..removals as usize {
+            let mut indices: Vec<_> = self.iter_full().map(|(index, _, _)| index).collect();
+            if indices.is_empty() {
+                break;
+            }
+            indices.sort_unstable();
+            let pick = (churn as usize & 0x3f) * (pick + 1);
+            self.remove_index(indices[pick % indices.len()]);
+        }
+    }
+}
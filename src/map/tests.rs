@@ -169,3 +169,251 @@ fn drain() {
         s.extend((1..100).map(|n| (n, n * 10)));
     }
 }
+
+fn entries<K: Ord + Clone, V: Ord + Clone>(map: &HashSlabMap<K, V>) -> Vec<(usize, K, V)> {
+    let mut vec: Vec<_> = map
+        .iter_full()
+        .map(|(i, k, v)| (i, k.clone(), v.clone()))
+        .collect();
+    vec.sort_unstable();
+    vec
+}
+
+#[test]
+fn transaction_rollback_restores_indices() {
+    let mut map: HashSlabMap<u32, u32> = (0..6).map(|i| (i, i * 10)).collect();
+    map.remove(&1);
+    map.remove(&4);
+    let mut expected = map.clone();
+    let before = entries(&map);
+
+    let mut tx = map.begin();
+    assert_eq!(tx.insert(0, 1), Some(&0));
+    assert_eq!(tx.insert(0, 2), Some(&1));
+    assert_eq!(tx.remove(&2), Some(&20));
+    // Freed slots are reused within the transaction
+    assert_eq!(tx.insert_full(7, 70), (2, None));
+    assert_eq!(tx.insert_full(8, 80), (4, None));
+    assert_eq!(tx.remove_index(5), Some((&5, &50)));
+    tx.retain(|&k, _| k != 3);
+    tx.extend([(9, 90), (10, 100), (11, 110)]);
+    assert_eq!(tx.len(), 6);
+    tx.rollback();
+
+    assert_eq!(entries(&map), before);
+    assert_eq!(map.get_index_of(&2), Some(2));
+    // The free list is restored too, so new keys get the same indices
+    for key in 20..25 {
+        assert_eq!(map.insert_full(key, 0), expected.insert_full(key, 0));
+    }
+}
+
+#[test]
+fn transaction_checkpoint_and_commit() {
+    let mut map = HashSlabMap::new();
+    map.insert('a', 1);
+
+    let mut tx = map.begin();
+    tx.insert('b', 2);
+    let checkpoint = tx.checkpoint();
+    tx.insert('c', 3);
+    tx.remove(&'a');
+    tx.clear();
+    assert!(tx.is_empty());
+    tx.rollback_to(checkpoint);
+    assert_eq!(tx.len(), 2);
+    tx.insert('d', 4);
+    tx.commit();
+
+    assert_eq!(map.get_full(&'b'), Some((1, &'b', &2)));
+    assert_eq!(map.get_full(&'d'), Some((2, &'d', &4)));
+    assert!(!map.contains_key(&'c'));
+
+    // Dropping an uncommitted transaction rolls it back
+    map.begin().clear();
+    assert_eq!(map.len(), 3);
+}
+
+#[test]
+#[should_panic(expected = "Transaction: checkpoint was already rolled back")]
+fn transaction_stale_checkpoint() {
+    let mut map = HashSlabMap::new();
+    let mut tx = map.begin();
+    let start = tx.checkpoint();
+    tx.insert(1, 1);
+    let checkpoint = tx.checkpoint();
+    tx.rollback_to(start);
+    tx.rollback_to(checkpoint);
+}
+
+fn full<K: Copy + Ord, V: Copy + Ord, S>(map: &HashSlabMap<K, V, S>) -> Vec<(usize, K, V)> {
+    let mut entries: Vec<_> = map.iter_full().map(|(i, &k, &v)| (i, k, v)).collect();
+    entries.sort_unstable();
+    entries
+}
+
+#[test]
+fn diff_and_apply_patch() {
+    let old: HashSlabMap<char, u32> = ('a'..='f').zip(0..).collect();
+    let mut new = old.clone();
+    // Swap the indices of 'a' and 'b', and move 'c' past the end
+    let a = new.remove(&'a').unwrap();
+    let b = new.remove(&'b').unwrap();
+    new.insert('a', a + 10);
+    new.insert('b', b);
+    new.remove(&'c');
+    new.insert('x', 9);
+    new.insert('y', 9);
+    new.insert('c', 2);
+    new.remove(&'x');
+    new.remove(&'d');
+
+    let patch = diff(&old, &new);
+    assert_eq!(
+        patch.ops(),
+        [
+            PatchOp::Removed { index: 3, key: 'd' },
+            PatchOp::Moved {
+                from: 1,
+                to: 0,
+                key: 'b'
+            },
+            PatchOp::Moved {
+                from: 0,
+                to: 1,
+                key: 'a'
+            },
+            PatchOp::Moved {
+                from: 2,
+                to: 7,
+                key: 'c'
+            },
+            PatchOp::Changed {
+                index: 1,
+                key: 'a',
+                value: 10
+            },
+            PatchOp::Added {
+                index: 6,
+                key: 'y',
+                value: 9
+            },
+        ]
+    );
+
+    let mut map = old.clone();
+    map.apply_patch(patch).unwrap();
+    assert_eq!(full(&map), full(&new));
+    assert!(diff(&map, &new).is_empty());
+    assert_eq!(map.get_index_of(&'c'), Some(7));
+}
+
+#[test]
+fn apply_patch_checks_the_map() {
+    let old: HashSlabMap<char, u32> = ('a'..='c').zip(0..).collect();
+    let mut new = old.clone();
+    new.remove(&'a');
+    new.insert('d', 3);
+    new.insert('b', 10);
+    let patch = diff(&old, &new);
+
+    let mut other = old.clone();
+    other.remove(&'a');
+    assert_eq!(
+        other.clone().apply_patch(patch.clone()),
+        Err(PatchError::KeyMismatch(0))
+    );
+    other.insert('d', 0);
+    let before = full(&other);
+    assert_eq!(
+        other.apply_patch(patch.clone()),
+        Err(PatchError::KeyMismatch(0))
+    );
+    assert_eq!(full(&other), before);
+
+    let mut taken = old.clone();
+    taken.insert('z', 0);
+    let added = Patch::from(std::vec![PatchOp::Added {
+        index: 3,
+        key: 'y',
+        value: 0
+    }]);
+    assert_eq!(taken.apply_patch(added), Err(PatchError::Occupied(3)));
+
+    // Keys placed or entries freed twice by the patch itself
+    let invalid = [
+        (
+            std::vec![
+                PatchOp::Added {
+                    index: 3,
+                    key: 'x',
+                    value: 0,
+                },
+                PatchOp::Added {
+                    index: 4,
+                    key: 'x',
+                    value: 1,
+                },
+            ],
+            PatchError::DuplicateKey(4),
+        ),
+        (
+            std::vec![
+                PatchOp::Moved {
+                    from: 0,
+                    to: 3,
+                    key: 'a',
+                },
+                PatchOp::Added {
+                    index: 4,
+                    key: 'a',
+                    value: 1,
+                },
+            ],
+            PatchError::DuplicateKey(4),
+        ),
+        (
+            std::vec![
+                PatchOp::Removed { index: 0, key: 'a' },
+                PatchOp::Moved {
+                    from: 0,
+                    to: 5,
+                    key: 'a',
+                },
+            ],
+            PatchError::KeyMismatch(0),
+        ),
+        (
+            std::vec![PatchOp::Added {
+                index: 1 << 40,
+                key: 'x',
+                value: 0,
+            }],
+            PatchError::OutOfRange(1 << 40),
+        ),
+    ];
+    for (ops, error) in invalid {
+        let mut map = old.clone();
+        assert_eq!(map.apply_patch(Patch::from(ops)), Err(error));
+        assert_eq!(full(&map), full(&old));
+    }
+
+    let mut map = old;
+    map.apply_patch(patch).unwrap();
+    assert_eq!(full(&map), full(&new));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn patch_serde() {
+    let old = HashSlabMap::from([(1, 'a'), (2, 'b')]);
+    let mut new = old.clone();
+    new.remove(&1);
+    new.insert(3, 'c');
+    new.insert(2, 'x');
+
+    let patch = diff(&old, &new);
+    let json = serde_json::to_string(&patch).unwrap();
+    let decoded: Patch<i32, char> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, patch);
+}
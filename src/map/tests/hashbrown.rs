@@ -680,7 +680,7 @@ fn test_index_nonexistent() {
     map.insert(2, 1);
     map.insert(3, 4);
 
-    #[allow(clippy::no_effect)] // false positive lint
+    #[allow(clippy::no_effect, clippy::unnecessary_operation)] // false positive lint
     map[&4];
 }
 
@@ -0,0 +1,260 @@
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    mem,
+    ops::Deref,
+};
+
+use hashbrown::Equivalent;
+
+use crate::{KeyData, ValueData};
+
+use super::HashSlabMap;
+
+// A change made to a map, with what's needed to revert it.
+pub(crate) enum Change<K, V> {
+    Insert(usize),
+    Replace(usize, V),
+    Remove {
+        index: usize,
+        key: K,
+        value: V,
+        hash: u64,
+    },
+}
+
+impl<K, V> Change<K, V> {
+    // Revert the change, returning the change which reverts it back. Changes
+    // must be reverted in the reverse order they were made.
+    pub(crate) fn revert<S>(self, map: &mut HashSlabMap<K, V, S>) -> Self {
+        match self {
+            Change::Insert(index) => {
+                let ValueData { value, hash } = map.slab.remove(index);
+                let key = match map.table.find_entry(hash, |e| e.index == index) {
+                    Ok(entry) => entry.remove().0.key,
+                    Err(_) => unreachable!("HashSlabMap: index is missing in the table"),
+                };
+                Change::Remove {
+                    index,
+                    key,
+                    value,
+                    hash,
+                }
+            }
+            Change::Replace(index, value) => {
+                Change::Replace(index, mem::replace(&mut map.slab[index].value, value))
+            }
+            Change::Remove {
+                index,
+                key,
+                value,
+                hash,
+            } => {
+                // The slab reuses the most recently freed slot first, so the
+                // entry gets its index back
+                let restored = map.slab.insert(ValueData::new(value, hash));
+                debug_assert_eq!(restored, index);
+                let slab = &map.slab;
+                map.table
+                    .insert_unique(hash, KeyData::new(key, restored), |e| slab[e.index].hash);
+                Change::Insert(restored)
+            }
+        }
+    }
+}
+
+/// A position in the undo log of a [`Transaction`].
+///
+/// This `struct` is created by the [`Transaction::checkpoint`] method.
+/// See its documentation for more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Checkpoint(usize);
+
+/// A batch of changes to a [`HashSlabMap`] which can be reverted.
+///
+/// Every change made through the transaction is recorded in an undo log.
+/// [`rollback`][Self::rollback], or dropping the transaction without calling
+/// [`commit`][Self::commit], reverts all of them and restores the exact
+/// previous state: the same keys and values at the same indices, with freed
+/// slots reused by later insertions in the same order as before.
+///
+/// The map can be read through [`Deref`] while the transaction is open.
+/// Replaced and removed values are kept in the undo log until the
+/// transaction ends, so they are only returned by reference.
+///
+/// This `struct` is created by the [`HashSlabMap::begin`] method.
+/// See its documentation for more.
+pub struct Transaction<'a, K, V, S> {
+    map: &'a mut HashSlabMap<K, V, S>,
+    log: Vec<Change<K, V>>,
+}
+
+impl<'a, K, V, S> Transaction<'a, K, V, S> {
+    pub(super) fn new(map: &'a mut HashSlabMap<K, V, S>) -> Self {
+        Self {
+            map,
+            log: Vec::new(),
+        }
+    }
+
+    /// Return the current position in the undo log, to revert later changes
+    /// with [`rollback_to`][Self::rollback_to].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.log.len())
+    }
+
+    /// Revert all changes made after `checkpoint`, keeping the transaction
+    /// open.
+    ///
+    /// ***Panics***
+    ///
+    /// If changes made before `checkpoint` were already reverted.
+    pub fn rollback_to(&mut self, checkpoint: Checkpoint) {
+        assert!(
+            checkpoint.0 <= self.log.len(),
+            "Transaction: checkpoint was already rolled back"
+        );
+        let undone = self.log.split_off(checkpoint.0);
+        for change in undone.into_iter().rev() {
+            change.revert(self.map);
+        }
+    }
+
+    /// Keep all changes and close the transaction.
+    pub fn commit(mut self) {
+        self.log.clear();
+    }
+
+    /// Revert all changes and close the transaction.
+    pub fn rollback(self) {
+        // Reverted on drop
+    }
+}
+
+impl<K, V, S> Transaction<'_, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Insert a key-value pair in the map.
+    ///
+    /// If an equivalent key already exists, its value is replaced and the old
+    /// value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<&V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Insert a key-value pair in the map, and get its index together with
+    /// the old value if an equivalent key already existed.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<&V>) {
+        match self.map.insert_full(key, value) {
+            (index, Some(old)) => {
+                self.log.push(Change::Replace(index, old));
+                match self.log.last() {
+                    Some(Change::Replace(_, old)) => (index, Some(old)),
+                    _ => unreachable!(),
+                }
+            }
+            (index, None) => {
+                self.log.push(Change::Insert(index));
+                (index, None)
+            }
+        }
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return it and the
+    /// index it had.
+    pub fn remove_full<Q>(&mut self, key: &Q) -> Option<(usize, &K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.map.get_index_of(key)?;
+        self.remove_index(index)
+            .map(|(key, value)| (index, key, value))
+    }
+
+    /// Remove the key-value pair by index.
+    pub fn remove_index(&mut self, index: usize) -> Option<(&K, &V)> {
+        let hash = self.map.slab.get(index)?.hash;
+        let (key, value) = self.map.remove_index(index)?;
+        self.log.push(Change::Remove {
+            index,
+            key,
+            value,
+            hash,
+        });
+        match self.log.last() {
+            Some(Change::Remove { key, value, .. }) => Some((key, value)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Retains only the key-value pairs specified by the predicate.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let removed: Vec<_> = self
+            .map
+            .iter_full()
+            .filter(|&(_, key, value)| !f(key, value))
+            .map(|(index, _, _)| index)
+            .collect();
+        for index in removed {
+            self.remove_index(index);
+        }
+    }
+
+    /// Remove all key-value pairs.
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for Transaction<'_, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
+
+impl<K, V, S> Deref for Transaction<'_, K, V, S> {
+    type Target = HashSlabMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        self.map
+    }
+}
+
+impl<K, V, S> Drop for Transaction<'_, K, V, S> {
+    fn drop(&mut self) {
+        self.rollback_to(Checkpoint(0));
+    }
+}
+
+impl<K, V, S> fmt::Debug for Transaction<'_, K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("map", &self.map.iter_full())
+            .field("changes", &self.log.len())
+            .finish()
+    }
+}
@@ -0,0 +1,641 @@
+//! A [`HashSlabMap`] wrapper reporting every change to an [`Observer`]
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    iter::FusedIterator,
+    mem,
+    ops::Deref,
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+
+use crate::{map, HashSlabMap};
+
+#[cfg(test)]
+mod tests;
+
+/// Callbacks invoked by [`ObservedHashSlabMap`] on every change.
+///
+/// All methods do nothing by default. They are called right after the change
+/// was applied. [`on_remove`][Self::on_remove] is given the removed entry by
+/// value and hands it back, to be returned by the removal or dropped.
+pub trait Observer<K, V> {
+    /// A new key was inserted at `index`.
+    fn on_insert(&mut self, index: usize, key: &K, value: &V) {
+        let _ = (index, key, value);
+    }
+
+    /// The value of the key at `index` was replaced or modified.
+    fn on_update(&mut self, index: usize, key: &K, value: &V) {
+        let _ = (index, key, value);
+    }
+
+    /// The key at `index` was removed, `index` may be reused afterwards.
+    ///
+    /// The removed key and value are passed by value, so parts of them can be
+    /// taken without cloning, and must be returned: they are what the removal
+    /// returns, if it returns the removed entry.
+    fn on_remove(&mut self, index: usize, key: K, value: V) -> (K, V) {
+        let _ = index;
+        (key, value)
+    }
+}
+
+impl<K, V> Observer<K, V> for () {}
+
+impl<K, V, O: Observer<K, V> + ?Sized> Observer<K, V> for &mut O {
+    fn on_insert(&mut self, index: usize, key: &K, value: &V) {
+        (**self).on_insert(index, key, value);
+    }
+
+    fn on_update(&mut self, index: usize, key: &K, value: &V) {
+        (**self).on_update(index, key, value);
+    }
+
+    fn on_remove(&mut self, index: usize, key: K, value: V) -> (K, V) {
+        (**self).on_remove(index, key, value)
+    }
+}
+
+/// A [`HashSlabMap`] which reports every insertion, update and removal to an
+/// [`Observer`].
+///
+/// The map can be read through [`Deref`], but only modified through the
+/// methods of this type, so no change goes unnoticed. Values can't be
+/// borrowed mutably: modify them with [`update`][Self::update],
+/// [`update_index`][Self::update_index] or [`Entry::and_modify`], which
+/// report the change once done.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::{observed::Observer, ObservedHashSlabMap};
+/// // Keep a secondary index from values to map indices
+/// #[derive(Default)]
+/// struct ByValue(Vec<(u32, usize)>);
+///
+/// impl<K> Observer<K, u32> for ByValue {
+///     fn on_insert(&mut self, index: usize, _: &K, value: &u32) {
+///         self.0.push((*value, index));
+///     }
+///
+///     fn on_update(&mut self, index: usize, key: &K, value: &u32) {
+///         self.0.retain(|&(_, i)| i != index);
+///         self.on_insert(index, key, value);
+///     }
+///
+///     fn on_remove(&mut self, index: usize, key: K, value: u32) -> (K, u32) {
+///         self.0.retain(|&(_, i)| i != index);
+///         (key, value)
+///     }
+/// }
+///
+/// let mut map = ObservedHashSlabMap::new(ByValue::default());
+/// map.insert("a", 1);
+/// map.insert("b", 2);
+/// map.entry("a").and_modify(|value| *value = 10);
+/// map.retain(|_, &value| value > 5);
+///
+/// assert_eq!(map.len(), 1);
+/// assert_eq!(map.observer().0, [(10, 0)]);
+/// ```
+#[cfg(feature = "std")]
+pub struct ObservedHashSlabMap<K, V, O, S = RandomState> {
+    map: HashSlabMap<K, V, S>,
+    observer: O,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct ObservedHashSlabMap<K, V, O, S> {
+    map: HashSlabMap<K, V, S>,
+    observer: O,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V, O> ObservedHashSlabMap<K, V, O> {
+    /// Creates an empty `ObservedHashSlabMap` reporting to `observer`.
+    pub fn new(observer: O) -> Self {
+        Self::with_hasher(Default::default(), observer)
+    }
+}
+
+impl<K, V, O, S> ObservedHashSlabMap<K, V, O, S> {
+    /// Creates an empty `ObservedHashSlabMap` which will use the given hash
+    /// builder and report to `observer`.
+    pub const fn with_hasher(hash_builder: S, observer: O) -> Self {
+        Self {
+            map: HashSlabMap::with_hasher(hash_builder),
+            observer,
+        }
+    }
+
+    /// Return a reference to the observer.
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /// Return a mutable reference to the observer.
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Return the underlying map.
+    pub fn as_map(&self) -> &HashSlabMap<K, V, S> {
+        &self.map
+    }
+
+    /// Consumes the wrapper, returning the map and the observer.
+    pub fn into_parts(self) -> (HashSlabMap<K, V, S>, O) {
+        (self.map, self.observer)
+    }
+}
+
+impl<K, V, O, S> ObservedHashSlabMap<K, V, O, S>
+where
+    O: Observer<K, V>,
+{
+    /// Wraps `map`, reporting its existing entries to `observer` as inserted.
+    pub fn from_map(map: HashSlabMap<K, V, S>, mut observer: O) -> Self {
+        for (index, key, value) in map.iter_full() {
+            observer.on_insert(index, key, value);
+        }
+        Self { map, observer }
+    }
+
+    /// Clears the map, returning all key-value pairs as an iterator.
+    ///
+    /// Every pair is reported as removed when the iterator yields it. Pairs
+    /// which are not consumed are reported when the iterator is dropped.
+    pub fn drain(&mut self) -> Drain<'_, K, V, O> {
+        Drain {
+            drain: self.map.drain_full(),
+            observer: &mut self.observer,
+        }
+    }
+
+    /// Removes all entries, reporting each of them as removed.
+    pub fn clear(&mut self) {
+        self.drain();
+    }
+}
+
+impl<K, V, O, S> ObservedHashSlabMap<K, V, O, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    O: Observer<K, V>,
+{
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the key is already present, its value is replaced and the old value
+    /// is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Inserts a key-value pair into the map, and returns its index together
+    /// with the old value if the key was present.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => (entry.index(), Some(entry.insert(value))),
+            Entry::Vacant(entry) => {
+                let index = entry.index();
+                entry.insert(value);
+                (index, None)
+            }
+        }
+    }
+
+    /// Get the given key's corresponding entry in the map for insertion
+    /// and/or in-place manipulation.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, O> {
+        let observer = &mut self.observer;
+        match self.map.entry(key) {
+            map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry { entry, observer }),
+            map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry { entry, observer }),
+        }
+    }
+
+    /// Call `f` with a mutable reference to the value of `key`, and report
+    /// the update.
+    pub fn update<Q, F, R>(&mut self, key: &Q, f: F) -> Option<R>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        let (index, key, value) = self.map.get_full_mut(key)?;
+        let result = f(value);
+        self.observer.on_update(index, key, value);
+        Some(result)
+    }
+
+    /// Call `f` with a mutable reference to the value at the given index, and
+    /// report the update.
+    pub fn update_index<F, R>(&mut self, index: usize, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        let (key, value) = self.map.get_index_mut(index)?;
+        let result = f(value);
+        self.observer.on_update(index, key, value);
+        Some(result)
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was present.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key).map(|(_, key, value)| (key, value))
+    }
+
+    /// Removes a key from the map, returning its index, key and value if it
+    /// was present.
+    pub fn remove_full<Q>(&mut self, key: &Q) -> Option<(usize, K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let (index, key, value) = self.map.remove_full(key)?;
+        let (key, value) = self.observer.on_remove(index, key, value);
+        Some((index, key, value))
+    }
+
+    /// Removes the entry at the given index, returning its key and value.
+    pub fn remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let (key, value) = self.map.remove_index(index)?;
+        Some(self.observer.on_remove(index, key, value))
+    }
+
+    /// Retains only the entries specified by the predicate, reporting the
+    /// others as removed.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let removed: Vec<_> = self
+            .map
+            .iter_full()
+            .filter(|&(_, key, value)| !f(key, value))
+            .map(|(index, _, _)| index)
+            .collect();
+        for index in removed {
+            self.remove_index(index);
+        }
+    }
+
+    /// Moves all key-value pairs from `other` into `self`, leaving `other`
+    /// empty.
+    ///
+    /// Every pair is reported as inserted or updated.
+    pub fn append<S2>(&mut self, other: &mut HashSlabMap<K, V, S2>) {
+        self.extend(other.drain());
+    }
+}
+
+impl<K, V, O, S> Deref for ObservedHashSlabMap<K, V, O, S> {
+    type Target = HashSlabMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, O, S> Clone for ObservedHashSlabMap<K, V, O, S>
+where
+    K: Clone,
+    V: Clone,
+    O: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            observer: self.observer.clone(),
+        }
+    }
+}
+
+impl<K, V, O, S> fmt::Debug for ObservedHashSlabMap<K, V, O, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    O: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObservedHashSlabMap")
+            .field("map", &self.map.iter())
+            .field("observer", &self.observer)
+            .finish()
+    }
+}
+
+impl<K, V, O, S> Default for ObservedHashSlabMap<K, V, O, S>
+where
+    O: Default,
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default(), O::default())
+    }
+}
+
+impl<K, V, O, S> Extend<(K, V)> for ObservedHashSlabMap<K, V, O, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    O: Observer<K, V>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
+
+impl<K, V, O, S> FromIterator<(K, V)> for ObservedHashSlabMap<K, V, O, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+    O: Observer<K, V> + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iterable: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iterable);
+        map
+    }
+}
+
+/// A view into a single entry of an [`ObservedHashSlabMap`], which may
+/// either be vacant or occupied.
+///
+/// This `enum` is constructed from the [`ObservedHashSlabMap::entry`] method.
+pub enum Entry<'a, K, V, O> {
+    /// Existing slot with equivalent key.
+    Occupied(OccupiedEntry<'a, K, V, O>),
+    /// Vacant slot (no equivalent key in the map).
+    Vacant(VacantEntry<'a, K, V, O>),
+}
+
+impl<'a, K, V, O> Entry<'a, K, V, O>
+where
+    K: Hash,
+    O: Observer<K, V>,
+{
+    /// Return the index where the key-value pair exists or will be inserted.
+    pub fn index(&self) -> usize {
+        match self {
+            Entry::Occupied(entry) => entry.index(),
+            Entry::Vacant(entry) => entry.index(),
+        }
+    }
+
+    /// Gets a reference to the entry's key, either within the map if occupied,
+    /// or else the new key that was used to find the entry.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Modifies the entry if it is occupied, and reports the update.
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            entry.update(f);
+        }
+        self
+    }
+
+    /// Inserts the given default value in the entry if it is vacant, and
+    /// returns a reference to the value.
+    pub fn or_insert(self, default: V) -> &'a V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `call` in the entry if it is vacant, and returns
+    /// a reference to the value.
+    pub fn or_insert_with<F>(self, call: F) -> &'a V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(call()),
+        }
+    }
+
+    /// Inserts the default value in the entry if it is vacant, and returns a
+    /// reference to the value.
+    pub fn or_default(self) -> &'a V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, O> fmt::Debug for Entry<'_, K, V, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Occupied(entry) => f.debug_tuple("Entry").field(entry).finish(),
+            Entry::Vacant(entry) => f.debug_tuple("Entry").field(entry).finish(),
+        }
+    }
+}
+
+/// A view into an occupied entry in an [`ObservedHashSlabMap`].
+/// It is part of the [`Entry`] enum.
+pub struct OccupiedEntry<'a, K, V, O> {
+    entry: map::OccupiedEntry<'a, K, V>,
+    observer: &'a mut O,
+}
+
+impl<'a, K, V, O> OccupiedEntry<'a, K, V, O>
+where
+    O: Observer<K, V>,
+{
+    /// Return the index of the key-value pair.
+    pub fn index(&self) -> usize {
+        self.entry.index()
+    }
+
+    /// Gets a reference to the entry's key in the map.
+    pub fn key(&self) -> &K {
+        self.entry.key()
+    }
+
+    /// Gets a reference to the entry's value in the map.
+    pub fn get(&self) -> &V {
+        self.entry.get()
+    }
+
+    /// Converts into a reference to the entry's value in the map, with a
+    /// lifetime bound to the map itself.
+    pub fn into_ref(self) -> &'a V {
+        self.entry.into_mut()
+    }
+
+    /// Call `f` with a mutable reference to the value, and report the update.
+    pub fn update<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        let result = f(self.entry.get_mut());
+        self.notify_update();
+        result
+    }
+
+    /// Sets the value of the entry to `value`, and returns the entry's old
+    /// value.
+    pub fn insert(&mut self, value: V) -> V {
+        self.update(|old| mem::replace(old, value))
+    }
+
+    /// Remove the key-value pair stored in the map for this entry, and
+    /// return the value.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Remove and return the key-value pair stored in the map for this entry.
+    pub fn remove_entry(self) -> (K, V) {
+        let index = self.entry.index();
+        let (key, value) = self.entry.remove_entry();
+        self.observer.on_remove(index, key, value)
+    }
+
+    fn notify_update(&mut self) {
+        let index = self.entry.index();
+        self.observer
+            .on_update(index, self.entry.key(), self.entry.get());
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, O> fmt::Debug for OccupiedEntry<'_, K, V, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("index", &self.entry.index())
+            .field("key", self.entry.key())
+            .field("value", self.entry.get())
+            .finish()
+    }
+}
+
+/// A view into a vacant entry in an [`ObservedHashSlabMap`].
+/// It is part of the [`Entry`] enum.
+pub struct VacantEntry<'a, K, V, O> {
+    entry: map::VacantEntry<'a, K, V>,
+    observer: &'a mut O,
+}
+
+impl<'a, K, V, O> VacantEntry<'a, K, V, O>
+where
+    O: Observer<K, V>,
+{
+    /// Return the index where a key-value pair may be inserted.
+    pub fn index(&self) -> usize {
+        self.entry.index()
+    }
+
+    /// Gets a reference to the key that was used to find the entry.
+    pub fn key(&self) -> &K {
+        self.entry.key()
+    }
+
+    /// Takes ownership of the key, leaving the entry vacant.
+    pub fn into_key(self) -> K {
+        self.entry.into_key()
+    }
+
+    /// Inserts the entry's key and the given value into the map, reports the
+    /// insertion and returns a reference to the value.
+    pub fn insert(self, value: V) -> &'a V
+    where
+        K: Hash,
+    {
+        let entry = self.entry.insert_entry(value);
+        self.observer
+            .on_insert(entry.index(), entry.key(), entry.get());
+        entry.into_mut()
+    }
+}
+
+impl<K: fmt::Debug, V, O> fmt::Debug for VacantEntry<'_, K, V, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VacantEntry")
+            .field("index", &self.entry.index())
+            .field("key", self.entry.key())
+            .finish()
+    }
+}
+
+/// A draining iterator over the entries of an [`ObservedHashSlabMap`].
+///
+/// This `struct` is created by the [`ObservedHashSlabMap::drain`] method.
+/// See its documentation for more.
+pub struct Drain<'a, K, V, O>
+where
+    O: Observer<K, V>,
+{
+    drain: map::DrainFull<'a, K, V>,
+    observer: &'a mut O,
+}
+
+impl<K, V, O> Drop for Drain<'_, K, V, O>
+where
+    O: Observer<K, V>,
+{
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+impl<K, V, O> fmt::Debug for Drain<'_, K, V, O>
+where
+    O: Observer<K, V>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Drain")
+            .field("remaining", &self.drain.len())
+            .finish()
+    }
+}
+
+impl<K, V, O> Iterator for Drain<'_, K, V, O>
+where
+    O: Observer<K, V>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.drain
+            .next()
+            .map(|(index, key, value)| self.observer.on_remove(index, key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.drain.len();
+        (len, Some(len))
+    }
+}
+
+impl<K, V, O> ExactSizeIterator for Drain<'_, K, V, O> where O: Observer<K, V> {}
+
+impl<K, V, O> FusedIterator for Drain<'_, K, V, O> where O: Observer<K, V> {}
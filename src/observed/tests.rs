@@ -0,0 +1,164 @@
+use super::*;
+use std::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Insert(usize, char, u32),
+    Update(usize, char, u32),
+    Remove(usize, char, u32),
+}
+
+#[derive(Debug, Default)]
+struct Log(Vec<Event>);
+
+impl Observer<char, u32> for Log {
+    fn on_insert(&mut self, index: usize, key: &char, value: &u32) {
+        self.0.push(Event::Insert(index, *key, *value));
+    }
+
+    fn on_update(&mut self, index: usize, key: &char, value: &u32) {
+        self.0.push(Event::Update(index, *key, *value));
+    }
+
+    fn on_remove(&mut self, index: usize, key: char, value: u32) -> (char, u32) {
+        self.0.push(Event::Remove(index, key, value));
+        (key, value)
+    }
+}
+
+fn take(map: &mut ObservedHashSlabMap<char, u32, Log>) -> Vec<Event> {
+    core::mem::take(&mut map.observer_mut().0)
+}
+
+#[test]
+fn insert_update_remove() {
+    use Event::*;
+
+    let mut map = ObservedHashSlabMap::new(Log::default());
+    assert_eq!(map.insert_full('a', 1), (0, None));
+    assert_eq!(map.insert('b', 2), None);
+    assert_eq!(map.insert('a', 3), Some(1));
+    assert_eq!(map.update(&'b', |v| *v += 1), Some(()));
+    assert_eq!(map.update_index(7, |v| *v += 1), None);
+    assert_eq!(map.remove(&'a'), Some(3));
+    assert_eq!(map.remove_index(0), None);
+    assert_eq!(map.remove_full(&'b'), Some((1, 'b', 3)));
+    assert_eq!(
+        take(&mut map),
+        vec![
+            Insert(0, 'a', 1),
+            Insert(1, 'b', 2),
+            Update(0, 'a', 3),
+            Update(1, 'b', 3),
+            Remove(0, 'a', 3),
+            Remove(1, 'b', 3),
+        ]
+    );
+}
+
+#[test]
+fn entry() {
+    use Event::*;
+
+    let mut map = ObservedHashSlabMap::new(Log::default());
+    assert_eq!(map.entry('a').or_insert(1), &1);
+    assert_eq!(map.entry('a').or_insert(2), &1);
+    map.entry('a').and_modify(|v| *v *= 10).or_default();
+    map.entry('b').and_modify(|v| *v *= 10).or_default();
+    match map.entry('a') {
+        Entry::Occupied(mut entry) => assert_eq!(entry.insert(5), 10),
+        Entry::Vacant(_) => unreachable!(),
+    }
+    match map.entry('b') {
+        Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), ('b', 0)),
+        Entry::Vacant(_) => unreachable!(),
+    }
+    match map.entry('c') {
+        Entry::Occupied(_) => unreachable!(),
+        Entry::Vacant(entry) => assert_eq!(entry.into_key(), 'c'),
+    }
+    assert_eq!(
+        take(&mut map),
+        vec![
+            Insert(0, 'a', 1),
+            Update(0, 'a', 10),
+            Insert(1, 'b', 0),
+            Update(0, 'a', 5),
+            Remove(1, 'b', 0),
+        ]
+    );
+}
+
+#[test]
+fn bulk_operations() {
+    use Event::*;
+
+    let mut map: ObservedHashSlabMap<char, u32, Log> = ('a'..='e').zip(0..).collect();
+    assert_eq!(take(&mut map).len(), 5);
+
+    map.retain(|_, &v| v % 2 == 0);
+    let mut removed = take(&mut map);
+    removed.sort_by_key(|event| match event {
+        Remove(index, _, _) => *index,
+        _ => usize::MAX,
+    });
+    assert_eq!(removed, vec![Remove(1, 'b', 1), Remove(3, 'd', 3)]);
+
+    let mut other = HashSlabMap::new();
+    other.insert('a', 10);
+    other.insert('x', 11);
+    map.append(&mut other);
+    assert!(other.is_empty());
+    let appended = take(&mut map);
+    assert_eq!(appended.len(), 2);
+    assert!(appended.contains(&Update(0, 'a', 10)));
+
+    // Entries left in a dropped drain are still reported
+    let mut drain = map.drain();
+    assert!(drain.next().is_some());
+    drop(drain);
+    assert!(map.is_empty());
+    assert_eq!(take(&mut map).len(), 4);
+
+    map.extend([('y', 1), ('z', 2)]);
+    map.clear();
+    let events = take(&mut map);
+    assert_eq!(events.len(), 4);
+    assert!(events
+        .iter()
+        .skip(2)
+        .all(|event| matches!(event, Remove(..))));
+
+    let mut plain = HashSlabMap::new();
+    plain.insert('q', 1);
+    let (plain, log) = ObservedHashSlabMap::from_map(plain, Log::default()).into_parts();
+    assert_eq!(log.0, vec![Insert(0, 'q', 1)]);
+    assert_eq!(plain.len(), 1);
+}
+
+#[test]
+fn remove_passes_entry_by_value() {
+    // Keeps removed values, handing back empty ones
+    #[derive(Default)]
+    struct Graveyard(Vec<String>);
+
+    impl Observer<u32, String> for Graveyard {
+        fn on_remove(&mut self, _: usize, key: u32, mut value: String) -> (u32, String) {
+            self.0.push(core::mem::take(&mut value));
+            (key, value)
+        }
+    }
+
+    let mut map = ObservedHashSlabMap::new(Graveyard::default());
+    map.insert(1, "one".to_string());
+    map.insert(2, "two".to_string());
+    map.insert(3, "three".to_string());
+    assert_eq!(map.remove(&1), Some(String::new()));
+    map.retain(|&key, _| key != 3);
+    map.clear();
+    assert_eq!(map.observer().0, ["one", "three", "two"]);
+}
@@ -0,0 +1,370 @@
+//! A [`HashSlabMap`] which logs its mutations, to replicate them
+//!
+//! A primary records the mutations made through [`LoggedHashSlabMap`], ships
+//! the log to replicas, and replicas apply it with
+//! [`HashSlabMap::replay`]. Every operation records the index it produced or
+//! removed with its key, so replicas can verify that they assign the same
+//! indices to the same keys.
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    mem,
+    ops::Deref,
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+use thiserror::Error;
+
+use crate::HashSlabMap;
+
+#[cfg(test)]
+mod tests;
+
+/// A mutation of a [`HashSlabMap`], with the index it produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Op<K, V> {
+    /// A key-value pair was inserted, or the value of an existing key was
+    /// replaced, at `index`.
+    Insert {
+        /// The index of the key.
+        index: usize,
+        /// The inserted key.
+        key: K,
+        /// The inserted value.
+        value: V,
+    },
+    /// The entry at `index` was removed.
+    Remove {
+        /// The index of the removed entry.
+        index: usize,
+        /// The removed key.
+        key: K,
+    },
+    /// All entries were removed.
+    Clear,
+}
+
+/// The error returned by [`HashSlabMap::replay`] when the map diverges from
+/// the log.
+///
+/// The operations before `position` were applied. An insertion which produced
+/// an unexpected index was applied as well.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// An insertion produced another index than the logged one.
+    #[error("operation {position} produced index {found} instead of {expected}")]
+    IndexMismatch {
+        /// The position of the operation in the replayed log.
+        position: usize,
+        /// The logged index.
+        expected: usize,
+        /// The index produced by the map.
+        found: usize,
+    },
+    /// A removal targeted a vacant index.
+    #[error("operation {position} removes vacant index {index}")]
+    Vacant {
+        /// The position of the operation in the replayed log.
+        position: usize,
+        /// The logged index.
+        index: usize,
+    },
+    /// A removal targeted an index holding another key than the logged one.
+    #[error("operation {position} removes another key at index {index}")]
+    KeyMismatch {
+        /// The position of the operation in the replayed log.
+        position: usize,
+        /// The logged index.
+        index: usize,
+    },
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Apply a log recorded by a [`LoggedHashSlabMap`], checking that every
+    /// operation yields the logged index.
+    ///
+    /// The map must be in the state the primary was in when the log started,
+    /// e.g. empty for a complete log.
+    ///
+    /// # Errors
+    ///
+    /// Stops at the first operation which diverges from the log, see
+    /// [`ReplayError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::{HashSlabMap, LoggedHashSlabMap};
+    /// let mut primary = LoggedHashSlabMap::new();
+    /// primary.insert("a", 1);
+    /// primary.insert("b", 2);
+    /// primary.remove("a");
+    /// primary.insert("c", 3);
+    ///
+    /// let mut replica = HashSlabMap::new();
+    /// replica.replay(primary.take_log()).unwrap();
+    /// assert_eq!(replica.get_index(0), Some((&"c", &3)));
+    /// assert_eq!(replica.get_index_of("b"), Some(1));
+    /// ```
+    pub fn replay<I>(&mut self, log: I) -> Result<(), ReplayError>
+    where
+        I: IntoIterator<Item = Op<K, V>>,
+    {
+        for (position, op) in log.into_iter().enumerate() {
+            match op {
+                Op::Insert { index, key, value } => {
+                    let (found, _) = self.insert_full(key, value);
+                    if found != index {
+                        return Err(ReplayError::IndexMismatch {
+                            position,
+                            expected: index,
+                            found,
+                        });
+                    }
+                }
+                Op::Remove { index, key } => match self.get_index(index) {
+                    None => return Err(ReplayError::Vacant { position, index }),
+                    Some((found, _)) if *found != key => {
+                        return Err(ReplayError::KeyMismatch { position, index });
+                    }
+                    Some(_) => {
+                        self.remove_index(index);
+                    }
+                },
+                Op::Clear => self.clear(),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A [`HashSlabMap`] which records every mutation in a log of [`Op`]s.
+///
+/// The log grows until it is taken with [`take_log`][Self::take_log]. The map
+/// can be read through [`Deref`]. Values are logged when they are inserted,
+/// so there is no mutable access to them.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::LoggedHashSlabMap;
+/// # use hashslab::oplog::Op;
+/// let mut map = LoggedHashSlabMap::new();
+/// map.insert('a', 1);
+/// map.remove_index(0);
+/// assert_eq!(
+///     map.take_log(),
+///     [
+///         Op::Insert { index: 0, key: 'a', value: 1 },
+///         Op::Remove { index: 0, key: 'a' },
+///     ]
+/// );
+/// assert!(map.log().is_empty());
+/// ```
+#[cfg(feature = "std")]
+pub struct LoggedHashSlabMap<K, V, S = RandomState> {
+    map: HashSlabMap<K, V, S>,
+    log: Vec<Op<K, V>>,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct LoggedHashSlabMap<K, V, S> {
+    map: HashSlabMap<K, V, S>,
+    log: Vec<Op<K, V>>,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V> LoggedHashSlabMap<K, V> {
+    /// Creates an empty `LoggedHashSlabMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<K, V, S> LoggedHashSlabMap<K, V, S> {
+    /// Creates an empty `LoggedHashSlabMap` which will use the given hash
+    /// builder.
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self::from_map(HashSlabMap::with_hasher(hash_builder))
+    }
+
+    /// Creates a `LoggedHashSlabMap` with the content of `map` and an empty
+    /// log. Replicas must start from a copy of `map` with the same indices.
+    pub const fn from_map(map: HashSlabMap<K, V, S>) -> Self {
+        Self {
+            map,
+            log: Vec::new(),
+        }
+    }
+
+    /// Return the operations logged since the log was last taken.
+    pub fn log(&self) -> &[Op<K, V>] {
+        &self.log
+    }
+
+    /// Take the logged operations, starting a new log.
+    pub fn take_log(&mut self) -> Vec<Op<K, V>> {
+        mem::take(&mut self.log)
+    }
+
+    /// Return the underlying map.
+    pub fn as_map(&self) -> &HashSlabMap<K, V, S> {
+        &self.map
+    }
+
+    /// Consumes the logged map, returning the map and the operations which
+    /// were not taken.
+    pub fn into_parts(self) -> (HashSlabMap<K, V, S>, Vec<Op<K, V>>) {
+        (self.map, self.log)
+    }
+
+    /// Remove all key-value pairs.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.log.push(Op::Clear);
+    }
+}
+
+impl<K, V, S> LoggedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    /// Insert a key-value pair in the map.
+    ///
+    /// If an equivalent key already exists, its value is replaced and the old
+    /// value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Insert a key-value pair in the map, and get its index together with
+    /// the old value if an equivalent key already existed.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        let op_key = key.clone();
+        let op_value = value.clone();
+        let (index, old) = self.map.insert_full(key, value);
+        self.log.push(Op::Insert {
+            index,
+            key: op_key,
+            value: op_value,
+        });
+        (index, old)
+    }
+}
+
+impl<K, V, S> LoggedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    S: BuildHasher,
+{
+    /// Remove the key-value pair equivalent to `key` and return its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return it and the
+    /// index it had.
+    pub fn remove_full<Q>(&mut self, key: &Q) -> Option<(usize, K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let (index, key, value) = self.map.remove_full(key)?;
+        self.log.push(Op::Remove {
+            index,
+            key: key.clone(),
+        });
+        Some((index, key, value))
+    }
+
+    /// Remove the key-value pair by index.
+    pub fn remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let (key, value) = self.map.remove_index(index)?;
+        self.log.push(Op::Remove {
+            index,
+            key: key.clone(),
+        });
+        Some((key, value))
+    }
+
+    /// Retains only the key-value pairs specified by the predicate.
+    ///
+    /// The removals are logged one by one, in the order they were made.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let removed: Vec<_> = self
+            .map
+            .iter_full()
+            .filter(|&(_, key, value)| !f(key, value))
+            .map(|(index, _, _)| index)
+            .collect();
+        for index in removed {
+            self.remove_index(index);
+        }
+    }
+}
+
+impl<K, V, S> Deref for LoggedHashSlabMap<K, V, S> {
+    type Target = HashSlabMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, S> fmt::Debug for LoggedHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoggedHashSlabMap")
+            .field("map", &self.map.iter_full())
+            .field("log", &self.log)
+            .finish()
+    }
+}
+
+impl<K, V, S> Default for LoggedHashSlabMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> From<HashSlabMap<K, V, S>> for LoggedHashSlabMap<K, V, S> {
+    fn from(map: HashSlabMap<K, V, S>) -> Self {
+        Self::from_map(map)
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for LoggedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
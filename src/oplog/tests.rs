@@ -0,0 +1,79 @@
+use super::*;
+use std::{
+    string::{String, ToString},
+    vec,
+};
+
+#[test]
+fn replica_follows_primary() {
+    let mut primary: LoggedHashSlabMap<String, u32> = LoggedHashSlabMap::new();
+    let mut replica = HashSlabMap::new();
+
+    primary.extend((0..20u32).map(|i| (i.to_string(), i)));
+    primary.retain(|_, v| v % 3 != 0);
+    primary.insert("x".into(), 100);
+    primary.insert("1".into(), 10);
+    replica.replay(primary.take_log()).unwrap();
+
+    // Indices freed by `retain` are reused in the same order
+    primary.insert("y".into(), 200);
+    primary.insert("z".into(), 300);
+    primary.remove("5");
+    replica.replay(primary.take_log()).unwrap();
+
+    let mut expected: std::vec::Vec<_> = primary.iter_full().collect();
+    let mut found: std::vec::Vec<_> = replica.iter_full().collect();
+    expected.sort_unstable();
+    found.sort_unstable();
+    assert_eq!(found, expected);
+
+    primary.clear();
+    primary.insert("a".into(), 0);
+    replica.replay(primary.take_log()).unwrap();
+    assert_eq!(replica.get_index(0), Some((&"a".into(), &0)));
+    assert_eq!(replica.len(), 1);
+}
+
+#[test]
+fn divergence() {
+    let mut primary = LoggedHashSlabMap::new();
+    primary.insert('a', 1);
+    primary.insert('b', 2);
+    primary.remove_index(0);
+    let log = primary.take_log();
+
+    let mut replica = HashSlabMap::new();
+    replica.insert('z', 0);
+    assert_eq!(
+        replica.replay(log.clone()),
+        Err(ReplayError::IndexMismatch {
+            position: 0,
+            expected: 0,
+            found: 1
+        })
+    );
+
+    let mut replica = HashSlabMap::new();
+    assert_eq!(
+        replica.replay(vec![Op::Remove { index: 3, key: 'a' }]),
+        Err(ReplayError::Vacant {
+            position: 0,
+            index: 3
+        })
+    );
+    replica.replay(log).unwrap();
+    assert_eq!(replica.get_index(1), Some((&'b', &2)));
+
+    // A replica holding another key at the index keeps it
+    let mut replica = HashSlabMap::new();
+    replica.insert('x', 1);
+    replica.insert('b', 2);
+    assert_eq!(
+        replica.replay(vec![Op::Remove { index: 0, key: 'a' }]),
+        Err(ReplayError::KeyMismatch {
+            position: 0,
+            index: 0
+        })
+    );
+    assert_eq!(replica.get_index(0), Some((&'x', &1)));
+}
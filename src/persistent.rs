@@ -0,0 +1,559 @@
+//! A [`HashSlabMap`] persisted to a directory with a write-ahead log
+//!
+//! [`PersistentHashSlabMap`] keeps two files in its directory:
+//!
+//! - `snapshot`: the entries of the map with their indices, written by
+//!   [`compact`][PersistentHashSlabMap::compact].
+//! - `wal`: every mutation made since the snapshot, appended before the map
+//!   is changed.
+//!
+//! Both files start with a 4-byte magic and a generation number, which is
+//! incremented by every compaction, so a log left over from an interrupted
+//! compaction is never replayed onto a newer snapshot. Log records are
+//! prefixed with their length and a CRC-32 checksum; an incomplete or
+//! mismatching last record, as left by a crash during a write, is discarded
+//! on open, while a damaged record followed by others is reported as
+//! corruption. Keys and values are encoded with the [`codec`](crate::codec)
+//! traits.
+use std::{
+    fmt, format,
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, Hash, RandomState},
+    io::{self, Read, Write},
+    ops::Deref,
+    path::{Path, PathBuf},
+    vec,
+    vec::Vec,
+};
+
+use hashbrown::Equivalent;
+use thiserror::Error;
+
+use crate::{
+    codec::{self, crc32, Decode, DecodeError, Encode},
+    oplog::Op,
+    HashSlabMap,
+};
+
+#[cfg(test)]
+mod tests;
+
+const SNAPSHOT: &str = "snapshot";
+const WAL: &str = "wal";
+const SNAPSHOT_MAGIC: &[u8; 4] = b"HSSN";
+const WAL_MAGIC: &[u8; 4] = b"HSWL";
+// Magic and generation
+const HEADER_LEN: usize = 12;
+// Payload length and checksum
+const RECORD_HEADER_LEN: usize = 8;
+// Bytes checksummed per byte of a damaged tail while looking for records
+const SCAN_FACTOR: usize = 16;
+
+const INSERT: u8 = 0;
+const REMOVE: u8 = 1;
+const CLEAR: u8 = 2;
+
+/// The error type for [`PersistentHashSlabMap`] operations.
+#[derive(Debug, Error)]
+pub enum PersistError {
+    /// Reading or writing a file failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A file holds data which can't be read back.
+    #[error("corrupted {file} file at byte {offset}")]
+    Corrupted {
+        /// The name of the file in the directory.
+        file: &'static str,
+        /// The offset of the damaged data in the file.
+        offset: usize,
+    },
+    /// A write to the log failed and the partial record could not be cut off,
+    /// or a compaction wrote the snapshot but could not start a new log. No
+    /// records are appended until [`compact`][PersistentHashSlabMap::compact]
+    /// starts a new log.
+    #[error("the log could not be repaired after a failed write")]
+    Poisoned,
+}
+
+/// A [`HashSlabMap`] which survives restarts.
+///
+/// Every mutation is appended to a write-ahead log before it is applied, and
+/// the log is compacted into a snapshot once it holds more than
+/// [`compaction_threshold`][Self::set_compaction_threshold] records.
+/// [`open`][Self::open] restores the map with the original indices, and
+/// with the same vacant indices to be reused by later insertions. See the
+/// [module documentation](self) for the files.
+///
+/// Records are written to the operating system immediately; call
+/// [`sync`][Self::sync] to wait until they reach the disk. The map can be
+/// read through [`Deref`].
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::PersistentHashSlabMap;
+/// # let dir = std::env::temp_dir().join(format!("hashslab-doc-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// let mut map = PersistentHashSlabMap::<String, u32>::open(&dir)?;
+/// map.insert("a".to_string(), 1)?;
+/// map.insert("b".to_string(), 2)?;
+/// map.remove("a")?;
+/// drop(map);
+///
+/// let mut map = PersistentHashSlabMap::<String, u32>::open(&dir)?;
+/// assert_eq!(map.get_index(1), Some((&"b".to_string(), &2)));
+/// assert_eq!(map.insert_full("c".to_string(), 3)?, (0, None));
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct PersistentHashSlabMap<K, V, S = RandomState> {
+    map: HashSlabMap<K, V, S>,
+    dir: PathBuf,
+    wal: File,
+    // Length of the log up to the last complete record
+    wal_len: u64,
+    // A failed write left a partial record in the log
+    poisoned: bool,
+    generation: u64,
+    // Records in the log
+    records: usize,
+    threshold: usize,
+}
+
+impl<K, V> PersistentHashSlabMap<K, V>
+where
+    K: Hash + Eq + Encode + Decode,
+    V: Encode + Decode,
+{
+    /// Open the map persisted in `dir`, creating the directory and an empty
+    /// map if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, PersistError> {
+        Self::open_with_hasher(dir, RandomState::new())
+    }
+}
+
+impl<K, V, S> PersistentHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Encode + Decode,
+    V: Encode + Decode,
+    S: BuildHasher,
+{
+    /// Open the map persisted in `dir` with the given hash builder, creating
+    /// the directory and an empty map if needed.
+    pub fn open_with_hasher(dir: impl AsRef<Path>, hash_builder: S) -> Result<Self, PersistError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut map = HashSlabMap::with_hasher(hash_builder);
+        let generation = match read_file(&dir.join(SNAPSHOT))? {
+            Some(bytes) => load_snapshot(&mut map, &bytes)?,
+            None => 0,
+        };
+
+        let wal_path = dir.join(WAL);
+        let mut records = 0;
+        let mut wal_len = HEADER_LEN;
+        match read_file(&wal_path)? {
+            Some(bytes) if read_generation(&bytes, WAL_MAGIC) == Some(generation) => {
+                let (count, valid) = replay_wal(&mut map, &bytes)?;
+                records = count;
+                wal_len = valid;
+                if valid < bytes.len() {
+                    // Drop the torn last record
+                    OpenOptions::new()
+                        .write(true)
+                        .open(&wal_path)?
+                        .set_len(valid as u64)?;
+                }
+            }
+            // A missing log, or one older than the snapshot
+            _ => write_atomically(&dir, WAL, &header(WAL_MAGIC, generation))?,
+        }
+        let wal = OpenOptions::new().append(true).open(&wal_path)?;
+
+        Ok(Self {
+            map,
+            dir,
+            wal,
+            wal_len: wal_len as u64,
+            poisoned: false,
+            generation,
+            records,
+            threshold: 1024,
+        })
+    }
+
+    /// Insert a key-value pair in the map.
+    ///
+    /// If an equivalent key already exists, its value is replaced and the old
+    /// value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, PersistError> {
+        self.insert_full(key, value).map(|(_, old)| old)
+    }
+
+    /// Insert a key-value pair in the map, and get its index together with
+    /// the old value if an equivalent key already existed.
+    pub fn insert_full(&mut self, key: K, value: V) -> Result<(usize, Option<V>), PersistError> {
+        let index = self
+            .map
+            .get_index_of(&key)
+            .unwrap_or_else(|| self.map.vacant_index());
+        let mut payload = vec![INSERT];
+        index.encode(&mut payload);
+        key.encode(&mut payload);
+        value.encode(&mut payload);
+        self.append(&payload)?;
+        let (inserted, old) = self.map.insert_full(key, value);
+        debug_assert_eq!(inserted, index);
+        self.maybe_compact();
+        Ok((inserted, old))
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Result<Option<V>, PersistError>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key)
+            .map(|removed| removed.map(|(_, _, value)| value))
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return it and the
+    /// index it had.
+    pub fn remove_full<Q>(&mut self, key: &Q) -> Result<Option<(usize, K, V)>, PersistError>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let Some(index) = self.map.get_index_of(key) else {
+            return Ok(None);
+        };
+        self.remove_index(index)
+            .map(|removed| removed.map(|(key, value)| (index, key, value)))
+    }
+
+    /// Remove the key-value pair by index.
+    pub fn remove_index(&mut self, index: usize) -> Result<Option<(K, V)>, PersistError> {
+        let Some((key, _)) = self.map.get_index(index) else {
+            return Ok(None);
+        };
+        let mut payload = vec![REMOVE];
+        index.encode(&mut payload);
+        key.encode(&mut payload);
+        self.append(&payload)?;
+        let removed = self.map.remove_index(index);
+        self.maybe_compact();
+        Ok(removed)
+    }
+
+    /// Remove all key-value pairs.
+    pub fn clear(&mut self) -> Result<(), PersistError> {
+        self.append(&[CLEAR])?;
+        self.map.clear();
+        self.maybe_compact();
+        Ok(())
+    }
+
+    /// Write the entries to a new snapshot and start an empty log.
+    ///
+    /// This also recovers from [`PersistError::Poisoned`].
+    pub fn compact(&mut self) -> Result<(), PersistError> {
+        let generation = self.generation + 1;
+        let mut snapshot = header(SNAPSHOT_MAGIC, generation);
+        let mut entries: Vec<_> = self.map.iter_full().collect();
+        entries.sort_unstable_by_key(|&(index, _, _)| index);
+        entries.len().encode(&mut snapshot);
+        for (index, key, value) in entries {
+            (index, key, value).encode(&mut snapshot);
+        }
+        crc32(&snapshot).encode(&mut snapshot);
+        write_atomically(&self.dir, SNAPSHOT, &snapshot)?;
+        // The old log is stale from now on, so nothing may be appended to it
+        self.generation = generation;
+        self.poisoned = true;
+
+        write_atomically(&self.dir, WAL, &header(WAL_MAGIC, generation))?;
+        self.wal = OpenOptions::new().append(true).open(self.dir.join(WAL))?;
+        self.wal_len = HEADER_LEN as u64;
+        self.poisoned = false;
+        self.records = 0;
+
+        // Rebuild the map the way `open` loads it, so the vacant indices are
+        // reused in the same order after a restart. Until both files are
+        // written, the old ones describe the map as it is.
+        let entries: Vec<_> = self.map.drain_full().collect();
+        self.map.clear();
+        self.map.place(entries);
+        Ok(())
+    }
+
+    // The change is already in the log, so a failed compaction is not an
+    // error of the change; it is retried after the next one.
+    fn maybe_compact(&mut self) {
+        if self.records > self.threshold && self.records > self.map.len() {
+            let _ = self.compact();
+        }
+    }
+}
+
+impl<K, V, S> PersistentHashSlabMap<K, V, S> {
+    /// Compact the log once it holds more than `threshold` records, and more
+    /// records than the map has entries. Defaults to 1024.
+    ///
+    /// A failed compaction does not fail the change which started it, and is
+    /// retried after the next change. Call [`compact`][Self::compact] to see
+    /// the error.
+    pub fn set_compaction_threshold(&mut self, threshold: usize) {
+        self.threshold = threshold;
+    }
+
+    /// Return the number of records in the log.
+    pub fn log_len(&self) -> usize {
+        self.records
+    }
+
+    /// Return the directory of the map.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Wait until all records are written to the disk.
+    pub fn sync(&self) -> Result<(), PersistError> {
+        self.wal.sync_data()?;
+        Ok(())
+    }
+
+    /// Return the underlying map.
+    pub fn as_map(&self) -> &HashSlabMap<K, V, S> {
+        &self.map
+    }
+
+    /// Close the files, returning the map.
+    pub fn into_inner(self) -> HashSlabMap<K, V, S> {
+        self.map
+    }
+
+    fn append(&mut self, payload: &[u8]) -> Result<(), PersistError> {
+        append_record(
+            &mut self.wal,
+            &mut self.wal_len,
+            &mut self.poisoned,
+            payload,
+        )?;
+        self.records += 1;
+        Ok(())
+    }
+}
+
+// The log file, or a stand-in in tests.
+trait Log: Write {
+    fn truncate(&mut self, len: u64) -> io::Result<()>;
+}
+
+impl Log for File {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.set_len(len)
+    }
+}
+
+// Append a record to a log of length `len`. If the write fails, the partial
+// record is cut off again, so the next record is not appended to a torn one;
+// if that fails too, the log is poisoned and refuses further records.
+fn append_record(
+    log: &mut impl Log,
+    len: &mut u64,
+    poisoned: &mut bool,
+    payload: &[u8],
+) -> Result<(), PersistError> {
+    if *poisoned {
+        return Err(PersistError::Poisoned);
+    }
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+    (payload.len() as u32).encode(&mut record);
+    crc32(payload).encode(&mut record);
+    record.extend_from_slice(payload);
+    if let Err(err) = log.write_all(&record) {
+        *poisoned = log.truncate(*len).is_err();
+        return Err(err.into());
+    }
+    *len += record.len() as u64;
+    Ok(())
+}
+
+impl<K, V, S> Deref for PersistentHashSlabMap<K, V, S> {
+    type Target = HashSlabMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, S> fmt::Debug for PersistentHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PersistentHashSlabMap")
+            .field("map", &self.map.iter_full())
+            .field("dir", &self.dir)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+fn header(magic: &[u8; 4], generation: u64) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    generation.encode(&mut bytes);
+    bytes
+}
+
+fn read_generation(bytes: &[u8], magic: &[u8; 4]) -> Option<u64> {
+    let mut input = bytes.strip_prefix(magic)?;
+    u64::decode(&mut input).ok()
+}
+
+fn read_file(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_end(&mut bytes).map(|_| Some(bytes)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// Replace the file `name` in `dir`, so it is either the old or the new one
+// after a crash.
+fn write_atomically(dir: &Path, name: &str, bytes: &[u8]) -> io::Result<()> {
+    let tmp = dir.join(format!("{name}.tmp"));
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, dir.join(name))?;
+    // Persist the rename
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+fn load_snapshot<K, V, S>(map: &mut HashSlabMap<K, V, S>, bytes: &[u8]) -> Result<u64, PersistError>
+where
+    K: Hash + Eq + Decode,
+    V: Decode,
+    S: BuildHasher,
+{
+    let corrupted = |offset| PersistError::Corrupted {
+        file: SNAPSHOT,
+        offset,
+    };
+    let generation = read_generation(bytes, SNAPSHOT_MAGIC).ok_or(corrupted(0))?;
+    let Some(body_len) = bytes.len().checked_sub(4).filter(|&len| len >= HEADER_LEN) else {
+        return Err(corrupted(0));
+    };
+    let (body, mut checksum) = bytes.split_at(body_len);
+    if u32::decode(&mut checksum).ok() != Some(crc32(body)) {
+        return Err(corrupted(body_len));
+    }
+
+    let mut input = &body[HEADER_LEN..];
+    let offset = |input: &[u8]| body_len - input.len();
+    let len = usize::decode(&mut input).map_err(|_| corrupted(offset(input)))?;
+    let mut entries = Vec::with_capacity(len.min(input.len()));
+    for _ in 0..len {
+        let entry = <(usize, K, V)>::decode(&mut input).map_err(|_| corrupted(offset(input)))?;
+        entries.push(entry);
+    }
+    map.place(entries);
+    Ok(generation)
+}
+
+// Replay the log records onto `map`, returning the number of records and the
+// length of the valid part of the log.
+fn replay_wal<K, V, S>(
+    map: &mut HashSlabMap<K, V, S>,
+    bytes: &[u8],
+) -> Result<(usize, usize), PersistError>
+where
+    K: Hash + Eq + Decode,
+    V: Decode,
+    S: BuildHasher,
+{
+    let mut offset = HEADER_LEN;
+    let mut records = 0;
+    while offset < bytes.len() {
+        let corrupted = || PersistError::Corrupted { file: WAL, offset };
+        let mut input = &bytes[offset..];
+        let Ok((len, checksum)) = <(u32, u32)>::decode(&mut input) else {
+            break;
+        };
+        let Ok(payload) = codec::take(&mut input, len as usize) else {
+            // A record running past the end was torn by a crash, unless a
+            // damaged length hides the records after it
+            if has_record(&bytes[offset + RECORD_HEADER_LEN..]) {
+                return Err(corrupted());
+            }
+            break;
+        };
+        if crc32(payload) != checksum {
+            if input.is_empty() {
+                break;
+            }
+            return Err(corrupted());
+        }
+        let op = decode_op(payload).map_err(|_| corrupted())?;
+        map.replay([op]).map_err(|_| corrupted())?;
+        offset += RECORD_HEADER_LEN + payload.len();
+        records += 1;
+    }
+    Ok((records, offset))
+}
+
+// Whether a complete record with a matching checksum starts anywhere in
+// `bytes`. Only payloads which fit are checksummed, up to `SCAN_FACTOR` times
+// the length of `bytes` in total; a tail costing more is taken for one which
+// holds records.
+fn has_record(bytes: &[u8]) -> bool {
+    let mut budget = bytes.len().saturating_mul(SCAN_FACTOR);
+    for start in 0..bytes.len() {
+        let mut input = &bytes[start..];
+        let Ok((len, checksum)) = <(u32, u32)>::decode(&mut input) else {
+            break;
+        };
+        // Payloads start with the operation
+        let Ok(payload) = codec::take(&mut input, len as usize) else {
+            continue;
+        };
+        if payload.is_empty() {
+            continue;
+        }
+        let Some(rest) = budget.checked_sub(payload.len()) else {
+            return true;
+        };
+        budget = rest;
+        if crc32(payload) == checksum {
+            return true;
+        }
+    }
+    false
+}
+
+fn decode_op<K: Decode, V: Decode>(mut payload: &[u8]) -> Result<Op<K, V>, DecodeError> {
+    let input = &mut payload;
+    let op = match u8::decode(input)? {
+        INSERT => {
+            let (index, key, value) = Decode::decode(input)?;
+            Op::Insert { index, key, value }
+        }
+        REMOVE => {
+            let (index, key) = Decode::decode(input)?;
+            Op::Remove { index, key }
+        }
+        CLEAR => Op::Clear,
+        _ => return Err(DecodeError::Invalid("operation")),
+    };
+    if input.is_empty() {
+        Ok(op)
+    } else {
+        Err(DecodeError::Invalid("operation"))
+    }
+}
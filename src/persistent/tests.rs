@@ -0,0 +1,293 @@
+use super::*;
+use std::{
+    env,
+    string::{String, ToString},
+};
+
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("hashslab-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+type Map = PersistentHashSlabMap<String, u32>;
+
+fn sorted(map: &HashSlabMap<String, u32>) -> Vec<(usize, String, u32)> {
+    let mut entries: Vec<_> = map
+        .iter_full()
+        .map(|(i, k, v)| (i, k.clone(), *v))
+        .collect();
+    entries.sort_unstable();
+    entries
+}
+
+#[test]
+fn restores_indices() {
+    let dir = TempDir::new("restore");
+    let mut map = Map::open(&dir.0).unwrap();
+    map.set_compaction_threshold(8);
+    for i in 0..30 {
+        map.insert(i.to_string(), i).unwrap();
+    }
+    for i in (0..30).step_by(4) {
+        map.remove(&i.to_string()).unwrap();
+    }
+    map.insert("1".to_string(), 100).unwrap();
+    assert!(map.log_len() <= 30);
+    map.sync().unwrap();
+    let mut original = map.as_map().clone();
+    drop(map);
+
+    // The reopened map hands out the same indices as the original one
+    let mut map = Map::open(&dir.0).unwrap();
+    assert_eq!(sorted(&map), sorted(&original));
+    for i in 0..10 {
+        let key = format!("new{i}");
+        let expected = original.insert_full(key.clone(), i).0;
+        assert_eq!(map.insert_full(key, i).unwrap().0, expected);
+    }
+
+    map.clear().unwrap();
+    map.insert("x".to_string(), 0).unwrap();
+    drop(map);
+    let map = Map::open(&dir.0).unwrap();
+    assert_eq!(sorted(&map), [(0, "x".to_string(), 0)]);
+}
+
+#[test]
+fn torn_and_stale_logs() {
+    let dir = TempDir::new("torn");
+    let mut map = Map::open(&dir.0).unwrap();
+    map.insert("a".to_string(), 1).unwrap();
+    map.insert("b".to_string(), 2).unwrap();
+    drop(map);
+
+    // A record cut short by a crash is dropped
+    let wal = dir.0.join(WAL);
+    let len = fs::metadata(&wal).unwrap().len();
+    OpenOptions::new()
+        .write(true)
+        .open(&wal)
+        .unwrap()
+        .set_len(len - 3)
+        .unwrap();
+    let mut map = Map::open(&dir.0).unwrap();
+    assert_eq!(sorted(&map), [(0, "a".to_string(), 1)]);
+    map.insert("c".to_string(), 3).unwrap();
+    drop(map);
+    let map = Map::open(&dir.0).unwrap();
+    assert_eq!(map.get_index_of("c"), Some(1));
+    assert_eq!(map.log_len(), 2);
+
+    // A log older than the snapshot is ignored
+    let old_wal = fs::read(&wal).unwrap();
+    let mut map = map;
+    map.compact().unwrap();
+    drop(map);
+    fs::write(&wal, old_wal).unwrap();
+    let map = Map::open(&dir.0).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.log_len(), 0);
+    drop(map);
+
+    // A damaged record in the middle of the log is an error
+    let mut map = Map::open(&dir.0).unwrap();
+    map.insert("d".to_string(), 4).unwrap();
+    map.insert("e".to_string(), 5).unwrap();
+    drop(map);
+    let mut bytes = fs::read(&wal).unwrap();
+    bytes[HEADER_LEN + RECORD_HEADER_LEN + 2] ^= 0xff;
+    fs::write(&wal, bytes).unwrap();
+    assert!(matches!(
+        Map::open(&dir.0),
+        Err(PersistError::Corrupted {
+            file: "wal",
+            offset: HEADER_LEN
+        })
+    ));
+}
+
+#[test]
+fn damaged_length() {
+    let dir = TempDir::new("length");
+    let mut map = Map::open(&dir.0).unwrap();
+    for i in 0..3 {
+        map.insert(i.to_string(), i).unwrap();
+    }
+    drop(map);
+
+    // A length running past the end of the log does not drop the records
+    // after it
+    let wal = dir.0.join(WAL);
+    let mut bytes = fs::read(&wal).unwrap();
+    bytes[HEADER_LEN + 3] = 0x7f;
+    fs::write(&wal, &bytes).unwrap();
+    assert!(matches!(
+        Map::open(&dir.0),
+        Err(PersistError::Corrupted {
+            file: "wal",
+            offset: HEADER_LEN
+        })
+    ));
+    assert_eq!(fs::read(&wal).unwrap(), bytes);
+}
+
+// A log which fails writes past `limit` bytes, after writing up to it.
+struct FailingLog {
+    bytes: Vec<u8>,
+    limit: usize,
+    truncate: bool,
+}
+
+impl Write for FailingLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let room = self.limit.saturating_sub(self.bytes.len());
+        if room == 0 {
+            return Err(io::ErrorKind::StorageFull.into());
+        }
+        let len = buf.len().min(room);
+        self.bytes.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Log for FailingLog {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        if !self.truncate {
+            return Err(io::ErrorKind::Other.into());
+        }
+        self.bytes.truncate(len as usize);
+        Ok(())
+    }
+}
+
+#[test]
+fn failed_append() {
+    let mut log = FailingLog {
+        bytes: Vec::new(),
+        limit: 20,
+        truncate: true,
+    };
+    let (mut len, mut poisoned) = (0, false);
+    append_record(&mut log, &mut len, &mut poisoned, &[CLEAR]).unwrap();
+    assert_eq!(len, 9);
+
+    // The partial record is cut off, and the log accepts records again
+    let payload = [INSERT; 16];
+    assert!(matches!(
+        append_record(&mut log, &mut len, &mut poisoned, &payload),
+        Err(PersistError::Io(_))
+    ));
+    assert_eq!((log.bytes.len(), len, poisoned), (9, 9, false));
+    append_record(&mut log, &mut len, &mut poisoned, &[CLEAR]).unwrap();
+    assert_eq!(log.bytes.len(), 18);
+
+    // A log which can't be repaired refuses further records
+    log.truncate = false;
+    assert!(append_record(&mut log, &mut len, &mut poisoned, &payload).is_err());
+    assert!(poisoned);
+    log.limit = usize::MAX;
+    assert!(matches!(
+        append_record(&mut log, &mut len, &mut poisoned, &[CLEAR]),
+        Err(PersistError::Poisoned)
+    ));
+    assert_eq!(log.bytes.len(), 20);
+}
+
+#[test]
+fn failed_compaction() {
+    let dir = TempDir::new("compaction");
+    let mut map = Map::open(&dir.0).unwrap();
+    for i in 0..5 {
+        map.insert(i.to_string(), i).unwrap();
+    }
+    // Reused as 1 then 3, while a reloaded map reuses 3 then 1
+    map.remove("3").unwrap();
+    map.remove("1").unwrap();
+
+    // A snapshot which can't be written leaves the map as the log has it
+    let snapshot_tmp = dir.0.join("snapshot.tmp");
+    fs::create_dir(&snapshot_tmp).unwrap();
+    assert!(map.compact().is_err());
+    assert_eq!(map.insert_full("x".to_string(), 5).unwrap(), (1, None));
+    fs::remove_dir(&snapshot_tmp).unwrap();
+    drop(map);
+    let mut map = Map::open(&dir.0).unwrap();
+    assert_eq!(map.get_index_of("x"), Some(1));
+
+    // A log which can't be started refuses changes until a compaction works
+    let wal_tmp = dir.0.join("wal.tmp");
+    fs::create_dir(&wal_tmp).unwrap();
+    assert!(map.compact().is_err());
+    assert!(matches!(
+        map.insert("y".to_string(), 6),
+        Err(PersistError::Poisoned)
+    ));
+    fs::remove_dir(&wal_tmp).unwrap();
+    map.compact().unwrap();
+    map.insert("y".to_string(), 6).unwrap();
+    let expected = sorted(&map);
+    drop(map);
+    let map = Map::open(&dir.0).unwrap();
+    assert_eq!(sorted(&map), expected);
+}
+
+#[test]
+fn changes_survive_failed_compactions() {
+    let dir = TempDir::new("automatic");
+    let mut map = Map::open(&dir.0).unwrap();
+    map.set_compaction_threshold(0);
+    let snapshot_tmp = dir.0.join("snapshot.tmp");
+    fs::create_dir(&snapshot_tmp).unwrap();
+
+    // The changes are reported although the compactions fail
+    map.insert("a".to_string(), 1).unwrap();
+    assert_eq!(map.insert("a".to_string(), 2).unwrap(), Some(1));
+    assert_eq!(map.remove("a").unwrap(), Some(2));
+    map.clear().unwrap();
+    assert_eq!(map.log_len(), 4);
+    assert!(map.compact().is_err());
+
+    // and retried after the next change
+    fs::remove_dir(&snapshot_tmp).unwrap();
+    map.insert("b".to_string(), 3).unwrap();
+    assert_eq!(map.log_len(), 0);
+    drop(map);
+    let map = Map::open(&dir.0).unwrap();
+    assert_eq!(sorted(&map), [(0, "b".to_string(), 3)]);
+}
+
+#[test]
+fn large_damaged_tail() {
+    let dir = TempDir::new("tail");
+    let mut map = Map::open(&dir.0).unwrap();
+    map.insert("a".to_string(), 1).unwrap();
+    drop(map);
+
+    // Nearly every offset of the tail holds a length which fits, so checking
+    // them all would take ages
+    let wal = dir.0.join(WAL);
+    let mut bytes = fs::read(&wal).unwrap();
+    let offset = bytes.len();
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend([0, 0, 4, 0].iter().cycle().take(1 << 20));
+    fs::write(&wal, bytes).unwrap();
+    assert!(matches!(
+        Map::open(&dir.0),
+        Err(PersistError::Corrupted { file: "wal", offset: o }) if o == offset
+    ));
+}
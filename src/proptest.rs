@@ -0,0 +1,355 @@
+//! [proptest] strategies for [`HashSlabMap`] and [`HashSlabSet`].
+//!
+//! The strategies generate maps through interleaved inserts and removals, so
+//! their indices have holes like the indices of long-lived maps. Shrinking
+//! preserves the index structure: it first removes single entries, leaving
+//! every other entry at its index, then shrinks the remaining values in
+//! place. Keys are not shrunk, since a shrunk key could collide with another
+//! one.
+//!
+//! Maps and sets also implement [`Arbitrary`], so they can be generated with
+//! [`any`](::proptest::arbitrary::any).
+//!
+//! # Examples
+//!
+//! ```
+//! use hashslab::proptest::hash_slab_map;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     fn indices_are_stable(map in hash_slab_map(any::<u8>(), any::<u16>(), 0..32)) {
+//!         for (index, key, _) in map.iter_full() {
+//!             prop_assert_eq!(map.get_index_of(key), Some(index));
+//!         }
+//!     }
+//! }
+//! # indices_are_stable();
+//! ```
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+use std::hash::RandomState;
+
+use ::proptest::{
+    arbitrary::{any, Arbitrary},
+    collection::SizeRange,
+    strategy::{Just, NewTree, Strategy, ValueTree},
+    test_runner::TestRunner,
+};
+
+use crate::{HashSlabMap, HashSlabSet};
+
+/// Creates a strategy generating maps with up to `size` entries, with keys
+/// and values from `key` and `value`.
+///
+/// Maps may have fewer entries than the lower bound of `size`, since
+/// generated keys may collide and some entries are removed again to leave
+/// holes in the indices.
+pub fn hash_slab_map<K, V>(
+    key: K,
+    value: V,
+    size: impl Into<SizeRange>,
+) -> HashSlabMapStrategy<K, V>
+where
+    K: Strategy,
+    K::Value: Hash + Eq,
+    V: Strategy,
+{
+    HashSlabMapStrategy::new(key, value, size)
+}
+
+/// Creates a strategy generating sets with up to `size` values from
+/// `element`.
+///
+/// See [`hash_slab_map`] for the size of the generated sets.
+pub fn hash_slab_set<T>(element: T, size: impl Into<SizeRange>) -> HashSlabSetStrategy<T>
+where
+    T: Strategy,
+    T::Value: Hash + Eq,
+{
+    HashSlabSetStrategy::new(element, size)
+}
+
+/// Strategy generating [`HashSlabMap`]s, created by [`hash_slab_map`].
+pub struct HashSlabMapStrategy<K, V, S = RandomState> {
+    key: K,
+    value: V,
+    size: SizeRange,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<K, V, S> HashSlabMapStrategy<K, V, S>
+where
+    K: Strategy,
+    K::Value: Hash + Eq,
+    V: Strategy,
+    S: BuildHasher + Default,
+{
+    /// Creates a strategy generating maps with the hasher `S`.
+    pub fn new(key: K, value: V, size: impl Into<SizeRange>) -> Self {
+        Self {
+            key,
+            value,
+            size: size.into(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for HashSlabMapStrategy<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashSlabMapStrategy")
+            .field("key", &self.key)
+            .field("value", &self.value)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl<K, V, S> Strategy for HashSlabMapStrategy<K, V, S>
+where
+    K: Strategy,
+    K::Value: Hash + Eq,
+    V: Strategy,
+    S: BuildHasher + Default,
+{
+    type Tree = HashSlabMapValueTree<K::Tree, V::Tree, S>;
+    type Value = HashSlabMap<K::Value, V::Value, S>;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let (start, end) = self.size.start_end_incl();
+        let len = (start..=end).new_tree(runner)?.current();
+        let mut ops = Vec::with_capacity(len);
+        for _ in 0..len {
+            ops.push((
+                self.key.new_tree(runner)?,
+                self.value.new_tree(runner)?,
+                any::<u8>().new_tree(runner)?.current(),
+            ));
+        }
+        Ok(HashSlabMapValueTree::new(ops))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Shrink {
+    Remove(usize),
+    Value(usize),
+}
+
+/// Value tree of [`HashSlabMapStrategy`].
+pub struct HashSlabMapValueTree<K, V, S> {
+    // Generated operations: an insert, then a removal chosen by the churn
+    ops: Vec<(K, V, u8)>,
+    // Entries of the generated map as (index, operation), sorted by index
+    live: Vec<(usize, usize)>,
+    excluded: Vec<bool>,
+    shrink: Shrink,
+    last: Option<Shrink>,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<K, V, S> HashSlabMapValueTree<K, V, S>
+where
+    K: ValueTree,
+    K::Value: Hash + Eq,
+    V: ValueTree,
+    S: BuildHasher + Default,
+{
+    fn new(ops: Vec<(K, V, u8)>) -> Self {
+        // Replay with operation positions as values to find the entries
+        let mut map = HashSlabMap::<_, _, S>::with_hasher(S::default());
+        for (op, (key, _, churn)) in ops.iter().enumerate() {
+            map.churn(key.current(), op, *churn);
+        }
+        let mut live: Vec<_> = map.iter_full().map(|(index, _, &op)| (index, op)).collect();
+        live.sort_unstable();
+        Self {
+            excluded: vec![false; live.len()],
+            shrink: Shrink::Remove(live.len()),
+            live,
+            ops,
+            last: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> ValueTree for HashSlabMapValueTree<K, V, S>
+where
+    K: ValueTree,
+    K::Value: Hash + Eq,
+    V: ValueTree,
+    S: BuildHasher + Default,
+{
+    type Value = HashSlabMap<K::Value, V::Value, S>;
+
+    fn current(&self) -> Self::Value {
+        let mut map = HashSlabMap::with_hasher(S::default());
+        for (key, value, churn) in &self.ops {
+            map.churn(key.current(), value.current(), *churn);
+        }
+        for (&(index, _), _) in self
+            .live
+            .iter()
+            .zip(&self.excluded)
+            .filter(|(_, &excluded)| excluded)
+        {
+            map.remove_index(index);
+        }
+        map
+    }
+
+    fn simplify(&mut self) -> bool {
+        // Remove entries from the highest index down, then shrink values
+        while let Shrink::Remove(pos) = self.shrink {
+            if pos == 0 {
+                self.shrink = Shrink::Value(0);
+                break;
+            }
+            let pos = pos - 1;
+            self.shrink = Shrink::Remove(pos);
+            if !self.excluded[pos] {
+                self.excluded[pos] = true;
+                self.last = Some(Shrink::Remove(pos));
+                return true;
+            }
+        }
+        while let Shrink::Value(pos) = self.shrink {
+            if pos == self.live.len() {
+                break;
+            }
+            if !self.excluded[pos] && self.ops[self.live[pos].1].1.simplify() {
+                self.last = Some(Shrink::Value(pos));
+                return true;
+            }
+            self.shrink = Shrink::Value(pos + 1);
+        }
+        self.last = None;
+        false
+    }
+
+    fn complicate(&mut self) -> bool {
+        match self.last {
+            Some(Shrink::Remove(pos)) => {
+                self.excluded[pos] = false;
+                self.last = None;
+                true
+            }
+            Some(Shrink::Value(pos)) => {
+                if self.ops[self.live[pos].1].1.complicate() {
+                    true
+                } else {
+                    self.last = None;
+                    false
+                }
+            }
+            None => false,
+        }
+    }
+}
+
+/// Strategy generating [`HashSlabSet`]s, created by [`hash_slab_set`].
+pub struct HashSlabSetStrategy<T, S = RandomState> {
+    map: HashSlabMapStrategy<T, Just<()>, S>,
+}
+
+impl<T, S> HashSlabSetStrategy<T, S>
+where
+    T: Strategy,
+    T::Value: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    /// Creates a strategy generating sets with the hasher `S`.
+    pub fn new(element: T, size: impl Into<SizeRange>) -> Self {
+        Self {
+            map: HashSlabMapStrategy::new(element, Just(()), size),
+        }
+    }
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for HashSlabSetStrategy<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashSlabSetStrategy")
+            .field("element", &self.map.key)
+            .field("size", &self.map.size)
+            .finish()
+    }
+}
+
+impl<T, S> Strategy for HashSlabSetStrategy<T, S>
+where
+    T: Strategy,
+    T::Value: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    type Tree = HashSlabSetValueTree<T::Tree, S>;
+    type Value = HashSlabSet<T::Value, S>;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        self.map
+            .new_tree(runner)
+            .map(|map| HashSlabSetValueTree { map })
+    }
+}
+
+/// Value tree of [`HashSlabSetStrategy`].
+pub struct HashSlabSetValueTree<T, S> {
+    map: HashSlabMapValueTree<T, Just<()>, S>,
+}
+
+impl<T, S> ValueTree for HashSlabSetValueTree<T, S>
+where
+    T: ValueTree,
+    T::Value: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    type Value = HashSlabSet<T::Value, S>;
+
+    fn current(&self) -> Self::Value {
+        HashSlabSet {
+            map: self.map.current(),
+        }
+    }
+
+    fn simplify(&mut self) -> bool {
+        self.map.simplify()
+    }
+
+    fn complicate(&mut self) -> bool {
+        self.map.complicate()
+    }
+}
+
+impl<K, V, S> Arbitrary for HashSlabMap<K, V, S>
+where
+    K: Arbitrary + Hash + Eq,
+    V: Arbitrary,
+    S: BuildHasher + Default,
+{
+    type Parameters = (SizeRange, K::Parameters, V::Parameters);
+    type Strategy = HashSlabMapStrategy<K::Strategy, V::Strategy, S>;
+
+    fn arbitrary_with((size, key, value): Self::Parameters) -> Self::Strategy {
+        HashSlabMapStrategy::new(K::arbitrary_with(key), V::arbitrary_with(value), size)
+    }
+}
+
+impl<T, S> Arbitrary for HashSlabSet<T, S>
+where
+    T: Arbitrary + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    type Parameters = (SizeRange, T::Parameters);
+    type Strategy = HashSlabSetStrategy<T::Strategy, S>;
+
+    fn arbitrary_with((size, element): Self::Parameters) -> Self::Strategy {
+        HashSlabSetStrategy::new(T::arbitrary_with(element), size)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,77 @@
+use alloc::vec::Vec;
+
+use ::proptest::{
+    arbitrary::any,
+    strategy::{Strategy, ValueTree},
+    test_runner::TestRunner,
+};
+
+use super::*;
+
+#[test]
+fn leaves_index_holes() {
+    let mut runner = TestRunner::deterministic();
+    let strategy = hash_slab_map(any::<u8>(), any::<u16>(), 16..32);
+    let holes: usize = (0..16)
+        .map(|_| {
+            let map = strategy.new_tree(&mut runner).unwrap().current();
+            let end = map
+                .iter_full()
+                .map(|(index, _, _)| index + 1)
+                .max()
+                .unwrap_or(0);
+            end - map.len()
+        })
+        .sum();
+    assert!(holes > 0);
+}
+
+#[test]
+fn shrink_preserves_indices() {
+    let mut runner = TestRunner::deterministic();
+    let mut tree = hash_slab_map(any::<u8>(), 1..1000u16, 8..16)
+        .new_tree(&mut runner)
+        .unwrap();
+    let entries = |map: &HashSlabMap<u8, u16>| {
+        let mut entries: Vec<_> = map.iter_full().map(|(i, k, v)| (i, *k, *v)).collect();
+        entries.sort_unstable();
+        entries
+    };
+    let original = entries(&tree.current());
+
+    // Keep every other entry, then shrink the values of the kept ones
+    let mut removals = 0;
+    let mut len = original.len();
+    while tree.simplify() {
+        let current = entries(&tree.current());
+        assert!(current
+            .iter()
+            .all(|&(i, k, _)| original.iter().any(|e| (e.0, e.1) == (i, k))));
+        if current.len() < len {
+            removals += 1;
+            if removals % 2 == 0 {
+                assert!(tree.complicate());
+                continue;
+            }
+            len = current.len();
+        }
+    }
+    let shrunk = entries(&tree.current());
+    assert!(!shrunk.is_empty() && shrunk.len() < original.len());
+    for (index, key, value) in shrunk {
+        assert!(original.iter().any(|&(i, k, _)| (i, k) == (index, key)));
+        assert_eq!(value, 1);
+    }
+}
+
+#[test]
+fn arbitrary_set() {
+    let mut runner = TestRunner::deterministic();
+    let set = any::<HashSlabSet<u16>>()
+        .new_tree(&mut runner)
+        .unwrap()
+        .current();
+    for (index, value) in set.iter_full() {
+        assert_eq!(set.get_index_of(value), Some(index));
+    }
+}
@@ -0,0 +1,94 @@
+//! `quickcheck::Arbitrary` for [`HashSlabMap`] and [`HashSlabSet`]
+//!
+//! Generated maps go through interleaved inserts and removals, so their
+//! indices have holes. Shrinking preserves the index structure: it first
+//! removes single entries, leaving every other entry at its index, then
+//! shrinks values in place.
+use alloc::{boxed::Box, vec::Vec};
+use core::hash::{BuildHasher, Hash};
+
+use ::quickcheck::{Arbitrary, Gen};
+
+use crate::{HashSlabMap, HashSlabSet};
+
+fn generate<K, V, S>(entries: Vec<(K, V, u8)>) -> HashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    let mut map = HashSlabMap::with_hasher(S::default());
+    for (key, value, churn) in entries {
+        map.churn(key, value, churn);
+    }
+    map
+}
+
+fn sorted_indices<K, V, S>(map: &HashSlabMap<K, V, S>) -> Vec<usize> {
+    let mut indices: Vec<_> = map.iter_full().map(|(index, _, _)| index).collect();
+    indices.sort_unstable();
+    indices
+}
+
+// Smaller maps with one entry removed, highest index first
+fn shrink_entries<K, V, S>(map: HashSlabMap<K, V, S>) -> impl Iterator<Item = HashSlabMap<K, V, S>>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    sorted_indices(&map).into_iter().rev().map(move |index| {
+        let mut map = map.clone();
+        map.remove_index(index);
+        map
+    })
+}
+
+impl<K, V, S> Arbitrary for HashSlabMap<K, V, S>
+where
+    K: Arbitrary + Hash + Eq,
+    V: Arbitrary,
+    S: BuildHasher + Default + Clone + 'static,
+{
+    fn arbitrary(g: &mut Gen) -> Self {
+        generate(Arbitrary::arbitrary(g))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let map = self.clone();
+        let removed = shrink_entries(map.clone());
+        let values = sorted_indices(&map).into_iter().flat_map(move |index| {
+            let map = map.clone();
+            let (_, value) = map.get_index(index).unwrap();
+            value.shrink().map(move |value| {
+                let mut map = map.clone();
+                *map.get_index_mut(index).unwrap().1 = value;
+                map
+            })
+        });
+        Box::new(removed.chain(values))
+    }
+}
+
+impl<T, S> Arbitrary for HashSlabSet<T, S>
+where
+    T: Arbitrary + Hash + Eq,
+    S: BuildHasher + Default + Clone + 'static,
+{
+    fn arbitrary(g: &mut Gen) -> Self {
+        let entries: Vec<(T, u8)> = Arbitrary::arbitrary(g);
+        let map = generate(
+            entries
+                .into_iter()
+                .map(|(value, churn)| (value, (), churn))
+                .collect(),
+        );
+        Self { map }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(shrink_entries(self.map.clone()).map(|map| Self { map }))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,55 @@
+use alloc::vec::Vec;
+
+use ::quickcheck::{Arbitrary, Gen};
+use fnv::FnvBuildHasher;
+
+use crate::{HashSlabMap, HashSlabSet};
+
+type Map = HashSlabMap<u8, u16, FnvBuildHasher>;
+
+fn entries(map: &Map) -> Vec<(usize, u8, u16)> {
+    let mut entries: Vec<_> = map.iter_full().map(|(i, k, v)| (i, *k, *v)).collect();
+    entries.sort_unstable();
+    entries
+}
+
+#[test]
+fn leaves_index_holes() {
+    let mut g = Gen::new(64);
+    let holes: usize = (0..32)
+        .map(|_| {
+            let map = Map::arbitrary(&mut g);
+            let end = map
+                .iter_full()
+                .map(|(index, _, _)| index + 1)
+                .max()
+                .unwrap_or(0);
+            end - map.len()
+        })
+        .sum();
+    assert!(holes > 0);
+}
+
+#[test]
+fn shrink_preserves_indices() {
+    let mut g = Gen::new(32);
+    let map = (0..)
+        .map(|_| Map::arbitrary(&mut g))
+        .find(|map| map.len() > 2)
+        .unwrap();
+    let original = entries(&map);
+    for smaller in map.shrink().take(64) {
+        let smaller = entries(&smaller);
+        if smaller.len() < original.len() {
+            assert!(smaller.iter().all(|entry| original.contains(entry)));
+        } else {
+            assert!(smaller
+                .iter()
+                .zip(&original)
+                .all(|(a, b)| a.0 == b.0 && a.1 == b.1));
+        }
+    }
+
+    let set = HashSlabSet::<u8, FnvBuildHasher>::arbitrary(&mut g);
+    assert!(set.shrink().all(|smaller| smaller.len() + 1 == set.len()));
+}
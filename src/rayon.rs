@@ -0,0 +1,38 @@
+//! Parallel iterator types for [`HashSlabMap`][crate::HashSlabMap] and
+//! [`HashSlabSet`][crate::HashSlabSet] with [rayon].
+//!
+//! You will rarely need to interact with this module directly unless you
+//! need to name one of the iterator types.
+//!
+//! [rayon]: https://docs.rs/rayon/1.0/rayon
+use alloc::{collections::LinkedList, vec::Vec};
+
+use ::rayon::prelude::*;
+
+pub mod map;
+pub mod set;
+
+#[cfg(test)]
+mod tests;
+
+// Collect a parallel iterator into a list of vectors, one per rayon job,
+// so the items can be inserted sequentially afterwards.
+fn collect<I: IntoParallelIterator>(iter: I) -> (LinkedList<Vec<I::Item>>, usize) {
+    let list = iter
+        .into_par_iter()
+        .fold(Vec::new, |mut vec, elem| {
+            vec.push(elem);
+            vec
+        })
+        .map(|vec| {
+            let mut list = LinkedList::new();
+            list.push_back(vec);
+            list
+        })
+        .reduce(LinkedList::new, |mut list1, mut list2| {
+            list1.append(&mut list2);
+            list1
+        });
+    let len = list.iter().map(Vec::len).sum();
+    (list, len)
+}
@@ -0,0 +1,514 @@
+//! Parallel iterator types for [`HashSlabMap`] with [rayon].
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+
+use ::rayon::{iter::plumbing::UnindexedConsumer, prelude::*, vec};
+use hashbrown::HashTable;
+use slab::Slab;
+
+use crate::{HashSlabMap, KeyData, ValueData};
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Sync,
+    V: Sync,
+{
+    /// Returns a parallel iterator over the key-value pairs of the map, in
+    /// arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::HashSlabMap;
+    /// use rayon::prelude::*;
+    ///
+    /// let map: HashSlabMap<_, _> = (0..1000).map(|x| (x, x * 2)).collect();
+    /// let sum: i32 = map.par_iter().map(|(_, v)| v).sum();
+    /// assert_eq!(sum, 999_000);
+    /// ```
+    pub fn par_iter(&self) -> ParIter<'_, K, V> {
+        ParIter::new(&self.table, &self.slab)
+    }
+
+    /// Returns a parallel iterator over the index-key-value triples of the
+    /// map, in arbitrary order.
+    pub fn par_iter_full(&self) -> ParIterFull<'_, K, V> {
+        ParIterFull::new(&self.table, &self.slab)
+    }
+
+    /// Returns a parallel iterator over the keys of the map, in arbitrary order.
+    pub fn par_keys(&self) -> ParKeys<'_, K> {
+        ParKeys::new(&self.table)
+    }
+
+    /// Returns a parallel iterator over the values of the map, in arbitrary order.
+    pub fn par_values(&self) -> ParValues<'_, K, V> {
+        ParValues::new(&self.table, &self.slab)
+    }
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Sync,
+    V: Send,
+{
+    /// Returns a parallel iterator over the key-value pairs of the map, with
+    /// mutable references to the values, in arbitrary order.
+    ///
+    /// The entries are gathered sequentially before being split between
+    /// threads, so this pays off when the work per entry dominates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::HashSlabMap;
+    /// use rayon::prelude::*;
+    ///
+    /// let mut map: HashSlabMap<_, _> = (0..100).map(|x| (x, x)).collect();
+    /// map.par_iter_mut().for_each(|(k, v)| *v += k);
+    /// assert_eq!(map[&10], 20);
+    /// ```
+    pub fn par_iter_mut(&mut self) -> ParIterMut<'_, K, V> {
+        let entries = self.iter_full_mut().map(|(_, k, v)| (k, v)).collect();
+        ParIterMut::new(entries)
+    }
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    V: Send,
+{
+    /// Returns a parallel iterator over mutable references to the values of
+    /// the map, in arbitrary order.
+    pub fn par_values_mut(&mut self) -> ParValuesMut<'_, V> {
+        let values = self
+            .slab
+            .iter_mut()
+            .map(|(_, data)| &mut data.value)
+            .collect();
+        ParValuesMut::new(values)
+    }
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Send,
+    V: Send,
+{
+    /// Clears the map, returning all key-value pairs as a parallel iterator.
+    /// Keeps the allocated memory for reuse.
+    ///
+    /// Unlike [`drain`][HashSlabMap::drain], the map is emptied right away,
+    /// even if the returned iterator is not consumed.
+    pub fn par_drain(&mut self) -> ParDrain<K, V> {
+        ParDrain::new(self.drain().collect())
+    }
+}
+
+/// A parallel iterator over the entries of a [`HashSlabMap`].
+///
+/// This `struct` is created by the [`HashSlabMap::par_iter`] method.
+/// See its documentation for more.
+pub struct ParIter<'a, K, V> {
+    table: &'a HashTable<KeyData<K>>,
+    slab: &'a Slab<ValueData<V>>,
+}
+
+impl<'a, K, V> ParIter<'a, K, V> {
+    fn new(table: &'a HashTable<KeyData<K>>, slab: &'a Slab<ValueData<V>>) -> Self {
+        Self { table, slab }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K, V> Clone for ParIter<'_, K, V> {
+    fn clone(&self) -> Self {
+        ParIter { ..*self }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for ParIter<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let iter = self
+            .table
+            .iter()
+            .map(|KeyData { key, index }| (key, &self.slab[*index].value));
+        f.debug_map().entries(iter).finish()
+    }
+}
+
+impl<'a, K: Sync, V: Sync> ParallelIterator for ParIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let slab = self.slab;
+        self.table
+            .par_iter()
+            .map(move |KeyData { key, index }| (key, &slab[*index].value))
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator over the entries of a [`HashSlabMap`], with their indices.
+///
+/// This `struct` is created by the [`HashSlabMap::par_iter_full`] method.
+/// See its documentation for more.
+pub struct ParIterFull<'a, K, V> {
+    table: &'a HashTable<KeyData<K>>,
+    slab: &'a Slab<ValueData<V>>,
+}
+
+impl<'a, K, V> ParIterFull<'a, K, V> {
+    fn new(table: &'a HashTable<KeyData<K>>, slab: &'a Slab<ValueData<V>>) -> Self {
+        Self { table, slab }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K, V> Clone for ParIterFull<'_, K, V> {
+    fn clone(&self) -> Self {
+        ParIterFull { ..*self }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for ParIterFull<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let iter = self
+            .table
+            .iter()
+            .map(|KeyData { key, index }| (index, key, &self.slab[*index].value));
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+impl<'a, K: Sync, V: Sync> ParallelIterator for ParIterFull<'a, K, V> {
+    type Item = (usize, &'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let slab = self.slab;
+        self.table
+            .par_iter()
+            .map(move |KeyData { key, index }| (*index, key, &slab[*index].value))
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator over the keys of a [`HashSlabMap`].
+///
+/// This `struct` is created by the [`HashSlabMap::par_keys`] method.
+/// See its documentation for more.
+pub struct ParKeys<'a, K> {
+    table: &'a HashTable<KeyData<K>>,
+}
+
+impl<'a, K> ParKeys<'a, K> {
+    fn new(table: &'a HashTable<KeyData<K>>) -> Self {
+        Self { table }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K> Clone for ParKeys<'_, K> {
+    fn clone(&self) -> Self {
+        ParKeys { table: self.table }
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for ParKeys<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let iter = self.table.iter().map(|KeyData { key, .. }| key);
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+impl<'a, K: Sync> ParallelIterator for ParKeys<'a, K> {
+    type Item = &'a K;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.table
+            .par_iter()
+            .map(|KeyData { key, .. }| key)
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator over the values of a [`HashSlabMap`].
+///
+/// This `struct` is created by the [`HashSlabMap::par_values`] method.
+/// See its documentation for more.
+pub struct ParValues<'a, K, V> {
+    table: &'a HashTable<KeyData<K>>,
+    slab: &'a Slab<ValueData<V>>,
+}
+
+impl<'a, K, V> ParValues<'a, K, V> {
+    fn new(table: &'a HashTable<KeyData<K>>, slab: &'a Slab<ValueData<V>>) -> Self {
+        Self { table, slab }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K, V> Clone for ParValues<'_, K, V> {
+    fn clone(&self) -> Self {
+        ParValues { ..*self }
+    }
+}
+
+impl<K, V: fmt::Debug> fmt::Debug for ParValues<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let iter = self
+            .table
+            .iter()
+            .map(|KeyData { index, .. }| &self.slab[*index].value);
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+impl<'a, K: Sync, V: Sync> ParallelIterator for ParValues<'a, K, V> {
+    type Item = &'a V;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let slab = self.slab;
+        self.table
+            .par_iter()
+            .map(move |KeyData { index, .. }| &slab[*index].value)
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator over the entries of a [`HashSlabMap`], with mutable
+/// references to the values.
+///
+/// This `struct` is created by the [`HashSlabMap::par_iter_mut`] method.
+/// See its documentation for more.
+pub struct ParIterMut<'a, K, V> {
+    entries: Vec<(&'a K, &'a mut V)>,
+}
+
+impl<'a, K, V> ParIterMut<'a, K, V> {
+    fn new(entries: Vec<(&'a K, &'a mut V)>) -> Self {
+        Self { entries }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for ParIterMut<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let iter = self.entries.iter().map(|(k, v)| (k, &**v));
+        f.debug_map().entries(iter).finish()
+    }
+}
+
+impl<'a, K: Sync, V: Send> ParallelIterator for ParIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.entries.into_par_iter().drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// A parallel iterator over mutable references to the values of a [`HashSlabMap`].
+///
+/// This `struct` is created by the [`HashSlabMap::par_values_mut`] method.
+/// See its documentation for more.
+pub struct ParValuesMut<'a, V> {
+    values: Vec<&'a mut V>,
+}
+
+impl<'a, V> ParValuesMut<'a, V> {
+    fn new(values: Vec<&'a mut V>) -> Self {
+        Self { values }
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for ParValuesMut<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.values).finish()
+    }
+}
+
+impl<'a, V: Send> ParallelIterator for ParValuesMut<'a, V> {
+    type Item = &'a mut V;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.values.into_par_iter().drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+/// A draining parallel iterator over the entries of a [`HashSlabMap`].
+///
+/// This `struct` is created by the [`HashSlabMap::par_drain`] method.
+/// See its documentation for more.
+pub struct ParDrain<K, V> {
+    entries: Vec<(K, V)>,
+}
+
+impl<K, V> ParDrain<K, V> {
+    fn new(entries: Vec<(K, V)>) -> Self {
+        Self { entries }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for ParDrain<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let iter = self.entries.iter().map(|(k, v)| (k, v));
+        f.debug_map().entries(iter).finish()
+    }
+}
+
+impl<K: Send, V: Send> ParallelIterator for ParDrain<K, V> {
+    type Item = (K, V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.entries.into_par_iter().drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// An owning parallel iterator over the entries of a [`HashSlabMap`].
+///
+/// This `struct` is created by the `into_par_iter` method on [`HashSlabMap`]
+/// (provided by the [`IntoParallelIterator`] trait).
+pub struct IntoParIter<K, V> {
+    entries: vec::IntoIter<(K, V)>,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for IntoParIter<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoParIter").finish_non_exhaustive()
+    }
+}
+
+impl<K: Send, V: Send> ParallelIterator for IntoParIter<K, V> {
+    type Item = (K, V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.entries.drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+impl<K: Send, V: Send, S> IntoParallelIterator for HashSlabMap<K, V, S> {
+    type Item = (K, V);
+    type Iter = IntoParIter<K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        let entries: Vec<_> = self.into_iter().collect();
+        IntoParIter {
+            entries: entries.into_par_iter(),
+        }
+    }
+}
+
+impl<'a, K: Sync, V: Sync, S> IntoParallelIterator for &'a HashSlabMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<'a, K: Sync, V: Send, S> IntoParallelIterator for &'a mut HashSlabMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type Iter = ParIterMut<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter_mut()
+    }
+}
+
+impl<K, V, S> FromParallelIterator<(K, V)> for HashSlabMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Default,
+{
+    /// Collects the entries of a parallel iterator into a map.
+    ///
+    /// The entries are produced in parallel, but inserted sequentially.
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let mut map = HashSlabMap::default();
+        map.par_extend(par_iter);
+        map
+    }
+}
+
+impl<K, V, S> ParallelExtend<(K, V)> for HashSlabMap<K, V, S>
+where
+    K: Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let (list, len) = super::collect(par_iter);
+        self.reserve(len);
+        for vec in list {
+            self.extend(vec);
+        }
+    }
+}
+
+impl<'a, K, V, S> ParallelExtend<(&'a K, &'a V)> for HashSlabMap<K, V, S>
+where
+    K: Copy + Eq + Hash + Send + Sync,
+    V: Copy + Send + Sync,
+    S: BuildHasher,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (&'a K, &'a V)>,
+    {
+        let (list, len) = super::collect(par_iter);
+        self.reserve(len);
+        for vec in list {
+            self.extend(vec);
+        }
+    }
+}
@@ -0,0 +1,539 @@
+//! Parallel iterator types for [`HashSlabSet`] with [rayon].
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+
+use ::rayon::{iter::plumbing::UnindexedConsumer, prelude::*};
+
+use crate::HashSlabSet;
+
+use super::map;
+
+impl<T: Sync, S> HashSlabSet<T, S> {
+    /// Returns a parallel iterator over the values of the set, in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::HashSlabSet;
+    /// use rayon::prelude::*;
+    ///
+    /// let set: HashSlabSet<_> = (0..1000).collect();
+    /// assert_eq!(set.par_iter().filter(|&&x| x % 2 == 0).count(), 500);
+    /// ```
+    pub fn par_iter(&self) -> ParIter<'_, T> {
+        ParIter::new(self.map.par_keys())
+    }
+
+    /// Returns a parallel iterator over the index-value pairs of the set,
+    /// in arbitrary order.
+    pub fn par_iter_full(&self) -> ParIterFull<'_, T> {
+        ParIterFull::new(self.map.par_iter_full())
+    }
+}
+
+impl<T: Send, S> HashSlabSet<T, S> {
+    /// Clears the set, returning all values as a parallel iterator.
+    /// Keeps the allocated memory for reuse.
+    ///
+    /// Unlike [`drain`][HashSlabSet::drain], the set is emptied right away,
+    /// even if the returned iterator is not consumed.
+    pub fn par_drain(&mut self) -> ParDrain<T> {
+        ParDrain::new(self.map.par_drain())
+    }
+}
+
+impl<T, S1> HashSlabSet<T, S1>
+where
+    T: Hash + Eq + Sync,
+    S1: BuildHasher + Sync,
+{
+    /// Returns a parallel iterator over the values that are in `self` but
+    /// not in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::HashSlabSet;
+    /// use rayon::prelude::*;
+    ///
+    /// let a: HashSlabSet<_> = (0..100).collect();
+    /// let b: HashSlabSet<_> = (50..150).collect();
+    /// assert_eq!(a.par_difference(&b).count(), 50);
+    /// assert_eq!(a.par_symmetric_difference(&b).count(), 100);
+    /// assert_eq!(a.par_intersection(&b).count(), 50);
+    /// assert_eq!(a.par_union(&b).count(), 150);
+    /// ```
+    pub fn par_difference<'a, S2>(
+        &'a self,
+        other: &'a HashSlabSet<T, S2>,
+    ) -> ParDifference<'a, T, S1, S2>
+    where
+        S2: BuildHasher + Sync,
+    {
+        ParDifference { a: self, b: other }
+    }
+
+    /// Returns a parallel iterator over the values that are in `self` or in
+    /// `other`, but not in both.
+    pub fn par_symmetric_difference<'a, S2>(
+        &'a self,
+        other: &'a HashSlabSet<T, S2>,
+    ) -> ParSymmetricDifference<'a, T, S1, S2>
+    where
+        S2: BuildHasher + Sync,
+    {
+        ParSymmetricDifference { a: self, b: other }
+    }
+
+    /// Returns a parallel iterator over the values that are both in `self`
+    /// and `other`.
+    pub fn par_intersection<'a, S2>(
+        &'a self,
+        other: &'a HashSlabSet<T, S2>,
+    ) -> ParIntersection<'a, T, S1, S2>
+    where
+        S2: BuildHasher + Sync,
+    {
+        ParIntersection { a: self, b: other }
+    }
+
+    /// Returns a parallel iterator over all the values in `self` or `other`,
+    /// without duplicates.
+    pub fn par_union<'a, S2>(&'a self, other: &'a HashSlabSet<T, S2>) -> ParUnion<'a, T, S1, S2>
+    where
+        S2: BuildHasher + Sync,
+    {
+        ParUnion { a: self, b: other }
+    }
+
+    /// Returns `true` if `self` has no elements in common with `other`,
+    /// checking in parallel.
+    pub fn par_is_disjoint<S2>(&self, other: &HashSlabSet<T, S2>) -> bool
+    where
+        S2: BuildHasher + Sync,
+    {
+        if self.len() <= other.len() {
+            self.par_iter().all(move |value| !other.contains(value))
+        } else {
+            other.par_iter().all(move |value| !self.contains(value))
+        }
+    }
+
+    /// Returns `true` if all values of `self` are contained in `other`,
+    /// checking in parallel.
+    pub fn par_is_subset<S2>(&self, other: &HashSlabSet<T, S2>) -> bool
+    where
+        S2: BuildHasher + Sync,
+    {
+        self.len() <= other.len() && self.par_iter().all(move |value| other.contains(value))
+    }
+
+    /// Returns `true` if all values of `other` are contained in `self`,
+    /// checking in parallel.
+    pub fn par_is_superset<S2>(&self, other: &HashSlabSet<T, S2>) -> bool
+    where
+        S2: BuildHasher + Sync,
+    {
+        other.par_is_subset(self)
+    }
+}
+
+/// A parallel iterator over the values of a [`HashSlabSet`].
+///
+/// This `struct` is created by the [`HashSlabSet::par_iter`] method.
+/// See its documentation for more.
+pub struct ParIter<'a, T> {
+    keys: map::ParKeys<'a, T>,
+}
+
+impl<'a, T> ParIter<'a, T> {
+    fn new(keys: map::ParKeys<'a, T>) -> Self {
+        Self { keys }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<T> Clone for ParIter<'_, T> {
+    fn clone(&self) -> Self {
+        ParIter {
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ParIter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.keys.fmt(f)
+    }
+}
+
+impl<'a, T: Sync> ParallelIterator for ParIter<'a, T> {
+    type Item = &'a T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.keys.drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator over the index-value pairs of a [`HashSlabSet`].
+///
+/// This `struct` is created by the [`HashSlabSet::par_iter_full`] method.
+/// See its documentation for more.
+pub struct ParIterFull<'a, T> {
+    iter_full: map::ParIterFull<'a, T, ()>,
+}
+
+impl<'a, T> ParIterFull<'a, T> {
+    fn new(iter_full: map::ParIterFull<'a, T, ()>) -> Self {
+        Self { iter_full }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<T> Clone for ParIterFull<'_, T> {
+    fn clone(&self) -> Self {
+        ParIterFull {
+            iter_full: self.iter_full.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ParIterFull<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter_full.fmt(f)
+    }
+}
+
+impl<'a, T: Sync> ParallelIterator for ParIterFull<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.iter_full
+            .map(|(index, value, _)| (index, value))
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A draining parallel iterator over the values of a [`HashSlabSet`].
+///
+/// This `struct` is created by the [`HashSlabSet::par_drain`] method.
+/// See its documentation for more.
+pub struct ParDrain<T> {
+    drain: map::ParDrain<T, ()>,
+}
+
+impl<T> ParDrain<T> {
+    fn new(drain: map::ParDrain<T, ()>) -> Self {
+        Self { drain }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ParDrain<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.drain.fmt(f)
+    }
+}
+
+impl<T: Send> ParallelIterator for ParDrain<T> {
+    type Item = T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.drain.map(|(value, _)| value).drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.drain.opt_len()
+    }
+}
+
+/// An owning parallel iterator over the values of a [`HashSlabSet`].
+///
+/// This `struct` is created by the `into_par_iter` method on [`HashSlabSet`]
+/// (provided by the [`IntoParallelIterator`] trait).
+pub struct IntoParIter<T> {
+    iter: map::IntoParIter<T, ()>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for IntoParIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoParIter").finish_non_exhaustive()
+    }
+}
+
+impl<T: Send> ParallelIterator for IntoParIter<T> {
+    type Item = T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.iter.map(|(value, _)| value).drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.iter.opt_len()
+    }
+}
+
+impl<T: Send, S> IntoParallelIterator for HashSlabSet<T, S> {
+    type Item = T;
+    type Iter = IntoParIter<T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        IntoParIter {
+            iter: self.map.into_par_iter(),
+        }
+    }
+}
+
+impl<'a, T: Sync, S> IntoParallelIterator for &'a HashSlabSet<T, S> {
+    type Item = &'a T;
+    type Iter = ParIter<'a, T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<T, S> FromParallelIterator<T> for HashSlabSet<T, S>
+where
+    T: Eq + Hash + Send,
+    S: BuildHasher + Default,
+{
+    /// Collects the values of a parallel iterator into a set.
+    ///
+    /// The values are produced in parallel, but inserted sequentially.
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        let mut set = HashSlabSet::default();
+        set.par_extend(par_iter);
+        set
+    }
+}
+
+impl<T, S> ParallelExtend<T> for HashSlabSet<T, S>
+where
+    T: Eq + Hash + Send,
+    S: BuildHasher,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        self.map
+            .par_extend(par_iter.into_par_iter().map(|value| (value, ())));
+    }
+}
+
+impl<'a, T, S> ParallelExtend<&'a T> for HashSlabSet<T, S>
+where
+    T: Copy + Eq + Hash + Send + Sync,
+    S: BuildHasher,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = &'a T>,
+    {
+        self.map
+            .par_extend(par_iter.into_par_iter().map(|&value| (value, ())));
+    }
+}
+
+/// A parallel iterator producing the difference of two [`HashSlabSet`]s.
+///
+/// This `struct` is created by the [`HashSlabSet::par_difference`] method.
+/// See its documentation for more.
+pub struct ParDifference<'a, T, S1, S2> {
+    a: &'a HashSlabSet<T, S1>,
+    b: &'a HashSlabSet<T, S2>,
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<T, S1, S2> Clone for ParDifference<'_, T, S1, S2> {
+    fn clone(&self) -> Self {
+        ParDifference { ..*self }
+    }
+}
+
+impl<T, S1, S2> fmt::Debug for ParDifference<'_, T, S1, S2>
+where
+    T: fmt::Debug + Eq + Hash,
+    S1: BuildHasher,
+    S2: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.a.difference(self.b)).finish()
+    }
+}
+
+impl<'a, T, S1, S2> ParallelIterator for ParDifference<'a, T, S1, S2>
+where
+    T: Hash + Eq + Sync,
+    S1: BuildHasher + Sync,
+    S2: BuildHasher + Sync,
+{
+    type Item = &'a T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let Self { a, b } = self;
+        a.par_iter()
+            .filter(move |&value| !b.contains(value))
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator producing the symmetric difference of two [`HashSlabSet`]s.
+///
+/// This `struct` is created by the [`HashSlabSet::par_symmetric_difference`]
+/// method. See its documentation for more.
+pub struct ParSymmetricDifference<'a, T, S1, S2> {
+    a: &'a HashSlabSet<T, S1>,
+    b: &'a HashSlabSet<T, S2>,
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<T, S1, S2> Clone for ParSymmetricDifference<'_, T, S1, S2> {
+    fn clone(&self) -> Self {
+        ParSymmetricDifference { ..*self }
+    }
+}
+
+impl<T, S1, S2> fmt::Debug for ParSymmetricDifference<'_, T, S1, S2>
+where
+    T: fmt::Debug + Eq + Hash,
+    S1: BuildHasher,
+    S2: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.a.symmetric_difference(self.b))
+            .finish()
+    }
+}
+
+impl<'a, T, S1, S2> ParallelIterator for ParSymmetricDifference<'a, T, S1, S2>
+where
+    T: Hash + Eq + Sync,
+    S1: BuildHasher + Sync,
+    S2: BuildHasher + Sync,
+{
+    type Item = &'a T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let Self { a, b } = self;
+        a.par_difference(b)
+            .chain(b.par_difference(a))
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator producing the intersection of two [`HashSlabSet`]s.
+///
+/// This `struct` is created by the [`HashSlabSet::par_intersection`] method.
+/// See its documentation for more.
+pub struct ParIntersection<'a, T, S1, S2> {
+    a: &'a HashSlabSet<T, S1>,
+    b: &'a HashSlabSet<T, S2>,
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<T, S1, S2> Clone for ParIntersection<'_, T, S1, S2> {
+    fn clone(&self) -> Self {
+        ParIntersection { ..*self }
+    }
+}
+
+impl<T, S1, S2> fmt::Debug for ParIntersection<'_, T, S1, S2>
+where
+    T: fmt::Debug + Eq + Hash,
+    S1: BuildHasher,
+    S2: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.a.intersection(self.b)).finish()
+    }
+}
+
+impl<'a, T, S1, S2> ParallelIterator for ParIntersection<'a, T, S1, S2>
+where
+    T: Hash + Eq + Sync,
+    S1: BuildHasher + Sync,
+    S2: BuildHasher + Sync,
+{
+    type Item = &'a T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let Self { a, b } = self;
+        a.par_iter()
+            .filter(move |&value| b.contains(value))
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator producing the union of two [`HashSlabSet`]s.
+///
+/// This `struct` is created by the [`HashSlabSet::par_union`] method.
+/// See its documentation for more.
+pub struct ParUnion<'a, T, S1, S2> {
+    a: &'a HashSlabSet<T, S1>,
+    b: &'a HashSlabSet<T, S2>,
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<T, S1, S2> Clone for ParUnion<'_, T, S1, S2> {
+    fn clone(&self) -> Self {
+        ParUnion { ..*self }
+    }
+}
+
+impl<T, S1, S2> fmt::Debug for ParUnion<'_, T, S1, S2>
+where
+    T: fmt::Debug + Eq + Hash,
+    S1: BuildHasher,
+    S2: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.a.union(self.b)).finish()
+    }
+}
+
+impl<'a, T, S1, S2> ParallelIterator for ParUnion<'a, T, S1, S2>
+where
+    T: Hash + Eq + Sync,
+    S1: BuildHasher + Sync,
+    S2: BuildHasher + Sync,
+{
+    type Item = &'a T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let Self { a, b } = self;
+        a.par_iter()
+            .chain(b.par_difference(a))
+            .drive_unindexed(consumer)
+    }
+}
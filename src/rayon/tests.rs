@@ -0,0 +1,93 @@
+use super::*;
+use crate::{HashSlabMap, HashSlabSet};
+use std::{vec, vec::Vec};
+
+fn sorted<T: Ord>(mut vec: Vec<T>) -> Vec<T> {
+    vec.sort_unstable();
+    vec
+}
+
+#[test]
+fn map_par_iter() {
+    let mut map: HashSlabMap<u32, u32> = (0..1000).map(|i| (i, i * 10)).collect();
+    map.remove(&500);
+
+    let full: Vec<_> = map.par_iter_full().map(|(i, &k, &v)| (i, k, v)).collect();
+    let expected: Vec<_> = map.iter_full().map(|(i, &k, &v)| (i, k, v)).collect();
+    assert_eq!(sorted(full), sorted(expected));
+
+    assert_eq!(map.par_iter().filter(|(k, v)| **k * 10 == **v).count(), 999);
+    assert_eq!(map.par_keys().copied().max(), Some(999));
+    assert_eq!(map.par_values().copied().sum::<u32>(), 4_995_000 - 5000);
+    assert_eq!((&map).into_par_iter().count(), 999);
+}
+
+#[test]
+fn map_par_iter_mut() {
+    let mut map: HashSlabMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+    map.par_iter_mut().for_each(|(k, v)| *v += k);
+    map.par_values_mut().for_each(|v| *v += 1);
+    assert!(map.iter().all(|(k, v)| *v == 2 * k + 1));
+    (&mut map).into_par_iter().for_each(|(_, v)| *v = 0);
+    assert!(map.values().all(|&v| v == 0));
+}
+
+#[test]
+fn map_par_collect_and_drain() {
+    let mut map: HashSlabMap<u32, u32> =
+        (0..1000u32).into_par_iter().map(|i| (i % 100, i)).collect();
+    assert_eq!(map.len(), 100);
+    map.par_extend([(1000, 1), (1001, 2)].par_iter().map(|&(k, v)| (k, v)));
+    map.par_extend(vec![(&2000, &3)]);
+    assert_eq!(map.len(), 103);
+    assert_eq!(map[&2000], 3);
+
+    let drained: Vec<_> = map.par_drain().map(|(k, _)| k).collect();
+    assert_eq!(drained.len(), 103);
+    assert!(map.is_empty());
+
+    let map: HashSlabMap<u32, u32> = (0..10).map(|i| (i, i)).collect();
+    let keys = sorted(map.into_par_iter().map(|(k, _)| k).collect());
+    assert_eq!(keys, (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn set_par_ops() {
+    let a: HashSlabSet<u32> = (0..100).into_par_iter().collect();
+    let b: HashSlabSet<u32> = (50..150).collect();
+
+    let check = |par: Vec<&u32>, seq: Vec<&u32>| assert_eq!(sorted(par), sorted(seq));
+    check(a.par_difference(&b).collect(), a.difference(&b).collect());
+    check(
+        a.par_symmetric_difference(&b).collect(),
+        a.symmetric_difference(&b).collect(),
+    );
+    check(
+        a.par_intersection(&b).collect(),
+        a.intersection(&b).collect(),
+    );
+    check(a.par_union(&b).collect(), a.union(&b).collect());
+
+    assert!(!a.par_is_disjoint(&b));
+    let c: HashSlabSet<u32> = (60..70).collect();
+    assert!(c.par_is_subset(&a) && c.par_is_subset(&b));
+    assert!(a.par_is_superset(&c));
+    assert!(!a.par_is_subset(&b));
+
+    let full: Vec<_> = a.par_iter_full().map(|(i, &v)| (i, v)).collect();
+    let expected = a.iter_full().map(|(i, &v)| (i, v)).collect();
+    assert_eq!(sorted(full), sorted(expected));
+}
+
+#[test]
+fn set_par_extend_and_drain() {
+    let mut set = HashSlabSet::new();
+    set.par_extend((0..100u32).into_par_iter().map(|i| i % 10));
+    set.par_extend([&10, &11].into_par_iter());
+    assert_eq!(set.len(), 12);
+
+    let mut drained: Vec<_> = set.par_drain().collect();
+    drained.sort_unstable();
+    assert_eq!(drained, (0..12).collect::<Vec<_>>());
+    assert!(set.is_empty());
+}
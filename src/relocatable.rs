@@ -0,0 +1,499 @@
+//! A fixed-capacity [`HashSlabMap`](crate::HashSlabMap) stored entirely in a
+//! caller-provided buffer
+//!
+//! A [`RelocatableHashSlabMap`] keeps its header, entries and hash table in a
+//! byte buffer, and refers to entries by offsets only, so the buffer can be
+//! copied, moved, or placed in shared memory and reopened by another process
+//! with [`open`](RelocatableHashSlabMap::open). Keys and values are
+//! [plain-old-data](Pod) stored by their bytes, and indices are allocated
+//! like in a `HashSlabMap`: the last removed index is reused first.
+//!
+//! The map does not synchronize accesses to the buffer. Processes sharing it
+//! must ensure that a writer has exclusive access, e.g. with a lock stored
+//! next to the map.
+//!
+//! # Layout
+//!
+//! The buffer starts with a 48-byte header, with integers in little-endian
+//! byte order: the magic `HSRL`, the format version as a `u16`, the byte
+//! order of keys and values as a `u8`, a reserved byte, the sizes of a key
+//! and of a value as `u32`s, then the capacity, the number of entries, the
+//! next vacant index and the end of the used indices as `u64`s.
+//!
+//! It is followed by one slot per index up to the capacity, each a `u64` link
+//! to the next vacant index, or `u64::MAX` if the slot is occupied, then the
+//! bytes of the key and of the value. The buffer ends with the buckets of an
+//! open-addressing hash table, twice the capacity rounded up to a power of
+//! two, as `u64` indices plus one, or zero for an empty bucket. Keys are
+//! hashed with the same function as in [`frozen`](crate::frozen) maps.
+use core::{fmt, iter::FusedIterator, marker::PhantomData, mem, mem::MaybeUninit};
+
+use thiserror::Error;
+
+use crate::frozen::{bucket_of, bytes_of, hash_bytes, read, Pod, ENDIAN};
+
+#[cfg(test)]
+mod tests;
+
+const MAGIC: &[u8; 4] = b"HSRL";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 48;
+
+const CAPACITY: usize = 16;
+const LEN: usize = 24;
+const NEXT: usize = 32;
+const END: usize = 40;
+
+const OCCUPIED: u64 = u64::MAX;
+
+/// The error type for initializing or opening a [`RelocatableHashSlabMap`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocatableError {
+    #[error("not a relocatable hashslab map")]
+    BadMagic,
+    #[error("unsupported relocatable map version {0}")]
+    UnsupportedVersion(u16),
+    #[error("relocatable map was written with another byte order")]
+    ByteOrder,
+    #[error("relocatable map has {key_size}-byte keys and {value_size}-byte values")]
+    TypeMismatch { key_size: u32, value_size: u32 },
+    #[error("relocatable map header is inconsistent")]
+    InvalidHeader,
+    #[error("buffer of {found} bytes is too small, {needed} bytes are needed")]
+    BufferTooSmall { needed: usize, found: usize },
+}
+
+/// The error returned when inserting a new key in a full
+/// [`RelocatableHashSlabMap`], with the rejected key-value pair.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("RelocatableHashSlabMap: capacity exceeded")]
+pub struct CapacityError<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+/// A fixed-capacity map of [plain-old-data](Pod) keys and values stored in a
+/// borrowed byte buffer. See the [module documentation](self).
+///
+/// The buffer needs no particular alignment.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::RelocatableHashSlabMap;
+/// type Map<'a> = RelocatableHashSlabMap<'a, u32, u64>;
+///
+/// let mut buffer = vec![0; Map::size_for(16)];
+/// let mut map = Map::init(&mut buffer, 16).unwrap();
+/// map.insert(1, 10).unwrap();
+/// map.insert(2, 20).unwrap();
+/// map.remove(&1);
+///
+/// // E.g. in another process sharing the buffer
+/// let mut copy = buffer.clone();
+/// let mut map = Map::open(&mut copy).unwrap();
+/// assert_eq!(map.get(&2), Some(20));
+/// assert_eq!(map.insert_full(3, 30), Ok((0, None)));
+/// ```
+pub struct RelocatableHashSlabMap<'a, K, V> {
+    buf: &'a mut [u8],
+    capacity: usize,
+    buckets: usize,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl<'a, K: Pod, V: Pod> RelocatableHashSlabMap<'a, K, V> {
+    const SLOT: usize = 8 + mem::size_of::<K>() + mem::size_of::<V>();
+
+    // The number of buckets and the size of a buffer for `capacity` entries
+    fn layout(capacity: usize) -> Option<(usize, usize)> {
+        let buckets = capacity.checked_mul(2)?.checked_next_power_of_two()?;
+        let size = capacity
+            .checked_mul(Self::SLOT)?
+            .checked_add(buckets.checked_mul(8)?)?
+            .checked_add(HEADER_LEN)?;
+        Some((buckets, size))
+    }
+
+    /// Return the size of a buffer holding up to `capacity` entries.
+    ///
+    /// ***Panics***
+    ///
+    /// Panics if the size overflows `usize`.
+    pub fn size_for(capacity: usize) -> usize {
+        Self::layout(capacity)
+            .expect("RelocatableHashSlabMap: capacity overflow")
+            .1
+    }
+
+    /// Create an empty map with room for `capacity` entries at the start of
+    /// `buf`, overwriting its content.
+    pub fn init(buf: &'a mut [u8], capacity: usize) -> Result<Self, RelocatableError> {
+        let found = buf.len();
+        let (buckets, needed) = Self::layout(capacity).ok_or(RelocatableError::BufferTooSmall {
+            needed: usize::MAX,
+            found,
+        })?;
+        let buf = buf
+            .get_mut(..needed)
+            .ok_or(RelocatableError::BufferTooSmall { needed, found })?;
+        buf.fill(0);
+        buf[..4].copy_from_slice(MAGIC);
+        buf[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        buf[6] = ENDIAN;
+        buf[8..12].copy_from_slice(&(mem::size_of::<K>() as u32).to_le_bytes());
+        buf[12..16].copy_from_slice(&(mem::size_of::<V>() as u32).to_le_bytes());
+        let mut map = Self {
+            buf,
+            capacity,
+            buckets,
+            marker: PhantomData,
+        };
+        map.set(CAPACITY, capacity);
+        Ok(map)
+    }
+
+    /// Create an empty map with room for `capacity` entries at the start of
+    /// an uninitialized buffer, e.g. a fresh shared memory mapping.
+    pub fn init_uninit(
+        buf: &'a mut [MaybeUninit<u8>],
+        capacity: usize,
+    ) -> Result<Self, RelocatableError> {
+        buf.iter_mut().for_each(|byte| {
+            byte.write(0);
+        });
+        // SAFETY: every byte was initialized, and `MaybeUninit<u8>` has the
+        // layout of `u8`
+        let buf = unsafe { &mut *(buf as *mut [MaybeUninit<u8>] as *mut [u8]) };
+        Self::init(buf, capacity)
+    }
+
+    /// Open a map previously created with [`init`](Self::init) in `buf`.
+    ///
+    /// The header is checked, but not the entries: a map corrupted by
+    /// another writer returns wrong results or panics, without undefined
+    /// behavior.
+    pub fn open(buf: &'a mut [u8]) -> Result<Self, RelocatableError> {
+        let found = buf.len();
+        let header = buf
+            .get(..HEADER_LEN)
+            .ok_or(RelocatableError::BufferTooSmall {
+                needed: HEADER_LEN,
+                found,
+            })?;
+        if &header[..4] != MAGIC {
+            return Err(RelocatableError::BadMagic);
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != VERSION {
+            return Err(RelocatableError::UnsupportedVersion(version));
+        }
+        if header[6] != ENDIAN {
+            return Err(RelocatableError::ByteOrder);
+        }
+        let key_size = u32::from_le_bytes(read(&header[8..]));
+        let value_size = u32::from_le_bytes(read(&header[12..]));
+        if (key_size as usize, value_size as usize) != (mem::size_of::<K>(), mem::size_of::<V>()) {
+            return Err(RelocatableError::TypeMismatch {
+                key_size,
+                value_size,
+            });
+        }
+
+        let field = |at: usize| usize::try_from(u64::from_le_bytes(read(&header[at..])));
+        let capacity = field(CAPACITY).map_err(|_| RelocatableError::InvalidHeader)?;
+        let (buckets, needed) = Self::layout(capacity).ok_or(RelocatableError::InvalidHeader)?;
+        let (len, next, end) = match (field(LEN), field(NEXT), field(END)) {
+            (Ok(len), Ok(next), Ok(end)) => (len, next, end),
+            _ => return Err(RelocatableError::InvalidHeader),
+        };
+        if len > end || next > end || end > capacity {
+            return Err(RelocatableError::InvalidHeader);
+        }
+        let buf = buf
+            .get_mut(..needed)
+            .ok_or(RelocatableError::BufferTooSmall { needed, found })?;
+        Ok(Self {
+            buf,
+            capacity,
+            buckets,
+            marker: PhantomData,
+        })
+    }
+
+    fn get_u64(&self, at: usize) -> u64 {
+        u64::from_le_bytes(read(&self.buf[at..]))
+    }
+
+    fn set_u64(&mut self, at: usize, value: u64) {
+        self.buf[at..at + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn field(&self, at: usize) -> usize {
+        self.get_u64(at) as usize
+    }
+
+    fn set(&mut self, at: usize, value: usize) {
+        self.set_u64(at, value as u64);
+    }
+
+    fn slot(&self, index: usize) -> usize {
+        HEADER_LEN + index * Self::SLOT
+    }
+
+    fn bucket(&self, bucket: usize) -> usize {
+        HEADER_LEN + self.capacity * Self::SLOT + bucket * 8
+    }
+
+    fn key_bytes(&self, index: usize) -> &[u8] {
+        let at = self.slot(index) + 8;
+        &self.buf[at..at + mem::size_of::<K>()]
+    }
+
+    // The bucket holding `key`, or the empty bucket ending its probe sequence
+    fn find(&self, key: &[u8]) -> (usize, Option<usize>) {
+        let start = bucket_of(hash_bytes(key), self.buckets as u64);
+        for probe in 0..self.buckets {
+            let bucket = (start + probe) % self.buckets;
+            match self.get_u64(self.bucket(bucket)) {
+                0 => return (bucket, None),
+                entry => {
+                    let index = (entry - 1) as usize;
+                    if index < self.capacity && self.key_bytes(index) == key {
+                        return (bucket, Some(index));
+                    }
+                }
+            }
+        }
+        panic!("RelocatableHashSlabMap: corrupted hash table")
+    }
+
+    /// Return the number of entries the map can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Return the number of key-value pairs in the map.
+    pub fn len(&self) -> usize {
+        self.field(LEN)
+    }
+
+    /// Returns true if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the index the next inserted key will get, unless it is already
+    /// present.
+    pub fn vacant_index(&self) -> usize {
+        self.field(NEXT)
+    }
+
+    /// Get a key-value pair by index.
+    pub fn get_index(&self, index: usize) -> Option<(K, V)> {
+        if index >= self.field(END) || self.get_u64(self.slot(index)) != OCCUPIED {
+            return None;
+        }
+        let at = self.slot(index) + 8;
+        let key = read(&self.buf[at..]);
+        let value = read(&self.buf[at + mem::size_of::<K>()..]);
+        Some((key, value))
+    }
+
+    /// Return `true` if the index is occupied.
+    pub fn contains_index(&self, index: usize) -> bool {
+        self.get_index(index).is_some()
+    }
+
+    /// Return the index of `key`, if it is present.
+    pub fn get_index_of(&self, key: &K) -> Option<usize> {
+        self.find(bytes_of(key)).1
+    }
+
+    /// Return the index, key and value stored for `key`, if it is present.
+    pub fn get_full(&self, key: &K) -> Option<(usize, K, V)> {
+        let index = self.get_index_of(key)?;
+        let (key, value) = self.get_index(index)?;
+        Some((index, key, value))
+    }
+
+    /// Return the value stored for `key`, if it is present.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Return `true` if an equivalent to `key` exists in the map.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get_index_of(key).is_some()
+    }
+
+    /// Insert a key-value pair in the map.
+    ///
+    /// If an equivalent key already exists, its value is replaced and the old
+    /// value is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the pair if the key is new and the map is full.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, CapacityError<K, V>> {
+        self.insert_full(key, value).map(|(_, old)| old)
+    }
+
+    /// Insert a key-value pair in the map, and get its index together with
+    /// the old value if an equivalent key already existed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the pair if the key is new and the map is full.
+    pub fn insert_full(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(usize, Option<V>), CapacityError<K, V>> {
+        let (bucket, found) = self.find(bytes_of(&key));
+        if let Some(index) = found {
+            let at = self.slot(index) + 8 + mem::size_of::<K>();
+            let old = read(&self.buf[at..]);
+            self.buf[at..at + mem::size_of::<V>()].copy_from_slice(bytes_of(&value));
+            return Ok((index, Some(old)));
+        }
+        let len = self.len();
+        if len == self.capacity {
+            return Err(CapacityError { key, value });
+        }
+
+        let index = self.field(NEXT);
+        let end = self.field(END);
+        if index == end {
+            self.set(END, end + 1);
+            self.set(NEXT, end + 1);
+        } else {
+            let next = self.get_u64(self.slot(index));
+            assert!(
+                next != OCCUPIED && next as usize <= end,
+                "RelocatableHashSlabMap: corrupted free list"
+            );
+            self.set_u64(NEXT, next);
+        }
+        let at = self.slot(index);
+        self.set_u64(at, OCCUPIED);
+        let value_at = at + 8 + mem::size_of::<K>();
+        self.buf[at + 8..value_at].copy_from_slice(bytes_of(&key));
+        self.buf[value_at..at + Self::SLOT].copy_from_slice(bytes_of(&value));
+        let at = self.bucket(bucket);
+        self.set(at, index + 1);
+        self.set(LEN, len + 1);
+        Ok((index, None))
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return its value.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return it and the
+    /// index it had.
+    pub fn remove_full(&mut self, key: &K) -> Option<(usize, K, V)> {
+        let index = self.get_index_of(key)?;
+        let (key, value) = self.remove_index(index)?;
+        Some((index, key, value))
+    }
+
+    /// Remove the key-value pair by index.
+    pub fn remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let (key, value) = self.get_index(index)?;
+        let (mut hole, found) = self.find(bytes_of(&key));
+        assert_eq!(
+            found,
+            Some(index),
+            "RelocatableHashSlabMap: corrupted hash table"
+        );
+
+        // Shift back the following entries of the probe sequence which may
+        // live in the freed bucket, so no probe sequence is broken
+        let mut bucket = hole;
+        for _ in 1..self.buckets {
+            bucket = (bucket + 1) % self.buckets;
+            let entry = self.get_u64(self.bucket(bucket));
+            if entry == 0 {
+                break;
+            }
+            let ideal = bucket_of(
+                hash_bytes(self.key_bytes((entry - 1) as usize)),
+                self.buckets as u64,
+            );
+            let distance = |from: usize| (bucket + self.buckets - from) % self.buckets;
+            if distance(ideal) >= distance(hole) {
+                let at = self.bucket(hole);
+                self.set_u64(at, entry);
+                hole = bucket;
+            }
+        }
+        let at = self.bucket(hole);
+        self.set_u64(at, 0);
+
+        let at = self.slot(index);
+        let next = self.get_u64(NEXT);
+        self.set_u64(at, next);
+        self.set(NEXT, index);
+        self.set(LEN, self.len() - 1);
+        Some((key, value))
+    }
+
+    /// Remove all key-value pairs, keeping the capacity.
+    pub fn clear(&mut self) {
+        let start = self.slot(0);
+        self.buf[start..].fill(0);
+        for field in [LEN, NEXT, END] {
+            self.set(field, 0);
+        }
+    }
+
+    /// An iterator visiting all key-value pairs with their indices, in index
+    /// order.
+    pub fn iter_full(&self) -> IterFull<'_, 'a, K, V> {
+        IterFull {
+            map: self,
+            index: 0,
+            remaining: self.len(),
+        }
+    }
+}
+
+impl<K: Pod + fmt::Debug, V: Pod + fmt::Debug> fmt::Debug for RelocatableHashSlabMap<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter_full().map(|(i, k, v)| ((i, k), v)))
+            .finish()
+    }
+}
+
+/// An iterator over the entries of a [`RelocatableHashSlabMap`] with their
+/// indices.
+pub struct IterFull<'b, 'a, K, V> {
+    map: &'b RelocatableHashSlabMap<'a, K, V>,
+    index: usize,
+    remaining: usize,
+}
+
+impl<K: Pod, V: Pod> Iterator for IterFull<'_, '_, K, V> {
+    type Item = (usize, K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 && self.index < self.map.capacity {
+            let index = self.index;
+            self.index += 1;
+            if let Some((key, value)) = self.map.get_index(index) {
+                self.remaining -= 1;
+                return Some((index, key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl<K: Pod, V: Pod> FusedIterator for IterFull<'_, '_, K, V> {}
@@ -0,0 +1,90 @@
+use super::*;
+use crate::HashSlabMap;
+use std::{vec, vec::Vec};
+
+type Map<'a> = RelocatableHashSlabMap<'a, u64, [u16; 3]>;
+
+#[test]
+fn follows_hashslab_map() {
+    let mut buffer = vec![0xAA; Map::size_for(64) + 5];
+    let mut map = Map::init(&mut buffer, 64).unwrap();
+    let mut model = HashSlabMap::new();
+
+    // Colliding probe sequences are exercised by the small table
+    for round in 0..4u64 {
+        for i in 0..64u64 {
+            let key = i * 31 + round;
+            let value = [i as u16, round as u16, 7];
+            if model.len() < 64 {
+                assert_eq!(
+                    map.insert_full(key, value).ok(),
+                    Some(model.insert_full(key, value))
+                );
+            }
+        }
+        for i in (0..64u64).filter(|i| (i + round) % 3 == 0) {
+            let key = i * 31 + round;
+            assert_eq!(map.remove_full(&key), model.remove_full(&key));
+        }
+        assert_eq!(map.len(), model.len());
+        assert_eq!(map.vacant_index(), model.vacant_index());
+        for (index, key, value) in model.iter_full() {
+            assert_eq!(map.get_full(key), Some((index, *key, *value)));
+        }
+    }
+
+    let full = (0..).map(|i| (1_000_000 + i, [0; 3]));
+    let mut rejected = None;
+    for (key, value) in full {
+        if let Err(err) = map.insert(key, value) {
+            rejected = Some(err);
+            break;
+        }
+    }
+    assert_eq!(map.len(), 64);
+    assert_eq!(
+        rejected.map(|err| err.key),
+        Some(1_000_000 + 64 - model.len() as u64)
+    );
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.iter_full().count(), 0);
+    assert_eq!(map.insert_full(5, [1; 3]), Ok((0, None)));
+}
+
+#[test]
+fn reopens_a_moved_buffer() {
+    let mut buffer = vec![MaybeUninit::uninit(); Map::size_for(8)];
+    let mut map = Map::init_uninit(&mut buffer, 8).unwrap();
+    for i in 0..8 {
+        map.insert(i, [i as u16; 3]).unwrap();
+    }
+    map.remove(&2);
+    map.remove(&5);
+    let entries: Vec<_> = map.iter_full().collect();
+
+    // SAFETY: the buffer was initialized by `init_uninit`
+    let mut moved: Vec<u8> = buffer.iter().map(|b| unsafe { b.assume_init() }).collect();
+    moved.insert(0, 0);
+    let mut map = Map::open(&mut moved[1..]).unwrap();
+    assert_eq!(map.iter_full().collect::<Vec<_>>(), entries);
+    assert_eq!(map.insert_full(9, [9; 3]), Ok((5, None)));
+    assert_eq!(map.insert_full(10, [10; 3]), Ok((2, None)));
+
+    assert_eq!(
+        RelocatableHashSlabMap::<u32, u32>::open(&mut moved[1..]).unwrap_err(),
+        RelocatableError::TypeMismatch {
+            key_size: 8,
+            value_size: 6
+        }
+    );
+    assert!(matches!(
+        Map::open(&mut moved[1..100]),
+        Err(RelocatableError::BufferTooSmall { .. })
+    ));
+    assert_eq!(
+        Map::open(&mut moved).unwrap_err(),
+        RelocatableError::BadMagic
+    );
+}
@@ -0,0 +1,426 @@
+//! Zero-copy archived [`HashSlabMap`] and [`HashSlabSet`] with [rkyv].
+//!
+//! Maps and sets implement [`Archive`] and [`Serialize`], so they can be
+//! written with [`rkyv::to_bytes`](::rkyv::to_bytes) and read back in place
+//! with [`rkyv::access`](::rkyv::access). The archived forms support lookups
+//! by key and by index without allocating, and keep the indices of the
+//! original entries.
+//!
+//! Keys are hashed with [`FxHasher64`], which does not depend on the
+//! platform, so a lookup key must hash like the original key. That holds for
+//! the archived counterparts of primitives and strings, e.g. `&str` can be
+//! looked up in an archived `HashSlabMap<String, _>`.
+//!
+//! # Examples
+//!
+//! ```
+//! use hashslab::{rkyv::ArchivedHashSlabMap, HashSlabMap};
+//! use rkyv::{rancor::Error, string::ArchivedString, Archived};
+//!
+//! let mut map = HashSlabMap::new();
+//! map.insert("a".to_string(), 1u32);
+//! map.insert("b".to_string(), 2);
+//! map.remove("a");
+//!
+//! let bytes = rkyv::to_bytes::<Error>(&map).unwrap();
+//! let archived =
+//!     rkyv::access::<ArchivedHashSlabMap<ArchivedString, Archived<u32>>, Error>(&bytes).unwrap();
+//! assert_eq!(archived.get("b").map(|v| v.to_native()), Some(2));
+//! assert_eq!(archived.get_index_of("b"), Some(1));
+//! assert!(archived.get_index(0).is_none());
+//! ```
+//!
+//! [rkyv]: https://docs.rs/rkyv/0.8
+use alloc::{vec, vec::Vec};
+use core::{fmt, hash::Hash, iter::FusedIterator, slice};
+
+use ::rkyv::{
+    bytecheck::CheckBytes,
+    hash::{hash_value, FxHasher64},
+    munge::munge,
+    primitive::ArchivedUsize,
+    rancor::Fallible,
+    ser::{Allocator, Writer},
+    vec::{ArchivedVec, VecResolver},
+    Archive, Place, Portable, Serialize,
+};
+
+use crate::{HashSlabMap, HashSlabSet};
+
+#[cfg(test)]
+mod tests;
+
+/// An archived entry of a [`HashSlabMap`].
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = ::rkyv::bytecheck)]
+#[rkyv(crate = ::rkyv)]
+#[repr(C)]
+pub struct ArchivedEntry<K, V> {
+    index: ArchivedUsize,
+    key: K,
+    value: V,
+}
+
+// An entry to serialize, borrowed from the map
+struct EntryRef<'a, K, V> {
+    index: usize,
+    key: &'a K,
+    value: &'a V,
+}
+
+impl<K: Archive, V: Archive> Archive for EntryRef<'_, K, V> {
+    type Archived = ArchivedEntry<K::Archived, V::Archived>;
+    type Resolver = (K::Resolver, V::Resolver);
+
+    fn resolve(&self, (key_resolver, value_resolver): Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedEntry { index, key, value } = out);
+        self.index.resolve((), index);
+        self.key.resolve(key_resolver, key);
+        self.value.resolve(value_resolver, value);
+    }
+}
+
+impl<K, V, S> Serialize<S> for EntryRef<'_, K, V>
+where
+    K: Serialize<S>,
+    V: Serialize<S>,
+    S: Fallible + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        Ok((
+            self.key.serialize(serializer)?,
+            self.value.serialize(serializer)?,
+        ))
+    }
+}
+
+// The first bucket to probe for `hash`, the same on every platform
+fn bucket_of<T>(hash: u64, buckets: &[T]) -> usize {
+    (hash % buckets.len() as u64) as usize
+}
+
+/// An archived [`HashSlabMap`].
+///
+/// The entries are stored in index order, together with a table from indices
+/// to entries and an open-addressing hash table for lookups by key.
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = ::rkyv::bytecheck)]
+#[rkyv(crate = ::rkyv)]
+#[repr(C)]
+pub struct ArchivedHashSlabMap<K, V> {
+    entries: ArchivedVec<ArchivedEntry<K, V>>,
+    // Position of the entry at each index, plus one, or zero if vacant
+    positions: ArchivedVec<ArchivedUsize>,
+    // Position of an entry, plus one, or zero for an empty bucket
+    buckets: ArchivedVec<ArchivedUsize>,
+}
+
+/// The resolver for an archived [`HashSlabMap`].
+pub struct HashSlabMapResolver {
+    entries: (usize, VecResolver),
+    positions: (usize, VecResolver),
+    buckets: (usize, VecResolver),
+}
+
+impl<K: Archive, V: Archive, S> Archive for HashSlabMap<K, V, S> {
+    type Archived = ArchivedHashSlabMap<K::Archived, V::Archived>;
+    type Resolver = HashSlabMapResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedHashSlabMap { entries, positions, buckets } = out);
+        let (len, resolver_) = resolver.entries;
+        ArchivedVec::resolve_from_len(len, resolver_, entries);
+        let (len, resolver_) = resolver.positions;
+        ArchivedVec::resolve_from_len(len, resolver_, positions);
+        let (len, resolver_) = resolver.buckets;
+        ArchivedVec::resolve_from_len(len, resolver_, buckets);
+    }
+}
+
+impl<K, V, S, Ser> Serialize<Ser> for HashSlabMap<K, V, S>
+where
+    K: Hash + Serialize<Ser>,
+    V: Serialize<Ser>,
+    Ser: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut Ser) -> Result<Self::Resolver, Ser::Error> {
+        let mut entries: Vec<_> = self
+            .iter_full()
+            .map(|(index, key, value)| EntryRef { index, key, value })
+            .collect();
+        entries.sort_unstable_by_key(|entry| entry.index);
+
+        let end = entries.last().map_or(0, |entry| entry.index + 1);
+        let mut positions = vec![0; end];
+        // At most half full, so probing always ends on an empty bucket
+        let mut buckets = vec![0; (entries.len() * 2).next_power_of_two()];
+        for (position, entry) in entries.iter().enumerate() {
+            positions[entry.index] = position + 1;
+            let mut bucket = bucket_of(hash_value::<K, FxHasher64>(entry.key), &buckets);
+            while buckets[bucket] != 0 {
+                bucket = (bucket + 1) % buckets.len();
+            }
+            buckets[bucket] = position + 1;
+        }
+
+        Ok(HashSlabMapResolver {
+            entries: (
+                entries.len(),
+                ArchivedVec::serialize_from_iter::<EntryRef<K, V>, _, _>(
+                    entries.iter(),
+                    serializer,
+                )?,
+            ),
+            positions: (
+                positions.len(),
+                ArchivedVec::serialize_from_slice(&positions, serializer)?,
+            ),
+            buckets: (
+                buckets.len(),
+                ArchivedVec::serialize_from_slice(&buckets, serializer)?,
+            ),
+        })
+    }
+}
+
+impl<K, V> ArchivedHashSlabMap<K, V> {
+    /// Return the number of key-value pairs in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Return the position of the entry equivalent to `key`.
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + ?Sized,
+        K: PartialEq<Q>,
+    {
+        let buckets = self.buckets.as_slice();
+        if buckets.is_empty() {
+            return None;
+        }
+        let start = bucket_of(hash_value::<Q, FxHasher64>(key), buckets);
+        // Bounded, as archives are not checked for empty buckets
+        (0..buckets.len())
+            .map(|probe| buckets[(start + probe) % buckets.len()].to_native() as usize)
+            .take_while(|&bucket| bucket != 0)
+            .map(|bucket| bucket - 1)
+            .find(|&position| {
+                self.entries
+                    .get(position)
+                    .is_some_and(|entry| entry.key == *key)
+            })
+    }
+
+    /// Return a reference to the value stored for `key`, if it is present.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + ?Sized,
+        K: PartialEq<Q>,
+    {
+        self.position(key)
+            .map(|position| &self.entries[position].value)
+    }
+
+    /// Return the index, key and value stored for `key`, if it is present.
+    pub fn get_full<Q>(&self, key: &Q) -> Option<(usize, &K, &V)>
+    where
+        Q: Hash + ?Sized,
+        K: PartialEq<Q>,
+    {
+        let entry = &self.entries[self.position(key)?];
+        Some((entry.index.to_native() as usize, &entry.key, &entry.value))
+    }
+
+    /// Return the index of `key`, if it is present.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + ?Sized,
+        K: PartialEq<Q>,
+    {
+        self.get_full(key).map(|(index, _, _)| index)
+    }
+
+    /// Return `true` if an equivalent to `key` exists in the map.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + ?Sized,
+        K: PartialEq<Q>,
+    {
+        self.position(key).is_some()
+    }
+
+    /// Get a key-value pair by index.
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        let position = self.positions.get(index)?.to_native() as usize;
+        let entry = self.entries.get(position.checked_sub(1)?)?;
+        Some((&entry.key, &entry.value))
+    }
+
+    /// Return `true` if the index is occupied.
+    pub fn contains_index(&self, index: usize) -> bool {
+        self.get_index(index).is_some()
+    }
+
+    /// An iterator visiting all key-value pairs with their indices, in index
+    /// order.
+    pub fn iter_full(&self) -> IterFull<'_, K, V> {
+        IterFull {
+            iter: self.entries.iter(),
+        }
+    }
+
+    /// An iterator visiting all key-value pairs, in index order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            iter: self.entries.iter(),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for ArchivedHashSlabMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter_full().map(|(i, k, v)| ((i, k), v)))
+            .finish()
+    }
+}
+
+/// An iterator over the entries of an [`ArchivedHashSlabMap`] with their
+/// indices.
+pub struct IterFull<'a, K, V> {
+    iter: slice::Iter<'a, ArchivedEntry<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterFull<'a, K, V> {
+    type Item = (usize, &'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next()
+            .map(|entry| (entry.index.to_native() as usize, &entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterFull<'_, K, V> {}
+impl<K, V> FusedIterator for IterFull<'_, K, V> {}
+
+/// An iterator over the entries of an [`ArchivedHashSlabMap`].
+pub struct Iter<'a, K, V> {
+    iter: slice::Iter<'a, ArchivedEntry<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|entry| (&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+/// An archived [`HashSlabSet`].
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = ::rkyv::bytecheck)]
+#[rkyv(crate = ::rkyv)]
+#[repr(transparent)]
+pub struct ArchivedHashSlabSet<T> {
+    map: ArchivedHashSlabMap<T, ()>,
+}
+
+impl<T: Archive, S> Archive for HashSlabSet<T, S> {
+    type Archived = ArchivedHashSlabSet<T::Archived>;
+    type Resolver = HashSlabMapResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedHashSlabSet { map } = out);
+        self.map.resolve(resolver, map);
+    }
+}
+
+impl<T, S, Ser> Serialize<Ser> for HashSlabSet<T, S>
+where
+    T: Hash + Serialize<Ser>,
+    Ser: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut Ser) -> Result<Self::Resolver, Ser::Error> {
+        self.map.serialize(serializer)
+    }
+}
+
+impl<T> ArchivedHashSlabSet<T> {
+    /// Return the number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the set contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Return `true` if an equivalent to `value` exists in the set.
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        Q: Hash + ?Sized,
+        T: PartialEq<Q>,
+    {
+        self.map.contains_key(value)
+    }
+
+    /// Return a reference to the element equivalent to `value`, if it is
+    /// present.
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        Q: Hash + ?Sized,
+        T: PartialEq<Q>,
+    {
+        self.map.get_full(value).map(|(_, value, _)| value)
+    }
+
+    /// Return the index of the element equivalent to `value`, if it is
+    /// present.
+    pub fn get_index_of<Q>(&self, value: &Q) -> Option<usize>
+    where
+        Q: Hash + ?Sized,
+        T: PartialEq<Q>,
+    {
+        self.map.get_index_of(value)
+    }
+
+    /// Get an element by index.
+    pub fn get_index(&self, index: usize) -> Option<&T> {
+        self.map.get_index(index).map(|(value, _)| value)
+    }
+
+    /// An iterator visiting all elements with their indices, in index order.
+    pub fn iter_full(&self) -> impl ExactSizeIterator<Item = (usize, &T)> + '_ {
+        self.map.iter_full().map(|(index, value, _)| (index, value))
+    }
+
+    /// An iterator visiting all elements, in index order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &T> + '_ {
+        self.map.iter().map(|(value, _)| value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArchivedHashSlabSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter_full()).finish()
+    }
+}
@@ -0,0 +1,50 @@
+use super::*;
+use ::rkyv::{rancor::Error, string::ArchivedString, Archived};
+use std::{string::String, string::ToString, vec::Vec};
+
+#[test]
+fn archived_map() {
+    let mut map: HashSlabMap<String, u64> = (0..100u64).map(|i| (i.to_string(), i)).collect();
+    map.retain(|_, v| *v % 7 != 3);
+    let bytes = ::rkyv::to_bytes::<Error>(&map).unwrap();
+    let archived =
+        ::rkyv::access::<ArchivedHashSlabMap<ArchivedString, Archived<u64>>, Error>(&bytes)
+            .unwrap();
+
+    assert_eq!(archived.len(), map.len());
+    for (index, key, value) in map.iter_full() {
+        assert_eq!(
+            archived.get(key.as_str()).map(|v| v.to_native()),
+            Some(*value)
+        );
+        assert_eq!(archived.get_index_of(key.as_str()), Some(index));
+        let (k, v) = archived.get_index(index).unwrap();
+        assert_eq!((k.as_str(), v.to_native()), (key.as_str(), *value));
+    }
+    assert_eq!(archived.get("3"), None);
+    assert_eq!(archived.get_index(3), None);
+    assert_eq!(archived.get_index(100), None);
+
+    let indices: Vec<_> = archived.iter_full().map(|(index, _, _)| index).collect();
+    let mut expected: Vec<_> = map.iter_full().map(|(index, _, _)| index).collect();
+    expected.sort_unstable();
+    assert_eq!(indices, expected);
+}
+
+#[test]
+fn archived_set() {
+    let mut set: HashSlabSet<u32> = (0..10).collect();
+    set.remove(&4);
+    let bytes = ::rkyv::to_bytes::<Error>(&set).unwrap();
+    let archived = ::rkyv::access::<ArchivedHashSlabSet<Archived<u32>>, Error>(&bytes).unwrap();
+    assert_eq!(archived.len(), 9);
+    assert!(archived.contains(&9));
+    assert!(!archived.contains(&4));
+    assert_eq!(archived.get_index(5).map(|v| v.to_native()), Some(5));
+    assert_eq!(archived.get_index_of(&9), set.get_index_of(&9));
+
+    let empty = ::rkyv::to_bytes::<Error>(&HashSlabSet::<u32>::new()).unwrap();
+    let archived = ::rkyv::access::<ArchivedHashSlabSet<Archived<u32>>, Error>(&empty).unwrap();
+    assert!(archived.is_empty());
+    assert!(!archived.contains(&0));
+}
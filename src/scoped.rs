@@ -0,0 +1,495 @@
+//! A scoped symbol table implemented using [`HashSlabMap`]
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    iter::{FusedIterator, Rev},
+    ops::{Index, IndexMut},
+    slice,
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+use slab::Slab;
+
+use crate::HashSlabMap;
+
+#[cfg(test)]
+mod tests;
+
+// Per-name bookkeeping. `stack` holds the ids of visible bindings of the
+// name, innermost last. `refs` counts all bindings of the name, including
+// ones kept after their scope was popped, so the name is only removed when
+// no binding refers to its index any more.
+#[derive(Debug, Clone)]
+struct Name {
+    stack: Vec<usize>,
+    refs: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Binding<V> {
+    value: V,
+    // Index of the name in `names`
+    name: usize,
+    // Depth of the scope the binding belongs to, `None` once the scope is
+    // popped with `pop_scope_keep`
+    scope: Option<usize>,
+}
+
+/// A symbol table with nested scopes, where every binding has a stable id.
+///
+/// Names are resolved from the innermost scope outwards, so a binding
+/// shadows all earlier bindings of the same name until it is removed.
+/// Binding ids are indices into a slab: they stay valid until the binding
+/// is removed, either explicitly or by popping its scope with
+/// [`pop_scope`][Self::pop_scope]. Bindings of a scope popped with
+/// [`pop_scope_keep`][Self::pop_scope_keep] are no longer visible by name,
+/// but can still be accessed by id.
+///
+/// The outermost (global) scope has depth 0 and is never popped.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::ScopedHashSlabMap;
+/// let mut table = ScopedHashSlabMap::new();
+/// let global = table.insert("x", 1);
+///
+/// table.push_scope();
+/// let local = table.insert("x", 2);
+/// assert_eq!(table.lookup(&"x"), Some((local, &2)));
+/// assert_eq!(table.lookup_all(&"x").collect::<Vec<_>>(), [(local, &2), (global, &1)]);
+///
+/// table.pop_scope();
+/// assert_eq!(table.lookup(&"x"), Some((global, &1)));
+/// assert_eq!(table.get(local), None);
+/// ```
+#[cfg(feature = "std")]
+pub struct ScopedHashSlabMap<K, V, S = RandomState> {
+    names: HashSlabMap<K, Name, S>,
+    bindings: Slab<Binding<V>>,
+    // Binding ids introduced in each scope, in insertion order
+    scopes: Vec<Vec<usize>>,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct ScopedHashSlabMap<K, V, S> {
+    names: HashSlabMap<K, Name, S>,
+    bindings: Slab<Binding<V>>,
+    scopes: Vec<Vec<usize>>,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V> ScopedHashSlabMap<K, V> {
+    /// Creates an empty `ScopedHashSlabMap` with only the global scope.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty `ScopedHashSlabMap` with room for `n` bindings.
+    pub fn with_capacity(n: usize) -> Self {
+        Self::with_capacity_and_hasher(n, Default::default())
+    }
+}
+
+impl<K, V, S> ScopedHashSlabMap<K, V, S> {
+    /// Creates an empty `ScopedHashSlabMap` with room for `n` bindings,
+    /// using `hash_builder` to hash the names.
+    pub fn with_capacity_and_hasher(n: usize, hash_builder: S) -> Self {
+        Self {
+            names: HashSlabMap::with_capacity_and_hasher(n, hash_builder),
+            bindings: Slab::with_capacity(n),
+            scopes: vec![Vec::new()],
+        }
+    }
+
+    /// Creates an empty `ScopedHashSlabMap` which will use the given hash builder.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Return the number of bindings, including the ones kept after their
+    /// scope was popped.
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Returns true if the table contains no bindings.
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Return the depth of the current scope, 0 being the global scope.
+    pub fn depth(&self) -> usize {
+        self.scopes.len() - 1
+    }
+
+    /// Enter a new scope and return its depth.
+    pub fn push_scope(&mut self) -> usize {
+        self.scopes.push(Vec::new());
+        self.depth()
+    }
+
+    /// Return the ids of bindings introduced in the scope at `depth`, in
+    /// insertion order.
+    pub fn scope_bindings(&self, depth: usize) -> Option<&[usize]> {
+        self.scopes.get(depth).map(Vec::as_slice)
+    }
+
+    /// Return the depth of the scope a binding belongs to.
+    ///
+    /// Returns `None` if the binding doesn't exist or its scope was popped.
+    pub fn scope_of(&self, id: usize) -> Option<usize> {
+        self.bindings.get(id)?.scope
+    }
+
+    /// Get the name and value of a binding by id.
+    pub fn get(&self, id: usize) -> Option<(&K, &V)> {
+        let Binding { value, name, .. } = self.bindings.get(id)?;
+        let (key, _) = self.names.get_index(*name)?;
+        Some((key, value))
+    }
+
+    /// Get the name and a mutable reference to the value of a binding by id.
+    pub fn get_mut(&mut self, id: usize) -> Option<(&K, &mut V)> {
+        let Binding { value, name, .. } = self.bindings.get_mut(id)?;
+        let (key, _) = self.names.get_index(*name)?;
+        Some((key, value))
+    }
+
+    /// Returns `true` if a binding with the given id exists.
+    pub fn contains_id(&self, id: usize) -> bool {
+        self.bindings.contains(id)
+    }
+
+    /// An iterator visiting all bindings as id-name-value triples, in
+    /// arbitrary order.
+    pub fn iter(&self) -> Iter<'_, K, V, S> {
+        Iter::new(self.bindings.iter(), &self.names)
+    }
+
+    /// Remove all bindings and scopes, leaving only an empty global scope.
+    pub fn clear(&mut self) {
+        self.names.clear();
+        self.bindings.clear();
+        self.scopes.truncate(1);
+        self.scopes[0].clear();
+    }
+}
+
+impl<K, V, S> ScopedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Bind `key` to `value` in the current scope and return the binding id.
+    ///
+    /// The new binding shadows every earlier binding of the same name,
+    /// including one from the current scope.
+    pub fn insert(&mut self, key: K, value: V) -> usize {
+        let entry = self.names.entry(key);
+        let name = entry.index();
+        let name_data = entry.or_insert_with(|| Name {
+            stack: Vec::new(),
+            refs: 0,
+        });
+        let depth = self.scopes.len() - 1;
+        let id = self.bindings.insert(Binding {
+            value,
+            name,
+            scope: Some(depth),
+        });
+        name_data.stack.push(id);
+        name_data.refs += 1;
+        self.scopes[depth].push(id);
+        id
+    }
+
+    /// Resolve `key` to its innermost visible binding, returning its id and value.
+    pub fn lookup<Q>(&self, key: &Q) -> Option<(usize, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.lookup_all(key).next()
+    }
+
+    /// Resolve `key` to its innermost visible binding, returning its id and
+    /// a mutable reference to the value.
+    pub fn lookup_mut<Q>(&mut self, key: &Q) -> Option<(usize, &mut V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let &id = self.names.get(key)?.stack.last()?;
+        Some((id, &mut self.bindings[id].value))
+    }
+
+    /// An iterator over all visible bindings of `key`, from the innermost
+    /// binding to the outermost one it shadows.
+    pub fn lookup_all<Q>(&self, key: &Q) -> LookupAll<'_, V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let stack = self
+            .names
+            .get(key)
+            .map(|name| name.stack.as_slice())
+            .unwrap_or_default();
+        LookupAll::new(stack.iter().rev(), &self.bindings)
+    }
+
+    /// Leave the current scope, removing all bindings introduced in it.
+    ///
+    /// Returns the removed bindings as id-value pairs in insertion order, or
+    /// `None` if the current scope is the global one.
+    pub fn pop_scope(&mut self) -> Option<Vec<(usize, V)>> {
+        let ids = self.pop_scope_ids()?;
+        let removed = ids
+            .into_iter()
+            .map(|id| {
+                let Binding { value, name, .. } = self.bindings.remove(id);
+                self.release_name(name);
+                (id, value)
+            })
+            .collect();
+        Some(removed)
+    }
+
+    /// Leave the current scope, keeping its bindings accessible by id.
+    ///
+    /// The bindings are no longer visible by name and stay in the table
+    /// until removed with [`remove`][Self::remove]. Returns their ids in
+    /// insertion order, or `None` if the current scope is the global one.
+    pub fn pop_scope_keep(&mut self) -> Option<Vec<usize>> {
+        let ids = self.pop_scope_ids()?;
+        for &id in &ids {
+            self.bindings[id].scope = None;
+        }
+        Some(ids)
+    }
+
+    /// Remove a binding by id and return its value.
+    ///
+    /// If the binding is visible, the binding it shadowed becomes visible again.
+    pub fn remove(&mut self, id: usize) -> Option<V> {
+        let Binding { value, name, scope } = self.bindings.try_remove(id)?;
+        if let Some(depth) = scope {
+            let scope = &mut self.scopes[depth];
+            if let Some(pos) = scope.iter().rposition(|&x| x == id) {
+                scope.remove(pos);
+            }
+            let (_, name_data) = self
+                .names
+                .get_index_mut(name)
+                .expect("ScopedHashSlabMap: binding refers to a missing name");
+            if let Some(pos) = name_data.stack.iter().rposition(|&x| x == id) {
+                name_data.stack.remove(pos);
+            }
+        }
+        self.release_name(name);
+        Some(value)
+    }
+
+    // Pop the current scope and unlink its bindings from their name stacks.
+    fn pop_scope_ids(&mut self) -> Option<Vec<usize>> {
+        if self.scopes.len() == 1 {
+            return None;
+        }
+        let ids = self.scopes.pop()?;
+        // Bindings of the innermost scope are always on top of their name
+        // stacks, so unlink them in reverse insertion order.
+        for &id in ids.iter().rev() {
+            let (_, name_data) = self
+                .names
+                .get_index_mut(self.bindings[id].name)
+                .expect("ScopedHashSlabMap: binding refers to a missing name");
+            let top = name_data.stack.pop();
+            debug_assert_eq!(top, Some(id));
+        }
+        Some(ids)
+    }
+
+    // Drop a reference to a name, removing the name when it's unused.
+    fn release_name(&mut self, name: usize) {
+        let (_, name_data) = self
+            .names
+            .get_index_mut(name)
+            .expect("ScopedHashSlabMap: binding refers to a missing name");
+        name_data.refs -= 1;
+        if name_data.refs == 0 {
+            self.names.remove_index(name);
+        }
+    }
+}
+
+impl<K, V, S> Clone for ScopedHashSlabMap<K, V, S>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            names: self.names.clone(),
+            bindings: self.bindings.clone(),
+            scopes: self.scopes.clone(),
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for ScopedHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopedHashSlabMap")
+            .field("bindings", &self.iter())
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+impl<K, V, S> Default for ScopedHashSlabMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_capacity_and_hasher(0, S::default())
+    }
+}
+
+impl<K, V, S> Index<usize> for ScopedHashSlabMap<K, V, S> {
+    type Output = V;
+
+    /// Returns a reference to the value of the binding with the given id.
+    ///
+    /// ***Panics*** if the binding doesn't exist.
+    fn index(&self, id: usize) -> &V {
+        self.get(id)
+            .expect("ScopedHashSlabMap: index out of bounds")
+            .1
+    }
+}
+
+impl<K, V, S> IndexMut<usize> for ScopedHashSlabMap<K, V, S> {
+    /// Returns a mutable reference to the value of the binding with the given id.
+    ///
+    /// ***Panics*** if the binding doesn't exist.
+    fn index_mut(&mut self, id: usize) -> &mut V {
+        self.get_mut(id)
+            .expect("ScopedHashSlabMap: index out of bounds")
+            .1
+    }
+}
+
+/// An iterator over the bindings of a [`ScopedHashSlabMap`].
+///
+/// This `struct` is created by the [`ScopedHashSlabMap::iter`] method.
+/// See its documentation for more.
+pub struct Iter<'a, K, V, S> {
+    iter: slab::Iter<'a, Binding<V>>,
+    names: &'a HashSlabMap<K, Name, S>,
+}
+
+impl<'a, K, V, S> Iter<'a, K, V, S> {
+    fn new(iter: slab::Iter<'a, Binding<V>>, names: &'a HashSlabMap<K, Name, S>) -> Self {
+        Self { iter, names }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<K, V, S> Clone for Iter<'_, K, V, S> {
+    fn clone(&self) -> Self {
+        Iter {
+            iter: self.iter.clone(),
+            names: self.names,
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for Iter<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K, V, S> Iterator for Iter<'a, K, V, S> {
+    type Item = (usize, &'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(id, Binding { value, name, .. })| {
+            let (key, _) = self
+                .names
+                .get_index(*name)
+                .expect("ScopedHashSlabMap: binding refers to a missing name");
+            (id, key, value)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V, S> ExactSizeIterator for Iter<'_, K, V, S> {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl<K, V, S> FusedIterator for Iter<'_, K, V, S> {}
+
+/// An iterator over the visible bindings of a name in a [`ScopedHashSlabMap`].
+///
+/// This `struct` is created by the [`ScopedHashSlabMap::lookup_all`] method.
+/// See its documentation for more.
+pub struct LookupAll<'a, V> {
+    ids: Rev<slice::Iter<'a, usize>>,
+    bindings: &'a Slab<Binding<V>>,
+}
+
+impl<'a, V> LookupAll<'a, V> {
+    fn new(ids: Rev<slice::Iter<'a, usize>>, bindings: &'a Slab<Binding<V>>) -> Self {
+        Self { ids, bindings }
+    }
+}
+
+// https://github.com/rust-lang/rust/issues/26925
+impl<V> Clone for LookupAll<'_, V> {
+    fn clone(&self) -> Self {
+        LookupAll {
+            ids: self.ids.clone(),
+            bindings: self.bindings,
+        }
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for LookupAll<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, V> Iterator for LookupAll<'a, V> {
+    type Item = (usize, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ids.next().map(|&id| (id, &self.bindings[id].value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<V> ExactSizeIterator for LookupAll<'_, V> {
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl<V> FusedIterator for LookupAll<'_, V> {}
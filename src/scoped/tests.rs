@@ -0,0 +1,85 @@
+use super::*;
+use std::{string::ToString, vec, vec::Vec};
+
+#[test]
+fn shadowing_and_pop() {
+    let mut table = ScopedHashSlabMap::new();
+    let a0 = table.insert("a", 0);
+    let b0 = table.insert("b", 0);
+
+    assert_eq!(table.push_scope(), 1);
+    let a1 = table.insert("a", 1);
+    let a2 = table.insert("a", 2);
+    let c1 = table.insert("c", 1);
+    assert_eq!(table.lookup(&"a"), Some((a2, &2)));
+    assert_eq!(
+        table.lookup_all(&"a").collect::<Vec<_>>(),
+        [(a2, &2), (a1, &1), (a0, &0)]
+    );
+    assert_eq!(table.lookup(&"b"), Some((b0, &0)));
+    assert_eq!(table.scope_bindings(1), Some(&[a1, a2, c1][..]));
+
+    assert_eq!(table.pop_scope(), Some(vec![(a1, 1), (a2, 2), (c1, 1)]));
+    assert_eq!(table.depth(), 0);
+    assert_eq!(table.lookup(&"a"), Some((a0, &0)));
+    assert_eq!(table.lookup(&"c"), None);
+    assert_eq!(table.lookup_all(&"c").count(), 0);
+    assert_eq!(table.len(), 2);
+
+    // The global scope is never popped
+    assert_eq!(table.pop_scope(), None);
+    assert_eq!(table.pop_scope_keep(), None);
+}
+
+#[test]
+fn pop_scope_keep() {
+    let mut table = ScopedHashSlabMap::new();
+    let outer = table.insert("x".to_string(), 'o');
+    table.push_scope();
+    let inner = table.insert("x".to_string(), 'i');
+    let only = table.insert("y".to_string(), 'y');
+
+    assert_eq!(table.pop_scope_keep(), Some(vec![inner, only]));
+    assert_eq!(table.lookup("x"), Some((outer, &'o')));
+    assert_eq!(table.lookup("y"), None);
+    assert_eq!(table.get(inner), Some((&"x".to_string(), &'i')));
+    assert_eq!(table.get(only), Some((&"y".to_string(), &'y')));
+    assert_eq!(table.scope_of(inner), None);
+    assert_eq!(table.scope_of(outer), Some(0));
+
+    // Kept ids are not reused until removed
+    let other = table.insert("z".to_string(), 'z');
+    assert!(![outer, inner, only].contains(&other));
+
+    assert_eq!(table.remove(only), Some('y'));
+    assert_eq!(table.get(only), None);
+    // Name "y" has no bindings left, re-inserting it works as usual
+    let y = table.insert("y".to_string(), 'Y');
+    assert_eq!(table.lookup("y"), Some((y, &'Y')));
+    assert_eq!(table.get(inner).map(|(k, _)| k.as_str()), Some("x"));
+}
+
+#[test]
+fn remove_visible_binding() {
+    let mut table = ScopedHashSlabMap::new();
+    let a0 = table.insert('a', 0);
+    table.push_scope();
+    let a1 = table.insert('a', 1);
+    table.push_scope();
+
+    assert_eq!(table.remove(a1), Some(1));
+    assert_eq!(table.lookup(&'a'), Some((a0, &0)));
+    assert_eq!(table.scope_bindings(1), Some(&[][..]));
+
+    let a2 = table.insert('a', 2);
+    *table.lookup_mut(&'a').unwrap().1 += 10;
+    assert_eq!(table[a2], 12);
+    table[a0] = 5;
+    assert_eq!(table.pop_scope(), Some(vec![(a2, 12)]));
+    assert_eq!(table.pop_scope(), Some(vec![]));
+    assert_eq!(table.lookup(&'a'), Some((a0, &5)));
+
+    table.clear();
+    assert!(table.is_empty());
+    assert_eq!(table.lookup(&'a'), None);
+}
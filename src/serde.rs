@@ -0,0 +1,141 @@
+//! `Serialize` and `Deserialize` for [`HashSlabMap`] and [`HashSlabSet`]
+//!
+//! A map is serialized as a sequence of `(index, key, value)` tuples, and a
+//! set as a sequence of `(index, value)` tuples, in index order, so compact
+//! formats like postcard restore the same indices. The vacant indices below
+//! the highest one are reused highest first after deserialization, as if
+//! they had been removed in ascending order.
+//!
+//! Deserialization rejects a highest index of twice the number of entries
+//! plus 65536 or more, so a forged index can't allocate an arbitrary amount
+//! of memory. Serializing a map with more vacant indices than that fails,
+//! so everything written can be read back.
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+
+use ::serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::{self, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{map::index_bound, HashSlabMap, HashSlabSet};
+
+// Do not trust the size hint of the input for the allocation
+fn cautious(hint: Option<usize>) -> usize {
+    hint.unwrap_or(0).min(4096)
+}
+
+// The entries in index order, or an error if they could not be deserialized.
+fn sorted<K, V, S, E: ser::Error>(map: &HashSlabMap<K, V, S>) -> Result<Vec<(usize, &K, &V)>, E> {
+    let mut entries: Vec<_> = map.iter_full().collect();
+    entries.sort_unstable_by_key(|&(index, _, _)| index);
+    match entries.last() {
+        Some(&(index, _, _)) if index >= index_bound(entries.len()) => Err(E::custom(
+            "too many vacant indices below the highest one to deserialize",
+        )),
+        _ => Ok(entries),
+    }
+}
+
+impl<K, V, S> Serialize for HashSlabMap<K, V, S>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let entries = sorted(self)?;
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for entry in entries {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+}
+
+struct MapVisitor<K, V, S>(PhantomData<(K, V, S)>);
+
+impl<'de, K, V, S> Visitor<'de> for MapVisitor<K, V, S>
+where
+    K: Deserialize<'de> + Hash + Eq,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+{
+    type Value = HashSlabMap<K, V, S>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence of (index, key, value) tuples")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(cautious(seq.size_hint()));
+        while let Some(entry) = seq.next_element()? {
+            entries.push(entry);
+        }
+        HashSlabMap::from_indexed(entries, S::default())
+            .ok_or_else(|| de::Error::custom("duplicate index or key, or index out of range"))
+    }
+}
+
+impl<'de, K, V, S> Deserialize<'de> for HashSlabMap<K, V, S>
+where
+    K: Deserialize<'de> + Hash + Eq,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(MapVisitor(PhantomData))
+    }
+}
+
+impl<T, S> Serialize for HashSlabSet<T, S>
+where
+    T: Serialize,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let entries = sorted(&self.map)?;
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for (index, value, _) in entries {
+            seq.serialize_element(&(index, value))?;
+        }
+        seq.end()
+    }
+}
+
+struct SetVisitor<T, S>(PhantomData<(T, S)>);
+
+impl<'de, T, S> Visitor<'de> for SetVisitor<T, S>
+where
+    T: Deserialize<'de> + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    type Value = HashSlabSet<T, S>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence of (index, value) tuples")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(cautious(seq.size_hint()));
+        while let Some((index, value)) = seq.next_element()? {
+            entries.push((index, value, ()));
+        }
+        HashSlabMap::from_indexed(entries, S::default())
+            .map(|map| HashSlabSet { map })
+            .ok_or_else(|| de::Error::custom("duplicate index or value, or index out of range"))
+    }
+}
+
+impl<'de, T, S> Deserialize<'de> for HashSlabSet<T, S>
+where
+    T: Deserialize<'de> + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(SetVisitor(PhantomData))
+    }
+}
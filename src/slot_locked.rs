@@ -0,0 +1,420 @@
+//! A [`HashSlabMap`] with individually lockable values
+use std::{
+    fmt,
+    hash::{BuildHasher, Hash, RandomState},
+    ops::{Deref, DerefMut},
+    sync::{
+        Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError,
+    },
+};
+
+use hashbrown::Equivalent;
+use slab::Slab;
+
+use crate::{HashSlabMap, ValueData};
+
+#[cfg(test)]
+mod tests;
+
+/// A concurrent hash map with stable indices, whose values are locked one by
+/// one.
+///
+/// Every value sits behind its own [`Mutex`], and the whole map behind a
+/// [`RwLock`]. [`lock_index`][Self::lock_index] and [`lock`][Self::lock]
+/// hold the map lock for reading and the value lock, so threads updating
+/// different entries don't wait on each other. Structural changes like
+/// [`insert`][Self::insert] and [`remove`][Self::remove] take the map lock
+/// for writing, and wait until all value guards are dropped.
+///
+/// All methods take `&self`, so the map can be shared between threads in an
+/// [`Arc`](std::sync::Arc). Holding a [`SlotGuard`] while inserting or
+/// removing, or while locking the same entry again, on the same thread
+/// deadlocks. Locking another entry while holding a guard can deadlock too:
+/// the map lock is not reentrant, so if another thread is waiting to insert
+/// or remove, the second lock waits behind it while the writer waits for the
+/// first guard.
+///
+/// Lock poisoning is ignored: a panic while a value is locked doesn't make
+/// it unusable.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::SlotLockedHashSlabMap;
+/// use std::thread;
+///
+/// let jobs = SlotLockedHashSlabMap::new();
+/// let ids: Vec<usize> = (0..4).map(|i| jobs.insert_full(i, 0).0).collect();
+///
+/// thread::scope(|s| {
+///     for &id in &ids {
+///         let jobs = &jobs;
+///         s.spawn(move || {
+///             for _ in 0..100 {
+///                 *jobs.lock_index(id).unwrap() += 1;
+///             }
+///         });
+///     }
+/// });
+///
+/// assert_eq!(*jobs.lock(&2).unwrap(), 100);
+/// let mut guard = jobs.lock_index(ids[0]).unwrap();
+/// assert_eq!(guard.key(), &0);
+/// *guard = 7;
+/// drop(guard);
+/// assert_eq!(jobs.remove(&0), Some(7));
+/// ```
+pub struct SlotLockedHashSlabMap<K, V, S = RandomState> {
+    map: RwLock<HashSlabMap<K, Mutex<V>, S>>,
+}
+
+impl<K, V> SlotLockedHashSlabMap<K, V> {
+    /// Creates an empty `SlotLockedHashSlabMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    /// Creates an empty `SlotLockedHashSlabMap` with the specified capacity.
+    pub fn with_capacity(n: usize) -> Self {
+        Self::with_capacity_and_hasher(n, RandomState::new())
+    }
+}
+
+impl<K, V, S> SlotLockedHashSlabMap<K, V, S> {
+    /// Creates an empty `SlotLockedHashSlabMap` which will use the given hash
+    /// builder.
+    pub fn with_hasher(builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, builder)
+    }
+
+    /// Creates an empty `SlotLockedHashSlabMap` with the specified capacity,
+    /// using `builder` to hash the keys.
+    pub fn with_capacity_and_hasher(n: usize, builder: S) -> Self {
+        Self {
+            map: RwLock::new(HashSlabMap::with_capacity_and_hasher(n, builder)),
+        }
+    }
+
+    /// Return the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns true if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the map contains an entry at the given index.
+    pub fn contains_index(&self, index: usize) -> bool {
+        self.read().slab.contains(index)
+    }
+
+    /// Lock the value at the given index, blocking until it is available.
+    ///
+    /// Returns `None` if there is no entry at the index.
+    pub fn lock_index(&self, index: usize) -> Option<SlotGuard<'_, K, V, S>> {
+        SlotGuard::new(self.read(), index, |mutex| {
+            Some(mutex.lock().unwrap_or_else(PoisonError::into_inner))
+        })
+    }
+
+    /// Try to lock the value at the given index without blocking.
+    ///
+    /// Returns `None` if there is no entry at the index, or if the value or
+    /// the map is locked by someone else.
+    pub fn try_lock_index(&self, index: usize) -> Option<SlotGuard<'_, K, V, S>> {
+        let guard = match self.map.try_read() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(error)) => error.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        SlotGuard::new(guard, index, |mutex| match mutex.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(error)) => Some(error.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        })
+    }
+
+    /// Removes the entry at the given index, returning its key and value.
+    pub fn remove_index(&self, index: usize) -> Option<(K, V)>
+    where
+        K: Hash + Eq,
+        S: BuildHasher,
+    {
+        self.write()
+            .remove_index(index)
+            .map(|(key, value)| (key, into_value(value)))
+    }
+
+    /// Retains only the entries specified by the predicate.
+    pub fn retain<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.write().retain(|key, value| f(key, get_mut(value)));
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// Consumes the map, returning a [`HashSlabMap`] with the same entries
+    /// and indices.
+    ///
+    /// The returned map reuses the vacant indices highest first, as if they
+    /// had been removed in ascending order, which may differ from the order
+    /// this map would reuse them in: the slab has to be rebuilt to unwrap the
+    /// values, and a slab cannot be rebuilt with an arbitrary order of vacant
+    /// indices.
+    pub fn into_inner(self) -> HashSlabMap<K, V, S> {
+        let HashSlabMap {
+            table,
+            slab,
+            builder,
+        } = self
+            .map
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        let slab = slab
+            .into_iter()
+            .map(|(index, ValueData { value, hash })| {
+                let value = into_value(value);
+                (index, ValueData { value, hash })
+            })
+            .collect::<Slab<_>>();
+        HashSlabMap {
+            table,
+            slab,
+            builder,
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashSlabMap<K, Mutex<V>, S>> {
+        self.map.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashSlabMap<K, Mutex<V>, S>> {
+        self.map.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<K, V, S> SlotLockedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Lock the value of `key`, blocking until it is available.
+    pub fn lock<Q>(&self, key: &Q) -> Option<SlotGuard<'_, K, V, S>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let guard = self.read();
+        let index = guard.get_index_of(key)?;
+        SlotGuard::new(guard, index, |mutex| {
+            Some(mutex.lock().unwrap_or_else(PoisonError::into_inner))
+        })
+    }
+
+    /// Return the index of `key`.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.read().get_index_of(key)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.read().contains_key(key)
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the key is already present, its value is replaced and the old value
+    /// is returned.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Inserts a key-value pair into the map, and returns its index together
+    /// with the old value if the key was present.
+    pub fn insert_full(&self, key: K, value: V) -> (usize, Option<V>) {
+        let mut map = self.write();
+        match map.get_full_mut(&key) {
+            Some((index, _, old)) => (index, Some(std::mem::replace(get_mut(old), value))),
+            None => (map.insert_full(key, Mutex::new(value)).0, None),
+        }
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Removes a key from the map, returning its index, key and value if it
+    /// was present.
+    pub fn remove_full<Q>(&self, key: &Q) -> Option<(usize, K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.write()
+            .remove_full(key)
+            .map(|(index, key, value)| (index, key, into_value(value)))
+    }
+}
+
+fn get_mut<V>(mutex: &mut Mutex<V>) -> &mut V {
+    mutex.get_mut().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn into_value<V>(mutex: Mutex<V>) -> V {
+    mutex.into_inner().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<K, V, S> fmt::Debug for SlotLockedHashSlabMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlotLockedHashSlabMap")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V, S> Default for SlotLockedHashSlabMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> From<HashSlabMap<K, V, S>> for SlotLockedHashSlabMap<K, V, S> {
+    /// Wraps the values of `map` in locks, keeping indices.
+    ///
+    /// As with [`into_inner`](SlotLockedHashSlabMap::into_inner), the vacant
+    /// indices are reused highest first afterwards.
+    fn from(map: HashSlabMap<K, V, S>) -> Self {
+        let HashSlabMap {
+            table,
+            slab,
+            builder,
+        } = map;
+        let slab = slab
+            .into_iter()
+            .map(|(index, ValueData { value, hash })| {
+                let value = Mutex::new(value);
+                (index, ValueData { value, hash })
+            })
+            .collect::<Slab<_>>();
+        Self {
+            map: RwLock::new(HashSlabMap {
+                table,
+                slab,
+                builder,
+            }),
+        }
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for SlotLockedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for SlotLockedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iterable: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iterable);
+        map
+    }
+}
+
+/// A guard holding the lock of one value of a [`SlotLockedHashSlabMap`].
+///
+/// The map can't be modified structurally while the guard is alive.
+///
+/// This `struct` is created by the [`SlotLockedHashSlabMap::lock_index`],
+/// [`SlotLockedHashSlabMap::try_lock_index`] and [`SlotLockedHashSlabMap::lock`]
+/// methods. See their documentation for more.
+pub struct SlotGuard<'a, K, V, S> {
+    // Declared first, so it is unlocked before the map
+    value: MutexGuard<'a, V>,
+    key: &'a K,
+    index: usize,
+    _map: RwLockReadGuard<'a, HashSlabMap<K, Mutex<V>, S>>,
+}
+
+impl<'a, K, V, S> SlotGuard<'a, K, V, S> {
+    fn new<F>(
+        map: RwLockReadGuard<'a, HashSlabMap<K, Mutex<V>, S>>,
+        index: usize,
+        lock: F,
+    ) -> Option<Self>
+    where
+        F: FnOnce(&'a Mutex<V>) -> Option<MutexGuard<'a, V>>,
+    {
+        let (key, mutex) = map.get_index(index)?;
+        // SAFETY: the entry can't be moved or dropped while the read guard,
+        // which is stored alongside the references, is alive
+        let (key, mutex) = unsafe { (&*(key as *const K), &*(mutex as *const Mutex<V>)) };
+        Some(Self {
+            value: lock(mutex)?,
+            key,
+            index,
+            _map: map,
+        })
+    }
+
+    /// Return the index of the entry.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return a reference to the key of the entry.
+    pub fn key(&self) -> &K {
+        self.key
+    }
+}
+
+impl<K, V, S> Deref for SlotGuard<'_, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.value
+    }
+}
+
+impl<K, V, S> DerefMut for SlotGuard<'_, K, V, S> {
+    fn deref_mut(&mut self) -> &mut V {
+        &mut self.value
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for SlotGuard<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlotGuard")
+            .field("index", &self.index)
+            .field("key", self.key)
+            .field("value", &*self.value)
+            .finish()
+    }
+}
@@ -0,0 +1,89 @@
+use super::*;
+use std::{
+    string::{String, ToString},
+    thread,
+    vec::Vec,
+};
+
+#[test]
+fn lock_and_modify() {
+    let map = SlotLockedHashSlabMap::new();
+    assert_eq!(map.insert_full("a".to_string(), 1), (0, None));
+    assert_eq!(map.insert_full("b".to_string(), 2), (1, None));
+    assert_eq!(map.insert_full("a".to_string(), 3), (0, Some(1)));
+    assert!(map.lock_index(2).is_none());
+    assert!(map.lock("c").is_none());
+
+    {
+        let mut a = map.lock("a").unwrap();
+        let b = map.lock_index(1).unwrap();
+        assert_eq!((a.index(), a.key().as_str()), (0, "a"));
+        *a += *b;
+        // Locked entries can't be locked again
+        assert!(map.try_lock_index(0).is_none());
+        assert!(map.try_lock_index(1).is_none());
+    }
+    assert_eq!(*map.try_lock_index(0).unwrap(), 5);
+
+    assert_eq!(map.remove("a"), Some(5));
+    assert_eq!(map.insert_full("c".to_string(), 4), (0, None));
+    map.retain(|_, value| {
+        *value *= 10;
+        *value > 20
+    });
+    assert_eq!(map.get_index_of("c"), Some(0));
+    assert!(!map.contains_key("b"));
+    assert_eq!(map.remove_index(0), Some(("c".to_string(), 40)));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn from_and_into_map() {
+    let mut inner = HashSlabMap::new();
+    inner.insert(String::from("x"), 1);
+    inner.insert(String::from("y"), 2);
+    inner.insert(String::from("z"), 3);
+    inner.remove("y");
+
+    let map = SlotLockedHashSlabMap::from(inner);
+    assert!(!map.contains_index(1));
+    *map.lock_index(2).unwrap() = 30;
+    assert_eq!(map.get_index_of("z"), Some(2));
+
+    let inner = map.into_inner();
+    assert_eq!(inner.get_index(2), Some((&String::from("z"), &30)));
+    assert_eq!(inner.get_full("x"), Some((0, &String::from("x"), &1)));
+    assert_eq!(inner.len(), 2);
+
+    // Vacant indices are reused highest first after the conversions
+    let mut inner: HashSlabMap<_, _> = (0..5).map(|i| (i, i)).collect();
+    inner.remove(&3);
+    inner.remove(&1);
+    assert_eq!(inner.vacant_index(), 1);
+    let mut inner = SlotLockedHashSlabMap::from(inner).into_inner();
+    assert_eq!(inner.insert_full(5, 5).0, 3);
+    assert_eq!(inner.insert_full(6, 6).0, 1);
+}
+
+#[test]
+fn parallel_updates() {
+    let map: SlotLockedHashSlabMap<usize, Vec<usize>> = (0..8).map(|i| (i, Vec::new())).collect();
+    thread::scope(|s| {
+        for t in 0..8 {
+            let map = &map;
+            s.spawn(move || {
+                for i in 0..100 {
+                    map.lock_index((t + i) % 8).unwrap().push(t);
+                }
+            });
+        }
+        s.spawn(|| {
+            for i in 8..50 {
+                map.insert(i, Vec::new());
+            }
+        });
+    });
+    let total: usize = (0..8).map(|i| map.lock_index(i).unwrap().len()).sum();
+    assert_eq!(total, 800);
+    assert_eq!(map.len(), 50);
+}
@@ -0,0 +1,411 @@
+//! A versioned binary snapshot format for [`HashSlabMap`] and [`HashSlabSet`]
+//!
+//! A snapshot keeps the indices of the entries and the order in which the
+//! vacant indices are reused, so a reloaded map hands out the same
+//! [`vacant_index`](HashSlabMap::vacant_index) as the original one.
+//!
+//! # Format
+//!
+//! All integers are little-endian. A snapshot starts with a 7-byte header:
+//!
+//! | Bytes | Content                                   |
+//! |-------|-------------------------------------------|
+//! | 4     | Magic `HSLB`                              |
+//! | 2     | Format version, currently `1`             |
+//! | 1     | Kind: `0` for a map, `1` for a set        |
+//!
+//! It is followed by sections, each made of a 1-byte tag, the length of the
+//! payload as a `u64`, the payload, and the CRC-32 (IEEE) checksum of the
+//! payload as a `u32`:
+//!
+//! - `1`, layout, first and only once: the capacity, which is capped at twice
+//!   the highest index when reading, and the number of entries as `u64`s,
+//!   then the vacant indices in the order they are reused, as a `u64` count
+//!   followed by `u64` indices. Vacant indices which would be handed out in
+//!   sequence after all others are left out.
+//! - `2`, entries, any number of times: a `u64` count followed by the
+//!   entries, each an index as a `u64`, the key and, for maps, the value,
+//!   encoded with the [`codec`](crate::codec) traits.
+//! - `0`, end, last: an empty payload.
+//!
+//! Reading and rebuilding the order of the vacant indices requires cloning
+//! values, hence the `V: Clone` bounds. A map without entries is written
+//! without vacant indices, as the slab cannot be probed without a value, so
+//! it hands out indices from `0` once reloaded.
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+#[cfg(feature = "std")]
+use std::{
+    hash::RandomState,
+    io::{self, Read, Write},
+};
+
+use thiserror::Error;
+
+use crate::{
+    codec::{self, crc32, Decode, DecodeError, Encode},
+    HashSlabMap, HashSlabSet,
+};
+
+#[cfg(test)]
+mod tests;
+
+const MAGIC: &[u8; 4] = b"HSLB";
+/// The current version of the snapshot format.
+pub const VERSION: u16 = 1;
+
+const MAP: u8 = 0;
+const SET: u8 = 1;
+
+const END: u8 = 0;
+const LAYOUT: u8 = 1;
+const ENTRIES: u8 = 2;
+
+// Entries per entries section
+const CHUNK: usize = 1024;
+
+/// The error type for reading snapshots.
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("not a hashslab snapshot")]
+    BadMagic,
+    #[error("unsupported snapshot version {0}")]
+    UnsupportedVersion(u16),
+    #[error("snapshot of a {found}, expected a {expected}")]
+    WrongKind {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("snapshot is truncated")]
+    Truncated,
+    #[error("checksum mismatch in {section} section")]
+    ChecksumMismatch { section: &'static str },
+    #[error("unexpected section {0}")]
+    UnexpectedSection(u8),
+    #[error("malformed {section} section: {error}")]
+    Malformed {
+        section: &'static str,
+        error: DecodeError,
+    },
+    #[error("the entries do not match the layout")]
+    InvalidLayout,
+    #[error("trailing data after the end of the snapshot")]
+    TrailingData,
+}
+
+fn kind_name(kind: u8) -> &'static str {
+    match kind {
+        MAP => "map",
+        SET => "set",
+        _ => "unknown collection",
+    }
+}
+
+fn section_name(tag: u8) -> &'static str {
+    match tag {
+        END => "end",
+        LAYOUT => "layout",
+        _ => "entries",
+    }
+}
+
+// Write a snapshot of `map`, a set if `kind` is `SET`, through `write`.
+fn encode<K, V, S, E>(
+    map: &HashSlabMap<K, V, S>,
+    kind: u8,
+    mut write: impl FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E>
+where
+    K: Encode,
+    V: Encode + Clone,
+{
+    let mut header = MAGIC.to_vec();
+    VERSION.encode(&mut header);
+    kind.encode(&mut header);
+    write(&header)?;
+
+    let mut section = |tag: u8, payload: &[u8]| {
+        let mut header = Vec::with_capacity(9);
+        tag.encode(&mut header);
+        payload.len().encode(&mut header);
+        write(&header)?;
+        write(payload)?;
+        write(&crc32(payload).to_le_bytes())
+    };
+
+    let mut payload = Vec::new();
+    map.capacity().encode(&mut payload);
+    map.len().encode(&mut payload);
+    map.free_list().encode(&mut payload);
+    section(LAYOUT, &payload)?;
+
+    let mut entries: Vec<_> = map.iter_full().collect();
+    entries.sort_unstable_by_key(|&(index, _, _)| index);
+    for chunk in entries.chunks(CHUNK) {
+        payload.clear();
+        chunk.encode(&mut payload);
+        section(ENTRIES, &payload)?;
+    }
+    section(END, &[])
+}
+
+// Read a snapshot through `read`, which returns exactly the requested number
+// of bytes.
+fn decode<K, V, S>(
+    kind: u8,
+    builder: S,
+    mut read: impl FnMut(usize) -> Result<Vec<u8>, SnapshotError>,
+) -> Result<HashSlabMap<K, V, S>, SnapshotError>
+where
+    K: Hash + Eq + Decode,
+    V: Decode + Clone,
+    S: BuildHasher,
+{
+    let header = read(7)?;
+    let mut input = header.strip_prefix(MAGIC).ok_or(SnapshotError::BadMagic)?;
+    let version = u16::decode(&mut input).map_err(|_| SnapshotError::Truncated)?;
+    if version != VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    let found = input[0];
+    if found != kind {
+        return Err(SnapshotError::WrongKind {
+            expected: kind_name(kind),
+            found: kind_name(found),
+        });
+    }
+
+    let mut layout = None;
+    let mut entries: Vec<(usize, K, V)> = Vec::new();
+    loop {
+        let mut header = read(9)?;
+        let mut input = header.as_slice();
+        let (tag, len) = <(u8, u64)>::decode(&mut input).map_err(|_| SnapshotError::Truncated)?;
+        let section = section_name(tag);
+        let len = usize::try_from(len).map_err(|_| SnapshotError::Truncated)?;
+        let payload = read(len)?;
+        header = read(4)?;
+        if crc32(&payload).to_le_bytes()[..] != header[..] {
+            return Err(SnapshotError::ChecksumMismatch { section });
+        }
+
+        let malformed = |error| SnapshotError::Malformed { section, error };
+        let mut input = payload.as_slice();
+        match (tag, &layout) {
+            (LAYOUT, None) => {
+                let parsed = <(usize, usize, Vec<usize>)>::decode(&mut input).map_err(malformed)?;
+                layout = Some(parsed);
+            }
+            (ENTRIES, Some(_)) => {
+                let chunk = Vec::<(usize, K, V)>::decode(&mut input).map_err(malformed)?;
+                entries.extend(chunk);
+            }
+            (END, Some(_)) => {}
+            _ => return Err(SnapshotError::UnexpectedSection(tag)),
+        }
+        if !input.is_empty() {
+            return Err(malformed(DecodeError::Invalid("section length")));
+        }
+        if tag == END {
+            break;
+        }
+    }
+
+    let Some((capacity, len, free_list)) = layout else {
+        unreachable!()
+    };
+    if entries.len() != len {
+        return Err(SnapshotError::InvalidLayout);
+    }
+    HashSlabMap::from_layout(entries, &free_list, capacity, builder)
+        .ok_or(SnapshotError::InvalidLayout)
+}
+
+#[cfg(feature = "std")]
+fn read_io<R: Read>(reader: &mut R) -> impl FnMut(usize) -> Result<Vec<u8>, SnapshotError> + '_ {
+    move |n| {
+        // Grow the buffer with the data instead of trusting the length
+        let mut bytes = Vec::new();
+        reader.by_ref().take(n as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < n {
+            return Err(SnapshotError::Truncated);
+        }
+        Ok(bytes)
+    }
+}
+
+fn from_slice<K, V, S>(
+    kind: u8,
+    mut bytes: &[u8],
+    builder: S,
+) -> Result<HashSlabMap<K, V, S>, SnapshotError>
+where
+    K: Hash + Eq + Decode,
+    V: Decode + Clone,
+    S: BuildHasher,
+{
+    let map = decode(kind, builder, |n| {
+        codec::take(&mut bytes, n)
+            .map(<[u8]>::to_vec)
+            .map_err(|_| SnapshotError::Truncated)
+    })?;
+    if !bytes.is_empty() {
+        return Err(SnapshotError::TrailingData);
+    }
+    Ok(map)
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Encode,
+    V: Encode + Clone,
+{
+    /// Return a [snapshot](crate::snapshot) of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hashslab::HashSlabMap;
+    /// let mut map = HashSlabMap::new();
+    /// map.insert("a".to_string(), 1);
+    /// map.insert("b".to_string(), 2);
+    /// map.insert("c".to_string(), 3);
+    /// map.remove("a");
+    /// map.remove("c");
+    ///
+    /// let bytes = map.to_snapshot_bytes();
+    /// let mut copy = HashSlabMap::<String, i32>::from_snapshot_bytes(&bytes).unwrap();
+    /// assert_eq!(copy.get_index_of("b"), Some(1));
+    /// assert_eq!(copy.insert_full("d".to_string(), 4).0, 2);
+    /// assert_eq!(copy.insert_full("e".to_string(), 5).0, 0);
+    /// ```
+    pub fn to_snapshot_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let _ = encode(self, MAP, |chunk| {
+            bytes.extend_from_slice(chunk);
+            Ok::<_, ()>(())
+        });
+        bytes
+    }
+
+    /// Write a [snapshot](crate::snapshot) of the map to `writer`.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn write_snapshot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        encode(self, MAP, |chunk| writer.write_all(chunk))
+    }
+}
+
+impl<K, V, S> HashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Decode,
+    V: Decode + Clone,
+    S: BuildHasher,
+{
+    /// Read a map from a [snapshot](crate::snapshot) in `bytes`, using the
+    /// given hash builder.
+    pub fn from_snapshot_bytes_with_hasher(
+        bytes: &[u8],
+        builder: S,
+    ) -> Result<Self, SnapshotError> {
+        from_slice(MAP, bytes, builder)
+    }
+
+    /// Read a map from a [snapshot](crate::snapshot) in `reader`, using the
+    /// given hash builder.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn read_snapshot_with_hasher<R: Read>(
+        mut reader: R,
+        builder: S,
+    ) -> Result<Self, SnapshotError> {
+        decode(MAP, builder, read_io(&mut reader))
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V> HashSlabMap<K, V>
+where
+    K: Hash + Eq + Decode,
+    V: Decode + Clone,
+{
+    /// Read a map from a [snapshot](crate::snapshot) in `bytes`.
+    pub fn from_snapshot_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Self::from_snapshot_bytes_with_hasher(bytes, RandomState::new())
+    }
+
+    /// Read a map from a [snapshot](crate::snapshot) in `reader`.
+    pub fn read_snapshot<R: Read>(reader: R) -> Result<Self, SnapshotError> {
+        Self::read_snapshot_with_hasher(reader, RandomState::new())
+    }
+}
+
+impl<T, S> HashSlabSet<T, S>
+where
+    T: Encode,
+{
+    /// Return a [snapshot](crate::snapshot) of the set.
+    pub fn to_snapshot_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let _ = encode(&self.map, SET, |chunk| {
+            bytes.extend_from_slice(chunk);
+            Ok::<_, ()>(())
+        });
+        bytes
+    }
+
+    /// Write a [snapshot](crate::snapshot) of the set to `writer`.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn write_snapshot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        encode(&self.map, SET, |chunk| writer.write_all(chunk))
+    }
+}
+
+impl<T, S> HashSlabSet<T, S>
+where
+    T: Hash + Eq + Decode,
+    S: BuildHasher,
+{
+    /// Read a set from a [snapshot](crate::snapshot) in `bytes`, using the
+    /// given hash builder.
+    pub fn from_snapshot_bytes_with_hasher(
+        bytes: &[u8],
+        builder: S,
+    ) -> Result<Self, SnapshotError> {
+        from_slice(SET, bytes, builder).map(|map| Self { map })
+    }
+
+    /// Read a set from a [snapshot](crate::snapshot) in `reader`, using the
+    /// given hash builder.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn read_snapshot_with_hasher<R: Read>(
+        mut reader: R,
+        builder: S,
+    ) -> Result<Self, SnapshotError> {
+        decode(SET, builder, read_io(&mut reader)).map(|map| Self { map })
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<T> HashSlabSet<T>
+where
+    T: Hash + Eq + Decode,
+{
+    /// Read a set from a [snapshot](crate::snapshot) in `bytes`.
+    pub fn from_snapshot_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Self::from_snapshot_bytes_with_hasher(bytes, RandomState::new())
+    }
+
+    /// Read a set from a [snapshot](crate::snapshot) in `reader`.
+    pub fn read_snapshot<R: Read>(reader: R) -> Result<Self, SnapshotError> {
+        Self::read_snapshot_with_hasher(reader, RandomState::new())
+    }
+}
@@ -0,0 +1,124 @@
+use super::*;
+use std::{
+    string::{String, ToString},
+    vec,
+};
+
+fn sample() -> HashSlabMap<String, u32> {
+    let mut map: HashSlabMap<String, u32> = (0..10u32).map(|i| (i.to_string(), i)).collect();
+    for key in ["3", "7", "1", "9", "8"] {
+        map.remove(key);
+    }
+    map
+}
+
+#[test]
+fn round_trip_keeps_the_layout() {
+    let mut map = sample();
+    map.reserve(10);
+    let bytes = map.to_snapshot_bytes();
+    let mut copy = HashSlabMap::<String, u32>::from_snapshot_bytes(&bytes).unwrap();
+
+    let mut expected: vec::Vec<_> = map.iter_full().collect();
+    let mut found: vec::Vec<_> = copy.iter_full().collect();
+    expected.sort_unstable();
+    found.sort_unstable();
+    assert_eq!(found, expected);
+    assert!(copy.capacity() >= map.capacity());
+
+    // The vacant indices are handed out in the same order
+    for i in 10..20u32 {
+        assert_eq!(copy.vacant_index(), map.vacant_index());
+        let key = i.to_string();
+        assert_eq!(copy.insert_full(key.clone(), i), map.insert_full(key, i));
+    }
+
+    let mut streamed = vec::Vec::new();
+    map.write_snapshot(&mut streamed).unwrap();
+    let read = HashSlabMap::<String, u32>::read_snapshot(streamed.as_slice()).unwrap();
+    assert_eq!(read.vacant_index(), map.vacant_index());
+    assert_eq!(read.len(), map.len());
+}
+
+#[test]
+fn capacity_and_empty_maps() {
+    // The capacity is capped by the entries
+    let mut map = sample();
+    map.reserve(10_000);
+    let copy = HashSlabMap::<String, u32>::from_snapshot_bytes(&map.to_snapshot_bytes()).unwrap();
+    assert!(copy.capacity() < 1000);
+    assert_eq!(copy.vacant_index(), map.vacant_index());
+
+    // A map without entries loses its vacant indices
+    map.clear();
+    map.insert("a".to_string(), 0);
+    map.insert("b".to_string(), 1);
+    map.remove("a");
+    map.remove("b");
+    assert_eq!(map.vacant_index(), 1);
+    let copy = HashSlabMap::<String, u32>::from_snapshot_bytes(&map.to_snapshot_bytes()).unwrap();
+    assert!(copy.is_empty());
+    assert_eq!(copy.vacant_index(), 0);
+}
+
+#[test]
+fn set_round_trip() {
+    let mut set: HashSlabSet<char> = ('a'..='z').collect();
+    set.remove(&'q');
+    set.remove(&'c');
+    let bytes = set.to_snapshot_bytes();
+    let copy = HashSlabSet::<char>::from_snapshot_bytes(&bytes).unwrap();
+    assert_eq!(copy.len(), 24);
+    assert_eq!(copy.get_index_of(&'z'), set.get_index_of(&'z'));
+    assert_eq!(copy.map.vacant_index(), set.map.vacant_index());
+
+    assert!(matches!(
+        HashSlabMap::<char, ()>::from_snapshot_bytes(&bytes),
+        Err(SnapshotError::WrongKind { .. })
+    ));
+}
+
+#[test]
+fn corruption_is_detected() {
+    let bytes = sample().to_snapshot_bytes();
+    let read = |bytes: &[u8]| HashSlabMap::<String, u32>::from_snapshot_bytes(bytes);
+
+    let mut flipped = bytes.clone();
+    let last = flipped.len() - 20;
+    flipped[last] ^= 1;
+    assert!(matches!(
+        read(&flipped),
+        Err(SnapshotError::ChecksumMismatch { section: "entries" })
+    ));
+
+    assert!(matches!(
+        read(&bytes[..bytes.len() - 1]),
+        Err(SnapshotError::Truncated)
+    ));
+    assert!(matches!(read(b"HSL"), Err(SnapshotError::Truncated)));
+    assert!(matches!(read(b"JSON{}\0"), Err(SnapshotError::BadMagic)));
+
+    let mut future = bytes.clone();
+    future[4] = 2;
+    assert!(matches!(
+        read(&future),
+        Err(SnapshotError::UnsupportedVersion(2))
+    ));
+
+    // The end section has no payload
+    let mut end = bytes[..bytes.len() - 13].to_vec();
+    end.push(0);
+    1u64.encode(&mut end);
+    end.push(0);
+    end.extend_from_slice(&crc32(&[0]).to_le_bytes());
+    assert!(matches!(
+        read(&end),
+        Err(SnapshotError::Malformed { section: "end", .. })
+    ));
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(read(&trailing), Err(SnapshotError::TrailingData)));
+    // A stream may continue after the snapshot
+    assert!(HashSlabMap::<String, u32>::read_snapshot(trailing.as_slice()).is_ok());
+}
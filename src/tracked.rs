@@ -0,0 +1,488 @@
+//! A [`HashSlabMap`] which records when each entry was last changed
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    iter::FusedIterator,
+    ops::Deref,
+};
+
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use hashbrown::Equivalent;
+
+use crate::{map, HashSlabMap};
+
+#[cfg(test)]
+mod tests;
+
+/// The kind of change reported by [`TrackedHashSlabMap::changes_since`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// A new entry was inserted at the index, replacing whatever entry the
+    /// index held before.
+    Inserted,
+    /// The value of the entry was replaced or modified.
+    Updated,
+    /// The entry at the index was removed.
+    Removed,
+}
+
+// The epochs of the last changes of a slot.
+#[derive(Debug, Clone, Copy)]
+struct Stamp {
+    inserted: u64,
+    modified: u64,
+    removed: bool,
+}
+
+/// A [`HashSlabMap`] which records the epoch of the last change of every
+/// index, to sync its content incrementally.
+///
+/// Insertions, removals and value changes made through
+/// [`get_mut`][Self::get_mut], [`get_index_mut`][Self::get_index_mut],
+/// [`iter_mut`][Self::iter_mut] and the other mutable accessors are stamped
+/// with the current [`epoch`][Self::epoch]. Values handed out mutably are
+/// considered changed, whether they were written to or not. Removed entries
+/// leave a tombstone, which is reported until the index is reused.
+///
+/// [`changes_since`][Self::changes_since] iterates the indices changed during
+/// or after an epoch, and [`advance_epoch`][Self::advance_epoch] starts a new
+/// one. The map can be read through [`Deref`].
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::TrackedHashSlabMap;
+/// # use hashslab::tracked::ChangeKind;
+/// let mut map = TrackedHashSlabMap::new();
+/// map.insert("a", 1);
+/// map.insert("b", 2);
+///
+/// // Sync everything, then start a new round
+/// assert_eq!(map.changes_since(0).count(), 2);
+/// let round = map.advance_epoch();
+///
+/// *map.get_mut("a").unwrap() += 10;
+/// map.remove("b");
+/// map.insert("c", 3);
+///
+/// let mut changes: Vec<_> = map.changes_since(round).collect();
+/// changes.sort_unstable_by_key(|&(index, _)| index);
+/// assert_eq!(changes, [(0, ChangeKind::Updated), (1, ChangeKind::Inserted)]);
+/// ```
+#[cfg(feature = "std")]
+pub struct TrackedHashSlabMap<K, V, S = RandomState> {
+    map: HashSlabMap<K, V, S>,
+    // Indexed by slot, never shrinks
+    stamps: Vec<Option<Stamp>>,
+    epoch: u64,
+}
+
+#[cfg(not(feature = "std"))]
+pub struct TrackedHashSlabMap<K, V, S> {
+    map: HashSlabMap<K, V, S>,
+    stamps: Vec<Option<Stamp>>,
+    epoch: u64,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V> TrackedHashSlabMap<K, V> {
+    /// Creates an empty `TrackedHashSlabMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<K, V, S> TrackedHashSlabMap<K, V, S> {
+    /// Creates an empty `TrackedHashSlabMap` which will use the given hash
+    /// builder.
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            map: HashSlabMap::with_hasher(hash_builder),
+            stamps: Vec::new(),
+            epoch: 0,
+        }
+    }
+
+    /// Creates a `TrackedHashSlabMap` with the content of `map`, with all its
+    /// entries inserted in epoch 0.
+    pub fn from_map(map: HashSlabMap<K, V, S>) -> Self {
+        let mut tracked = Self {
+            map,
+            stamps: Vec::new(),
+            epoch: 0,
+        };
+        let indices: Vec<_> = tracked.map.iter_full().map(|(index, _, _)| index).collect();
+        indices
+            .into_iter()
+            .for_each(|index| tracked.mark_inserted(index));
+        tracked
+    }
+
+    /// Return the current epoch.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Start a new epoch and return it. Later changes are stamped with it.
+    pub fn advance_epoch(&mut self) -> u64 {
+        self.epoch += 1;
+        self.epoch
+    }
+
+    /// An iterator over the indices changed during or after `epoch`, with
+    /// the kind of change, in index order.
+    ///
+    /// An entry inserted since `epoch` is reported as
+    /// [`Inserted`](ChangeKind::Inserted) even if it was modified later, and
+    /// an index whose entry was removed is reported as
+    /// [`Removed`](ChangeKind::Removed) until it is reused.
+    pub fn changes_since(&self, epoch: u64) -> ChangesSince<'_> {
+        ChangesSince {
+            iter: self.stamps.iter().enumerate(),
+            epoch,
+        }
+    }
+
+    /// Returns the epoch of the last change at `index`, if it was ever used.
+    pub fn changed_at(&self, index: usize) -> Option<u64> {
+        self.stamps
+            .get(index)
+            .copied()
+            .flatten()
+            .map(|stamp| stamp.modified)
+    }
+
+    /// Forget all tombstones of removed entries.
+    pub fn forget_removed(&mut self) {
+        self.stamps
+            .iter_mut()
+            .filter(|stamp| stamp.is_some_and(|stamp| stamp.removed))
+            .for_each(|stamp| *stamp = None);
+    }
+
+    /// Return the underlying map.
+    pub fn as_map(&self) -> &HashSlabMap<K, V, S> {
+        &self.map
+    }
+
+    /// Consumes the tracker, returning the map.
+    pub fn into_inner(self) -> HashSlabMap<K, V, S> {
+        self.map
+    }
+
+    /// An iterator visiting all index-key-value triples in arbitrary order,
+    /// with mutable references to the values. Every visited entry is marked
+    /// as updated.
+    pub fn iter_full_mut(&mut self) -> IterFullMut<'_, K, V> {
+        IterFullMut {
+            iter: self.map.iter_full_mut(),
+            stamps: &mut self.stamps,
+            epoch: self.epoch,
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order, with
+    /// mutable references to the values. Every visited entry is marked as
+    /// updated.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            inner: self.iter_full_mut(),
+        }
+    }
+
+    /// Remove all key-value pairs, marking them as removed.
+    pub fn clear(&mut self) {
+        let indices: Vec<_> = self.map.iter_full().map(|(index, _, _)| index).collect();
+        self.map.clear();
+        indices
+            .into_iter()
+            .for_each(|index| self.mark_removed(index));
+    }
+
+    fn mark_inserted(&mut self, index: usize) {
+        if self.stamps.len() <= index {
+            self.stamps.resize(index + 1, None);
+        }
+        self.stamps[index] = Some(Stamp {
+            inserted: self.epoch,
+            modified: self.epoch,
+            removed: false,
+        });
+    }
+
+    fn mark_updated(&mut self, index: usize) {
+        mark_updated(&mut self.stamps, index, self.epoch);
+    }
+
+    fn mark_removed(&mut self, index: usize) {
+        if let Some(Some(stamp)) = self.stamps.get_mut(index) {
+            stamp.modified = self.epoch;
+            stamp.removed = true;
+        }
+    }
+}
+
+fn mark_updated(stamps: &mut [Option<Stamp>], index: usize, epoch: u64) {
+    if let Some(Some(stamp)) = stamps.get_mut(index) {
+        stamp.modified = epoch;
+    }
+}
+
+impl<K, V, S> TrackedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Insert a key-value pair in the map.
+    ///
+    /// If an equivalent key already exists, its value is replaced and the old
+    /// value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Insert a key-value pair in the map, and get its index together with
+    /// the old value if an equivalent key already existed.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        let (index, old) = self.map.insert_full(key, value);
+        if old.is_some() {
+            self.mark_updated(index);
+        } else {
+            self.mark_inserted(index);
+        }
+        (index, old)
+    }
+
+    /// Get a key and a mutable reference to the value by index, marking the
+    /// entry as updated.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)> {
+        if self.map.contains_index(index) {
+            self.mark_updated(index);
+        }
+        self.map.get_index_mut(index)
+    }
+
+    /// Return the index, key and a mutable reference to the value of `key`,
+    /// marking the entry as updated.
+    pub fn get_full_mut<Q>(&mut self, key: &Q) -> Option<(usize, &K, &mut V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.map.get_index_of(key)?;
+        self.get_index_mut(index)
+            .map(|(key, value)| (index, key, value))
+    }
+
+    /// Return a mutable reference to the value of `key`, marking the entry as
+    /// updated.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_full_mut(key).map(|(_, _, value)| value)
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Remove the key-value pair equivalent to `key` and return it and the
+    /// index it had.
+    pub fn remove_full<Q>(&mut self, key: &Q) -> Option<(usize, K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let (index, key, value) = self.map.remove_full(key)?;
+        self.mark_removed(index);
+        Some((index, key, value))
+    }
+
+    /// Remove the key-value pair by index.
+    pub fn remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let removed = self.map.remove_index(index)?;
+        self.mark_removed(index);
+        Some(removed)
+    }
+
+    /// Retains only the key-value pairs specified by the predicate, marking
+    /// the others as removed. The retained ones are left unchanged.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let removed: Vec<_> = self
+            .map
+            .iter_full()
+            .filter(|&(_, key, value)| !f(key, value))
+            .map(|(index, _, _)| index)
+            .collect();
+        for index in removed {
+            self.remove_index(index);
+        }
+    }
+}
+
+impl<K, V, S> Deref for TrackedHashSlabMap<K, V, S> {
+    type Target = HashSlabMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, S> fmt::Debug for TrackedHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedHashSlabMap")
+            .field("map", &self.map.iter_full())
+            .field("epoch", &self.epoch)
+            .finish()
+    }
+}
+
+impl<K, V, S> Default for TrackedHashSlabMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> From<HashSlabMap<K, V, S>> for TrackedHashSlabMap<K, V, S> {
+    fn from(map: HashSlabMap<K, V, S>) -> Self {
+        Self::from_map(map)
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for TrackedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iterable: I) {
+        iterable.into_iter().for_each(|(key, value)| {
+            self.insert(key, value);
+        });
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for TrackedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iterable: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iterable);
+        map
+    }
+}
+
+/// An iterator over the indices changed since an epoch.
+///
+/// This `struct` is created by the [`TrackedHashSlabMap::changes_since`]
+/// method. See its documentation for more.
+#[derive(Clone)]
+pub struct ChangesSince<'a> {
+    iter: core::iter::Enumerate<core::slice::Iter<'a, Option<Stamp>>>,
+    epoch: u64,
+}
+
+impl fmt::Debug for ChangesSince<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl Iterator for ChangesSince<'_> {
+    type Item = (usize, ChangeKind);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let epoch = self.epoch;
+        self.iter.find_map(|(index, stamp)| {
+            let stamp = stamp.filter(|stamp| stamp.modified >= epoch)?;
+            let kind = if stamp.removed {
+                ChangeKind::Removed
+            } else if stamp.inserted >= epoch {
+                ChangeKind::Inserted
+            } else {
+                ChangeKind::Updated
+            };
+            Some((index, kind))
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+impl FusedIterator for ChangesSince<'_> {}
+
+/// A mutable iterator over the entries of a [`TrackedHashSlabMap`].
+///
+/// This `struct` is created by the [`TrackedHashSlabMap::iter_full_mut`]
+/// method. See its documentation for more.
+pub struct IterFullMut<'a, K, V> {
+    iter: map::IterFullMut<'a, K, V>,
+    stamps: &'a mut Vec<Option<Stamp>>,
+    epoch: u64,
+}
+
+impl<'a, K, V> Iterator for IterFullMut<'a, K, V> {
+    type Item = (usize, &'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, key, value) = self.iter.next()?;
+        mark_updated(self.stamps, index, self.epoch);
+        Some((index, key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V> fmt::Debug for IterFullMut<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IterFullMut").finish_non_exhaustive()
+    }
+}
+
+/// A mutable iterator over the key-value pairs of a [`TrackedHashSlabMap`].
+///
+/// This `struct` is created by the [`TrackedHashSlabMap::iter_mut`] method.
+/// See its documentation for more.
+pub struct IterMut<'a, K, V> {
+    inner: IterFullMut<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, key, value)| (key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> fmt::Debug for IterMut<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IterMut").finish_non_exhaustive()
+    }
+}
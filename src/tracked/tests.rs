@@ -0,0 +1,79 @@
+use super::*;
+use std::{vec, vec::Vec};
+
+fn changes(map: &TrackedHashSlabMap<char, u32>, epoch: u64) -> Vec<(usize, ChangeKind)> {
+    map.changes_since(epoch).collect()
+}
+
+#[test]
+fn epochs() {
+    use ChangeKind::*;
+
+    let mut map: TrackedHashSlabMap<char, u32> = ('a'..='e').zip(0..).collect();
+    assert_eq!(map.epoch(), 0);
+    assert_eq!(changes(&map, 0).len(), 5);
+    assert_eq!(map.advance_epoch(), 1);
+    assert!(changes(&map, 1).is_empty());
+
+    map.insert('a', 10);
+    *map.get_index_mut(1).unwrap().1 += 1;
+    map.remove(&'c');
+    assert_eq!(
+        changes(&map, 1),
+        vec![(0, Updated), (1, Updated), (2, Removed)]
+    );
+    assert_eq!(map.changed_at(2), Some(1));
+    assert_eq!(map.changed_at(3), Some(0));
+    assert_eq!(map.changed_at(9), None);
+
+    // A reused index is reported as a new entry
+    assert_eq!(map.advance_epoch(), 2);
+    assert_eq!(map.insert_full('x', 0), (2, None));
+    *map.get_mut(&'x').unwrap() += 1;
+    assert_eq!(changes(&map, 2), vec![(2, Inserted)]);
+    // Older rounds see the latest state of each index
+    assert_eq!(
+        changes(&map, 1),
+        vec![(0, Updated), (1, Updated), (2, Inserted)]
+    );
+    assert_eq!(changes(&map, 0).len(), 5);
+}
+
+#[test]
+fn bulk_operations() {
+    use ChangeKind::*;
+
+    let mut map: TrackedHashSlabMap<char, u32> = ('a'..='d').zip(0..).collect();
+    let round = map.advance_epoch();
+    map.iter_mut()
+        .filter(|(&k, _)| k == 'b')
+        .for_each(|(_, v)| *v += 1);
+    // Every visited value counts as modified
+    assert!(changes(&map, round)
+        .iter()
+        .all(|&(_, kind)| kind == Updated));
+
+    let round = map.advance_epoch();
+    // Only the removals count
+    map.retain(|_, v| *v % 2 == 0);
+    assert_eq!(changes(&map, round), vec![(3, Removed)]);
+
+    let round = map.advance_epoch();
+    map.clear();
+    assert_eq!(
+        changes(&map, round),
+        vec![(0, Removed), (1, Removed), (2, Removed)]
+    );
+    assert_eq!(changes(&map, 0).len(), 4);
+    map.forget_removed();
+    assert!(changes(&map, 0).is_empty());
+
+    let mut plain = HashSlabMap::new();
+    plain.insert('q', 1);
+    let map = TrackedHashSlabMap::from(plain);
+    assert_eq!(
+        map.changes_since(0).collect::<Vec<_>>(),
+        vec![(0, Inserted)]
+    );
+    assert_eq!(map.into_inner().len(), 1);
+}
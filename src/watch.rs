@@ -0,0 +1,555 @@
+//! A shared [`HashSlabMap`] which notifies async tasks about changes
+//!
+//! [`WatchedHashSlabMap`] is a handle to a map behind a mutex, which can be
+//! cloned and moved into tasks. Mutations go through the handle and wake
+//! tasks waiting for a key or an index with
+//! [`wait_for_key`][WatchedHashSlabMap::wait_for_key],
+//! [`wait_for_index`][WatchedHashSlabMap::wait_for_index] and
+//! [`wait_for_change`][WatchedHashSlabMap::wait_for_change], and feed
+//! [`Changes`] streams.
+//!
+//! The futures only rely on [`Waker`]s, so they work with any executor.
+//! With the `futures` feature, [`Changes`] also implements the
+//! [`Stream`](futures_core::Stream) trait.
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    hash::{BuildHasher, Hash, RandomState},
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+    vec::Vec,
+};
+
+use hashbrown::Equivalent;
+use slab::Slab;
+
+use crate::HashSlabMap;
+
+#[cfg(test)]
+mod tests;
+
+/// The kind of change reported by [`Changes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// A new key was inserted.
+    Inserted,
+    /// The value of an existing key was replaced or modified.
+    Updated,
+    /// The key was removed.
+    Removed,
+}
+
+struct Subscriber<K> {
+    queue: VecDeque<(usize, K, ChangeKind)>,
+    waker: Option<Waker>,
+}
+
+struct Inner<K, V, S> {
+    map: HashSlabMap<K, V, S>,
+    // Tasks waiting for a key or an index, one slot per future, woken on
+    // every change
+    waiters: Slab<Option<Waker>>,
+    subscribers: Slab<Subscriber<K>>,
+}
+
+impl<K: Clone, V, S> Inner<K, V, S> {
+    fn notify(&mut self, index: usize, key: &K, kind: ChangeKind) {
+        for (_, subscriber) in &mut self.subscribers {
+            subscriber.queue.push_back((index, key.clone(), kind));
+        }
+    }
+}
+
+// Take the wakers of everyone notified, and wake them after unlocking
+fn wake<K, V, S>(mut inner: MutexGuard<'_, Inner<K, V, S>>) {
+    let Inner {
+        waiters,
+        subscribers,
+        ..
+    } = &mut *inner;
+    let wakers: Vec<_> = waiters
+        .iter_mut()
+        .filter_map(|(_, waker)| waker.take())
+        .chain(
+            subscribers
+                .iter_mut()
+                .filter(|(_, subscriber)| !subscriber.queue.is_empty())
+                .filter_map(|(_, subscriber)| subscriber.waker.take()),
+        )
+        .collect();
+    drop(inner);
+    wakers.into_iter().for_each(Waker::wake);
+}
+
+// Store the waker of a pending poll, replacing the previous one
+fn register(slot: &mut Option<Waker>, cx: &Context<'_>) {
+    match slot {
+        Some(waker) if waker.will_wake(cx.waker()) => {}
+        _ => *slot = Some(cx.waker().clone()),
+    }
+}
+
+/// A handle to a [`HashSlabMap`] shared between tasks, which reports every
+/// change.
+///
+/// Cloning the handle returns another handle to the same map. All methods
+/// take `&self`. Keys are cloned into change events, so they should be cheap
+/// to clone.
+///
+/// # Examples
+///
+/// ```
+/// # use hashslab::{watch::ChangeKind, WatchedHashSlabMap};
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let endpoints = WatchedHashSlabMap::new();
+/// let mut changes = endpoints.changes();
+///
+/// let waiter = tokio::spawn(endpoints.wait_for_key("users"));
+/// tokio::task::yield_now().await;
+/// let (index, _) = endpoints.insert_full("users", "starting");
+/// assert_eq!(waiter.await.unwrap(), index);
+///
+/// let status = endpoints.wait_for_change(index);
+/// endpoints.modify_index(index, |state| *state = "ready");
+/// assert_eq!(status.await, ChangeKind::Updated);
+///
+/// assert_eq!(changes.recv().await, (index, "users", ChangeKind::Inserted));
+/// assert_eq!(changes.recv().await, (index, "users", ChangeKind::Updated));
+/// assert_eq!(endpoints.read(|map| map.get("users").copied()), Some("ready"));
+/// # });
+/// ```
+pub struct WatchedHashSlabMap<K, V, S = RandomState> {
+    inner: Arc<Mutex<Inner<K, V, S>>>,
+}
+
+impl<K, V> WatchedHashSlabMap<K, V> {
+    /// Creates an empty `WatchedHashSlabMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, V, S> WatchedHashSlabMap<K, V, S> {
+    /// Creates an empty `WatchedHashSlabMap` which will use the given hash
+    /// builder.
+    pub fn with_hasher(builder: S) -> Self {
+        Self::from(HashSlabMap::with_hasher(builder))
+    }
+
+    /// Call `f` with a shared reference to the map.
+    ///
+    /// The map is locked while `f` runs, so `f` must not use this handle.
+    pub fn read<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&HashSlabMap<K, V, S>) -> R,
+    {
+        f(&self.lock().map)
+    }
+
+    /// Return the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+
+    /// Returns true if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the map contains an entry at the given index.
+    pub fn contains_index(&self, index: usize) -> bool {
+        self.lock().map.slab.contains(index)
+    }
+
+    /// Return a stream of all changes made from now on.
+    pub fn changes(&self) -> Changes<K, V, S> {
+        let id = self.lock().subscribers.insert(Subscriber {
+            queue: VecDeque::new(),
+            waker: None,
+        });
+        Changes {
+            inner: Arc::clone(&self.inner),
+            id,
+        }
+    }
+
+    /// Wait until the map contains an entry at the given index.
+    pub fn wait_for_index(&self, index: usize) -> WaitForIndex<K, V, S> {
+        WaitForIndex {
+            waiter: self.lock().waiters.insert(None),
+            inner: Arc::clone(&self.inner),
+            index,
+        }
+    }
+
+    /// Wait for the next change of the entry at the given index, including
+    /// its removal or the insertion of a key at a vacant index.
+    pub fn wait_for_change(&self, index: usize) -> WaitForChange<K, V, S> {
+        WaitForChange {
+            changes: self.changes(),
+            index,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<K, V, S>> {
+        lock(&self.inner)
+    }
+}
+
+impl<K, V, S> WatchedHashSlabMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    S: BuildHasher,
+{
+    /// Wait until `key` is in the map, and return its index.
+    pub fn wait_for_key(&self, key: K) -> WaitForKey<K, V, S> {
+        WaitForKey {
+            waiter: self.lock().waiters.insert(None),
+            inner: Arc::clone(&self.inner),
+            key,
+        }
+    }
+
+    /// Return the index of `key`.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.lock().map.get_index_of(key)
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the key is already present, its value is replaced and the old value
+    /// is returned.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Inserts a key-value pair into the map, and returns its index together
+    /// with the old value if the key was present.
+    pub fn insert_full(&self, key: K, value: V) -> (usize, Option<V>) {
+        let mut inner = self.lock();
+        let event_key = key.clone();
+        let (index, old) = inner.map.insert_full(key, value);
+        let kind = match old {
+            Some(_) => ChangeKind::Updated,
+            None => ChangeKind::Inserted,
+        };
+        inner.notify(index, &event_key, kind);
+        wake(inner);
+        (index, old)
+    }
+
+    /// Call `f` with a mutable reference to the value at the given index,
+    /// returning its result.
+    pub fn modify_index<F, R>(&self, index: usize, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        let mut inner = self.lock();
+        let (key, value) = inner.map.get_index_mut(index)?;
+        let result = f(value);
+        let key = key.clone();
+        inner.notify(index, &key, ChangeKind::Updated);
+        wake(inner);
+        Some(result)
+    }
+
+    /// Call `f` with a mutable reference to the value of `key`, returning
+    /// its result.
+    pub fn modify<Q, F, R>(&self, key: &Q, f: F) -> Option<R>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        let mut inner = self.lock();
+        let (index, key, value) = inner.map.get_full_mut(key)?;
+        let result = f(value);
+        let key = key.clone();
+        inner.notify(index, &key, ChangeKind::Updated);
+        wake(inner);
+        Some(result)
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let mut inner = self.lock();
+        let (index, key, value) = inner.map.remove_full(key)?;
+        inner.notify(index, &key, ChangeKind::Removed);
+        wake(inner);
+        Some(value)
+    }
+
+    /// Removes the entry at the given index, returning its key and value.
+    pub fn remove_index(&self, index: usize) -> Option<(K, V)> {
+        let mut inner = self.lock();
+        let (key, value) = inner.map.remove_index(index)?;
+        inner.notify(index, &key, ChangeKind::Removed);
+        wake(inner);
+        Some((key, value))
+    }
+
+    /// Removes all entries, reporting each of them as removed.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        let removed: Vec<_> = inner.map.drain_full().collect();
+        for (index, key, _) in removed {
+            inner.notify(index, &key, ChangeKind::Removed);
+        }
+        wake(inner);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<K, V, S> Clone for WatchedHashSlabMap<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for WatchedHashSlabMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.lock().map.iter()).finish()
+    }
+}
+
+impl<K, V, S> Default for WatchedHashSlabMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> From<HashSlabMap<K, V, S>> for WatchedHashSlabMap<K, V, S> {
+    fn from(map: HashSlabMap<K, V, S>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                map,
+                waiters: Slab::new(),
+                subscribers: Slab::new(),
+            })),
+        }
+    }
+}
+
+/// A stream of the changes of a [`WatchedHashSlabMap`].
+///
+/// Every change is reported as an `(index, key, kind)` triple, in the order
+/// the changes were made. Changes are buffered until they are received, and
+/// the stream never ends.
+///
+/// This `struct` is created by the [`WatchedHashSlabMap::changes`] method.
+/// See its documentation for more.
+pub struct Changes<K, V, S = RandomState> {
+    inner: Arc<Mutex<Inner<K, V, S>>>,
+    id: usize,
+}
+
+impl<K, V, S> Changes<K, V, S> {
+    /// Receive the next change, waiting for it if none is buffered.
+    pub fn recv(&mut self) -> Recv<'_, K, V, S> {
+        Recv { changes: self }
+    }
+
+    /// Receive the next change if one is buffered.
+    pub fn try_recv(&mut self) -> Option<(usize, K, ChangeKind)> {
+        lock(&self.inner).subscribers[self.id].queue.pop_front()
+    }
+
+    /// Return the number of buffered changes.
+    pub fn len(&self) -> usize {
+        lock(&self.inner).subscribers[self.id].queue.len()
+    }
+
+    /// Returns true if no change is buffered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<(usize, K, ChangeKind)> {
+        let mut inner = lock(&self.inner);
+        let subscriber = &mut inner.subscribers[self.id];
+        match subscriber.queue.pop_front() {
+            Some(change) => Poll::Ready(change),
+            None => {
+                register(&mut subscriber.waker, cx);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<K, V, S> Drop for Changes<K, V, S> {
+    fn drop(&mut self) {
+        lock(&self.inner).subscribers.remove(self.id);
+    }
+}
+
+impl<K: fmt::Debug, V, S> fmt::Debug for Changes<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(&lock(&self.inner).subscribers[self.id].queue)
+            .finish()
+    }
+}
+
+#[cfg(feature = "futures")]
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+impl<K, V, S> futures_core::Stream for Changes<K, V, S> {
+    type Item = (usize, K, ChangeKind);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_recv(cx).map(Some)
+    }
+}
+
+/// A future receiving the next change from [`Changes`].
+///
+/// This `struct` is created by the [`Changes::recv`] method.
+/// See its documentation for more.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Recv<'a, K, V, S> {
+    changes: &'a mut Changes<K, V, S>,
+}
+
+impl<K, V, S> Future for Recv<'_, K, V, S> {
+    type Output = (usize, K, ChangeKind);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().changes.poll_recv(cx)
+    }
+}
+
+/// A future resolving to the index of a key once it is in the map.
+///
+/// This `struct` is created by the [`WatchedHashSlabMap::wait_for_key`]
+/// method. See its documentation for more.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitForKey<K, V, S = RandomState> {
+    inner: Arc<Mutex<Inner<K, V, S>>>,
+    waiter: usize,
+    key: K,
+}
+
+impl<K, V, S> Future for WaitForKey<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Output = usize;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let mut inner = lock(&self.inner);
+        match inner.map.get_index_of(&self.key) {
+            Some(index) => Poll::Ready(index),
+            None => {
+                register(&mut inner.waiters[self.waiter], cx);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<K, V, S> Drop for WaitForKey<K, V, S> {
+    fn drop(&mut self) {
+        lock(&self.inner).waiters.remove(self.waiter);
+    }
+}
+
+impl<K: fmt::Debug, V, S> fmt::Debug for WaitForKey<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitForKey")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// A future resolving once the map contains an entry at an index.
+///
+/// This `struct` is created by the [`WatchedHashSlabMap::wait_for_index`]
+/// method. See its documentation for more.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitForIndex<K, V, S = RandomState> {
+    inner: Arc<Mutex<Inner<K, V, S>>>,
+    waiter: usize,
+    index: usize,
+}
+
+impl<K, V, S> Future for WaitForIndex<K, V, S> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = lock(&self.inner);
+        if inner.map.slab.contains(self.index) {
+            Poll::Ready(())
+        } else {
+            register(&mut inner.waiters[self.waiter], cx);
+            Poll::Pending
+        }
+    }
+}
+
+impl<K, V, S> Drop for WaitForIndex<K, V, S> {
+    fn drop(&mut self) {
+        lock(&self.inner).waiters.remove(self.waiter);
+    }
+}
+
+impl<K, V, S> fmt::Debug for WaitForIndex<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitForIndex")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// A future resolving to the kind of the next change of an entry.
+///
+/// This `struct` is created by the [`WatchedHashSlabMap::wait_for_change`]
+/// method. See its documentation for more.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitForChange<K, V, S = RandomState> {
+    changes: Changes<K, V, S>,
+    index: usize,
+}
+
+impl<K, V, S> Future for WaitForChange<K, V, S> {
+    type Output = ChangeKind;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<ChangeKind> {
+        let this = self.get_mut();
+        loop {
+            match this.changes.poll_recv(cx) {
+                Poll::Ready((index, _, kind)) if index == this.index => return Poll::Ready(kind),
+                Poll::Ready(_) => continue,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for WaitForChange<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitForChange")
+            .field("index", &self.index)
+            .finish()
+    }
+}
@@ -0,0 +1,154 @@
+use super::*;
+use std::{
+    string::{String, ToString},
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Wake, Waker},
+};
+
+fn poll<F: Future + Unpin>(future: &mut F) -> Poll<F::Output> {
+    Pin::new(future).poll(&mut Context::from_waker(Waker::noop()))
+}
+
+#[test]
+fn waiters() {
+    let map = WatchedHashSlabMap::new();
+    let mut key = map.wait_for_key("b".to_string());
+    let mut index = map.wait_for_index(1);
+    assert_eq!(poll(&mut key), Poll::Pending);
+    assert_eq!(poll(&mut index), Poll::Pending);
+
+    map.insert("a".to_string(), 1);
+    assert_eq!(poll(&mut key), Poll::Pending);
+    map.insert("b".to_string(), 2);
+    assert_eq!(poll(&mut key), Poll::Ready(1));
+    assert_eq!(poll(&mut index), Poll::Ready(()));
+
+    let mut change = map.wait_for_change(0);
+    assert_eq!(poll(&mut change), Poll::Pending);
+    assert_eq!(map.modify("b", |value| *value += 1), Some(()));
+    assert_eq!(poll(&mut change), Poll::Pending);
+    map.remove_index(0);
+    assert_eq!(poll(&mut change), Poll::Ready(ChangeKind::Removed));
+    assert_eq!(map.read(|map| map.get("b").copied()), Some(3));
+}
+
+// Counts wakes, and reads the map when woken, which would deadlock if the
+// map was still locked
+struct Reader {
+    map: WatchedHashSlabMap<&'static str, i32>,
+    wakes: AtomicUsize,
+}
+
+impl Wake for Reader {
+    fn wake(self: Arc<Self>) {
+        self.map.len();
+        self.wakes.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn one_waker_per_future() {
+    let map = WatchedHashSlabMap::new();
+    let reader = Arc::new(Reader {
+        map: map.clone(),
+        wakes: AtomicUsize::new(0),
+    });
+    let waker = Waker::from(Arc::clone(&reader));
+    let mut cx = Context::from_waker(&waker);
+
+    let mut key = map.wait_for_key("b");
+    let mut changes = map.changes();
+    for _ in 0..10 {
+        assert!(Pin::new(&mut key).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut changes.recv()).poll(&mut cx).is_pending());
+    }
+    let registered = |map: &WatchedHashSlabMap<_, _>| {
+        map.lock()
+            .waiters
+            .iter()
+            .filter(|(_, waker)| waker.is_some())
+            .count()
+    };
+    assert_eq!(registered(&map), 1);
+
+    map.insert("a", 1);
+    assert_eq!(reader.wakes.load(Ordering::SeqCst), 2);
+    assert_eq!(registered(&map), 0);
+    drop(key);
+    assert!(map.lock().waiters.is_empty());
+}
+
+#[test]
+fn changes_in_order() {
+    let map = WatchedHashSlabMap::new();
+    let mut changes = map.changes();
+    assert_eq!(poll(&mut changes.recv()), Poll::Pending);
+
+    map.insert_full(String::from("a"), 1);
+    map.insert_full(String::from("b"), 2);
+    map.insert(String::from("a"), 3);
+    assert_eq!(map.modify_index(1, |value| *value * 10), Some(20));
+    assert_eq!(map.modify_index(5, |value| *value * 10), None);
+    assert_eq!(map.remove("a"), Some(3));
+    map.clear();
+    assert!(map.is_empty());
+
+    let expected = [
+        (0, "a", ChangeKind::Inserted),
+        (1, "b", ChangeKind::Inserted),
+        (0, "a", ChangeKind::Updated),
+        (1, "b", ChangeKind::Updated),
+        (0, "a", ChangeKind::Removed),
+        (1, "b", ChangeKind::Removed),
+    ];
+    assert_eq!(changes.len(), expected.len());
+    for (index, key, kind) in expected {
+        assert_eq!(
+            poll(&mut changes.recv()),
+            Poll::Ready((index, key.to_string(), kind))
+        );
+    }
+    assert_eq!(changes.try_recv(), None);
+}
+
+#[test]
+fn tasks_on_local_executor() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let local = tokio::task::LocalSet::new();
+    local.block_on(&runtime, async {
+        let map = WatchedHashSlabMap::new();
+        let mut changes = map.changes();
+        let waiter = tokio::task::spawn_local(map.wait_for_key("ready"));
+        let writer = tokio::task::spawn_local({
+            let map = map.clone();
+            async move {
+                for key in ["starting", "ready"] {
+                    tokio::task::yield_now().await;
+                    map.insert(key, ());
+                }
+            }
+        });
+        assert_eq!(waiter.await.unwrap(), 1);
+        writer.await.unwrap();
+        assert_eq!(changes.recv().await, (0, "starting", ChangeKind::Inserted));
+        assert_eq!(changes.recv().await, (1, "ready", ChangeKind::Inserted));
+    });
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn stream() {
+    use futures_core::Stream;
+
+    let map = WatchedHashSlabMap::new();
+    let mut changes = map.changes();
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(Pin::new(&mut changes).poll_next(&mut cx), Poll::Pending);
+    map.insert(1, 'a');
+    assert_eq!(
+        Pin::new(&mut changes).poll_next(&mut cx),
+        Poll::Ready(Some((0, 1, ChangeKind::Inserted)))
+    );
+}
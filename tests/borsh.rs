@@ -0,0 +1,74 @@
+#![cfg(feature = "borsh")]
+
+use borsh::{from_slice, to_vec};
+use fnv::FnvBuildHasher;
+use hashslab::{HashSlabMap, HashSlabSet};
+
+type Map = HashSlabMap<String, Vec<u16>, FnvBuildHasher>;
+type Set = HashSlabSet<u64, FnvBuildHasher>;
+
+#[test]
+fn map_round_trip() {
+    let mut map: Map = (0..40u16).map(|i| (i.to_string(), vec![i; 3])).collect();
+    map.retain(|_, v| v[0] % 3 != 0);
+    let bytes = to_vec(&map).unwrap();
+    let mut copy: Map = from_slice(&bytes).unwrap();
+
+    let mut expected: Vec<_> = map.iter_full().collect();
+    let mut found: Vec<_> = copy.iter_full().collect();
+    expected.sort_unstable();
+    found.sort_unstable();
+    assert_eq!(found, expected);
+    assert_eq!(copy.insert_full("x".into(), vec![]), (36, None));
+
+    // The encoding is deterministic
+    assert_eq!(to_vec(&from_slice::<Map>(&bytes).unwrap()).unwrap(), bytes);
+}
+
+#[test]
+fn set_round_trip_and_errors() {
+    let mut set: Set = (0..20).collect();
+    set.remove(&7);
+    let bytes = to_vec(&set).unwrap();
+    let copy: Set = from_slice(&bytes).unwrap();
+    assert_eq!(copy.len(), 19);
+    assert_eq!(copy.get_index_of(&19), Some(19));
+    assert!(!copy.contains(&7));
+
+    assert!(from_slice::<Set>(&bytes[..bytes.len() - 1]).is_err());
+    // Two entries at index 0
+    let duplicate = to_vec(&(2u32, (0u64, 1u64), (0u64, 2u64))).unwrap();
+    assert!(from_slice::<Set>(&duplicate).is_err());
+}
+
+#[test]
+fn index_bound_round_trip() {
+    // Two entries may leave up to 65538 vacant indices below the highest one
+    let sparse = |highest: u64| -> Set {
+        let mut set: Set = (0..=highest).collect();
+        set.retain(|&value| value == 0 || value == highest);
+        set
+    };
+    let set = sparse(65_539);
+    let copy: Set = from_slice(&to_vec(&set).unwrap()).unwrap();
+    assert_eq!(copy.get_index_of(&65_539), Some(65_539));
+
+    // One more is rejected when writing
+    assert!(to_vec(&sparse(65_540)).is_err());
+}
+
+#[test]
+fn rejects_hostile_indices() {
+    // A single entry at a huge index would need a huge slab
+    let far = to_vec(&(1u32, (1u64 << 40, 1u64))).unwrap();
+    assert!(from_slice::<Set>(&far).is_err());
+    let far = to_vec(&(1u32, (u64::MAX, "a", vec![1u16]))).unwrap();
+    assert!(from_slice::<Map>(&far).is_err());
+
+    // Sparse maps within the bound are fine
+    let sparse = to_vec(&(2u32, (3u64, 1u64), (65_539u64, 2u64))).unwrap();
+    let set: Set = from_slice(&sparse).unwrap();
+    assert_eq!(set.get_index_of(&2), Some(65_539));
+    let sparse = to_vec(&(2u32, (3u64, 1u64), (65_540u64, 2u64))).unwrap();
+    assert!(from_slice::<Set>(&sparse).is_err());
+}
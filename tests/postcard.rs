@@ -0,0 +1,85 @@
+#![cfg(feature = "serde")]
+
+use fnv::FnvBuildHasher;
+use hashslab::{HashSlabMap, HashSlabSet};
+
+type Map = HashSlabMap<u32, String, FnvBuildHasher>;
+type Set = HashSlabSet<(u8, u8), FnvBuildHasher>;
+
+fn sorted(map: &Map) -> Vec<(usize, u32, String)> {
+    let mut entries: Vec<_> = map
+        .iter_full()
+        .map(|(i, k, v)| (i, *k, v.clone()))
+        .collect();
+    entries.sort_unstable();
+    entries
+}
+
+#[test]
+fn map_round_trip() {
+    let mut map: Map = (0..50).map(|i| (i, i.to_string())).collect();
+    map.retain(|k, _| k % 4 != 1);
+    let bytes = postcard::to_allocvec(&map).unwrap();
+    let mut copy: Map = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(sorted(&copy), sorted(&map));
+
+    // The highest vacant index is reused first
+    assert_eq!(copy.insert_full(100, "a".into()), (45, None));
+    assert_eq!(copy.insert_full(101, "b".into()), (41, None));
+}
+
+#[test]
+fn set_round_trip() {
+    let mut set: Set = (0..10).map(|i| (i, i * 2)).collect();
+    set.remove(&(3, 6));
+    let bytes = postcard::to_allocvec(&set).unwrap();
+    let copy: Set = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(copy.len(), 9);
+    assert_eq!(copy.get_index_of(&(9, 18)), Some(9));
+    assert_eq!(copy.get_index(3), None);
+}
+
+#[test]
+fn rejects_duplicates() {
+    let entries = [(0usize, 1u32, "a"), (1, 1, "b")];
+    let bytes = postcard::to_allocvec(&entries[..]).unwrap();
+    assert!(postcard::from_bytes::<Map>(&bytes).is_err());
+
+    let entries = [(3usize, 1u32, "a"), (3, 2, "b")];
+    let bytes = postcard::to_allocvec(&entries[..]).unwrap();
+    assert!(postcard::from_bytes::<Map>(&bytes).is_err());
+}
+
+#[test]
+fn index_bound_round_trip() {
+    // Two entries may leave up to 65538 vacant indices below the highest one
+    let sparse = |highest: u32| -> Map {
+        let mut map: Map = (0..=highest).map(|i| (i, String::new())).collect();
+        map.retain(|&k, _| k == 0 || k == highest);
+        map
+    };
+    let map = sparse(65_539);
+    let bytes = postcard::to_allocvec(&map).unwrap();
+    let copy: Map = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(sorted(&copy), sorted(&map));
+
+    // One more is rejected when writing
+    assert!(postcard::to_allocvec(&sparse(65_540)).is_err());
+}
+
+#[test]
+fn rejects_hostile_indices() {
+    // A single entry at a huge index would need a huge slab
+    let entries = [(1usize << 40, 1u32, "a")];
+    let bytes = postcard::to_allocvec(&entries[..]).unwrap();
+    assert!(postcard::from_bytes::<Map>(&bytes).is_err());
+    let entries = [(usize::MAX, (0u8, 0u8))];
+    let bytes = postcard::to_allocvec(&entries[..]).unwrap();
+    assert!(postcard::from_bytes::<Set>(&bytes).is_err());
+
+    // Sparse maps within the bound are fine
+    let entries = [(0usize, 1u32, "a"), (65_537, 2, "b")];
+    let bytes = postcard::to_allocvec(&entries[..]).unwrap();
+    let map: Map = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(map.get_index_of(&2), Some(65_537));
+}
@@ -52,7 +52,7 @@ macro_rules! quickcheck_limit {
                     let mut quickcheck = QuickCheck::new();
                     if cfg!(miri) {
                         quickcheck = quickcheck
-                            .gen(Gen::new(10))
+                            .rng(Gen::new(10))
                             .tests(10)
                             .max_tests(100);
                     }